@@ -1,5 +1,14 @@
-use crate::network::{WifiDeviceInfo, WifiInfo};
+use crate::config::{ActiveEnterAction, Config};
+use crate::keymap::Action;
+use crate::network::{
+  Band, ConnectionError, EapMethod, MacAddressPolicy, SettingComparison, WifiDeviceInfo, WifiInfo, quality_score,
+  uses_wpa_psk, validate_psk,
+};
+use crate::qr::{self, QrCode};
+use crate::state::PersistentState;
 use ratatui::widgets::ListState;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
 use throbber_widgets_tui::ThrobberState;
 use tui_input::Input;
 
@@ -9,20 +18,47 @@ pub enum Msg {
   Quit,
   MoveUp,
   MoveDown,
+  /// Focus the first network (vim's gg)
+  JumpTop,
+  /// Focus the last network (vim's G)
+  JumpBottom,
+  /// The network thread started fetching the network list
+  ScanStarted,
   NetworksFound(Vec<WifiInfo>),
+  ScanFailure(anyhow::Error),
+  /// The first scan came back empty and is being retried, in case the device just hasn't seen any access points yet
+  ScanRetrying,
   DeviceInfoUpdate(WifiDeviceInfo),
   DismissError,
   EnterInput,
+  /// Like `EnterInput`, but the connection gets autoconnect turned off
+  ConnectOnce,
   Input(char),
+  Paste(String),
+  /// Reading the clipboard for Ctrl+V failed
+  PasteFailure(anyhow::Error),
   Backspace,
   MoveCursorLeft,
   MoveCursorRight,
   MoveCursorWordLeft,
   MoveCursorWordRight,
   DeletePrevWord,
+  /// Move to the next field of a form with several, ie. the enterprise login
+  NextField,
+  CycleEapMethod,
+  /// Reveal or mask the password being typed
+  ToggleShowPassword,
+  /// Cycle the MAC address a new profile will use
+  CycleMacAddress,
   SubmitConnection,
+  /// Like `SubmitConnection`, but the new profile gets autoconnect turned on explicitly
+  SubmitWithAutoconnect,
   CancelInput,
   ConnectionSuccess,
+  /// Connecting failed for a reason other than the password, trying again with this attempt number
+  ConnectionRetry(u32),
+  /// Call off the connection attempt in progress
+  CancelConnect,
   ConnectionFailure(anyhow::Error),
   SubmitDisconnect,
   DisconnectSuccess,
@@ -31,10 +67,91 @@ pub enum Msg {
   SubmitForget,
   ForgetSuccess,
   ForgetFailure(anyhow::Error),
-  DPressed,
+  /// Forget every saved network except the connected one, after confirming
+  ForgetAllExceptActive,
+  /// The saved networks (SSIDs as broadcast) that forgetting all would remove, and the connected network it keeps
+  ConfirmForgetAll(Vec<Vec<u8>>, Option<String>),
+  /// Forgetting several saved networks at once removed this many
+  ForgotMany(usize),
+  /// Forget the focused network without asking, when `fast_forget` is on
+  ForgetNow,
+  /// A network was forgotten and its profile kept so that it can be restored
+  ForgottenWithUndo(String),
+  UndoForget,
+  ForgetUndone(String),
+  /// Forget the connected network and ask for its password again, eg. after the router's password changed
+  ResetPassword,
+  /// Change the focused known network's saved password, keeping the profile
+  EditPassword,
+  PasswordUpdated(String), // SSID
+  ExportProfile,
+  /// Answer to whether the exported profile should include the password
+  SubmitExportProfile(bool),
+  ProfileExported(String, String), // SSID, keyfile contents
+  ProfileExportFailure(anyhow::Error),
+  /// Copy the connected network's password to the clipboard, after asking
+  CopyPassword,
+  SubmitCopyPassword,
+  PasswordCopied(String), // SSID
+  PasswordCopyFailure(anyhow::Error),
+  /// Show a QR code for joining the focused known network
+  ShowQrCode,
+  SharedPassword(String, Option<String>), // SSID, saved password
+  ShareFailure(anyhow::Error),
+  /// The profile of this SSID was forgotten by `ResetPassword`, so it's time to ask for the new password
+  PasswordForgotten(String),
+  ToggleDetails,
+  ToggleSelection,
+  ToggleIgnore,
+  ToggleShowIgnored,
+  RaiseSignalFilter,
+  LowerSignalFilter,
+  /// Show all bands, or only 2.4, 5 or 6 GHz networks
+  CycleBandFilter,
   ToggleAutoconnect,
   AutoconnectSuccess,
   AutoconnectFailure(anyhow::Error),
+  CycleIp4Method,
+  CycleIp6Privacy,
+  TogglePowersave,
+  ToggleNeverDefault,
+  SettingsUpdateSuccess(String), // SSID
+  SettingsUpdateFailure(anyhow::Error),
+  SubmitReapply,
+  ToggleScanOnly,
+  SubmitNewProfile,
+  ToggleSignalScanner,
+  CompareSettings,
+  SettingsCompared(String, Vec<SettingComparison>), // SSID, comparison
+  CompareSettingsFailure(anyhow::Error),
+  CaptivePortalDetected(String), // SSID
+  OpenPortalLogin,
+  ToggleRefreshPause,
+  /// Rescan right now, eg. with automatic refreshing turned off
+  Refresh,
+  PickZone,
+  SubmitZone,
+  ExportList,
+  CycleSortMode,
+  ToggleWifi,
+  /// Confirmed turning WiFi off while connected
+  SubmitRadioOff,
+  /// NetworkManager turned the WiFi radio on (true) or off (false) as asked
+  WifiToggled(bool),
+  ToggleConnectivityCheck,
+  /// Turn autoconnecting on the WiFi device as a whole on or off
+  ToggleDeviceAutoconnect,
+  WifiToggleFailure(anyhow::Error),
+  /// Switch scans and connections to the next WiFi adapter
+  CycleWifiDevice,
+  SelectDeviceFailure(anyhow::Error),
+  /// Known networks whose autoconnect is currently off because of `min_autoconnect_signal`
+  AutoconnectGuarded(BTreeSet<String>),
+  AutoconnectGuardFailure(anyhow::Error),
+  ShowHelp,
+  ShowDeviceInfo,
+  ShowOnboarding,
+  DismissHelp,
 }
 
 /// Represents the different modal states of the application.
@@ -44,27 +161,184 @@ pub enum Msg {
 pub enum AppState {
   /// Normal browsing mode - user can navigate the network list
   Normal,
-  /// Editing password for a network connection
+  /// Editing password for a network connection. The password is masked unless `show_password`. `mac_address` is
+  /// what the new profile will use, starting from the configured default. With `update`, the password replaces the
+  /// one saved in the network's existing profile instead.
   EditingPassword {
     network: WifiInfo,
     password_input: Input,
+    show_password: bool,
+    mac_address: Option<MacAddressPolicy>,
+    update: bool,
+  },
+  /// Entering the login for an 802.1X (WPA-Enterprise) network. `mac_address` is the configured default for the new
+  /// profile, like with `EditingPassword`.
+  EditingEnterprise {
+    network: WifiInfo,
+    method: EapMethod,
+    identity_input: Input,
+    password_input: Input,
+    field: EnterpriseField,
+    show_password: bool,
+    mac_address: Option<MacAddressPolicy>,
   },
   /// Currently connecting to a network
   Connecting {
     network: WifiInfo,
     throbber_state: ThrobberState,
+    /// Counting from 1, goes up as transient failures are retried
+    attempt: u32,
   },
   /// Displaying an error message
-  ShowingError { error: anyhow::Error },
+  ShowingError { error: anyhow::Error, fade: u8 },
+  /// Displaying a notice that needs no decision, eg. where a file was written
+  ShowingInfo { message: String },
+  /// Key shortcut overview. `first_run` adds an introduction and records that onboarding happened when dismissed.
+  ShowingHelp { first_run: bool },
+  /// What the WiFi adapter supports, read live from the latest device info
+  ShowingDeviceInfo,
+  /// Showing saved vs applied settings of a connection
+  ShowingSettingsComparison {
+    ssid: String,
+    comparison: Vec<SettingComparison>,
+  },
   /// Confirming disconnect from active network
   ConfirmDisconnect { network: WifiInfo },
+  /// Confirming turning WiFi off, which drops the connection to `network`
+  ConfirmRadioOff { network: WifiInfo },
   /// Confirming forgetting a known network
-  ConfirmForget { network: WifiInfo },
+  ConfirmForget { network: WifiInfo, fade: u8 },
+  /// Confirming forgetting all multi-selected known networks, with their SSIDs as broadcast to tell NetworkManager
+  ConfirmBatchForget {
+    ssids: Vec<String>,
+    ssid_bytes: Vec<Vec<u8>>,
+  },
+  /// Confirming forgetting every saved network in `ssids` (as broadcast), which is all of them except `keep`
+  ConfirmForgetAll { ssids: Vec<Vec<u8>>, keep: Option<String> },
+  /// Offering to reconnect so that edited settings take effect on the active connection
+  ConfirmReapply { network: WifiInfo },
   /// Confirming connection to a network with weak/no security
   ConfirmWeakSecurity { network: WifiInfo },
+  /// Confirming connection to a secure network without a saved profile, when `confirm_new_connections` is set
+  ConfirmNewNetwork { network: WifiInfo },
+  /// Choosing a firewalld zone for a known network. `selected` indexes into `ZONE_CHOICES`.
+  PickingZone { network: WifiInfo, selected: usize },
+  /// Asking whether a known network's profile should be exported with its password
+  ConfirmExportSecrets { network: WifiInfo },
+  /// A QR code that phones can scan to join `ssid`
+  ShowingQrCode { ssid: String, code: QrCode },
+  /// Asking whether to copy the connected network's password to the clipboard
+  ConfirmRevealPassword { network: WifiInfo },
+  /// Connected, but traffic is held back by a captive portal until the user logs in
+  CaptivePortal { ssid: String },
+  /// Summarizing the profile NetworkManager will save before connecting to an unknown network
+  ConfirmNewProfile {
+    network: WifiInfo,
+    password: String,
+    mac_address: Option<MacAddressPolicy>,
+  },
+}
+
+/// Zones offered in the zone picker, `None` being firewalld's default zone. These are the common zones that firewalld
+/// ships with.
+pub const ZONE_CHOICES: &[Option<&str>] = &[None, Some("public"), Some("home"), Some("work"), Some("trusted")];
+
+/// How many ticks dialogs take to fade in
+pub const DIALOG_FADE_TICKS: u8 = 3;
+
+/// Weight of the newest scan in the smoothed signal strength. Lower is smoother but slower to react.
+const SIGNAL_SMOOTHING: f32 = 0.3;
+
+/// Number of scans of signal history kept per network for the signal scanner
+pub const SIGNAL_HISTORY_LEN: usize = 300;
+
+/// How much `[` and `]` change the minimum signal filter by
+const SIGNAL_FILTER_STEP: u8 = 10;
+
+/// How long a footer status message stays up
+pub const STATUS_DURATION: Duration = Duration::from_secs(8);
+
+/// j/k presses closer together than this count as holding the key down
+const NAVIGATION_REPEAT_WINDOW: Duration = Duration::from_millis(120);
+/// Held-down j/k moves one more row per step after this many repeats...
+const NAVIGATION_ACCELERATION_EVERY: u32 = 8;
+/// ...up to this many rows per step
+const NAVIGATION_MAX_STEP: usize = 5;
+
+/// An operation on a network that's been sent to NetworkManager but hasn't reported back yet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PendingOp {
+  Connecting,
+  Forgetting,
+}
+
+impl PendingOp {
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Connecting => "connecting…",
+      Self::Forgetting => "forgetting…",
+    }
+  }
+}
+
+/// Orders for the network list, cycled with `o`. The connected network always comes first.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortMode {
+  /// Strongest first
+  #[default]
+  SignalDesc,
+  /// Highest `quality_score` first
+  Quality,
+  /// By SSID, ignoring case
+  Alpha,
+  /// Fastest band first, strongest first within each
+  Band,
+  /// Saved networks first, strongest first within each group
+  Known,
+}
+
+impl SortMode {
+  pub fn next(self) -> Self {
+    match self {
+      Self::SignalDesc => Self::Quality,
+      Self::Quality => Self::Alpha,
+      Self::Alpha => Self::Band,
+      Self::Band => Self::Known,
+      Self::Known => Self::SignalDesc,
+    }
+  }
+
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::SignalDesc => "signal",
+      Self::Quality => "quality",
+      Self::Alpha => "name",
+      Self::Band => "band",
+      Self::Known => "known first",
+    }
+  }
+}
+
+/// User-controlled filters applied to the scan results before they are displayed
+#[derive(Debug, Default)]
+pub struct ListFilter {
+  /// Whether ignored networks are revealed so they can be un-ignored
+  pub show_ignored: bool,
+  /// Networks with a weaker signal (in percent) are hidden
+  pub min_signal: u8,
+  /// How networks are ordered after the connected one
+  pub sort_mode: SortMode,
+  /// Put known networks in autoconnect priority order ahead of unknown ones, set while the detail view is open
+  pub sort_by_priority: bool,
+  /// Only the first this many networks in the current order are shown
+  pub max_networks: Option<usize>,
+  /// Only networks in this band are shown. Those whose band is unknown show up only without a band filter.
+  pub band: Option<Band>,
 }
 
 // TODO: there are still some type-driven design style refactors due here
+// There is only ever one App, so the size difference between variants doesn't matter.
+#[allow(clippy::large_enum_variant)]
 pub enum App {
   Running {
     networks: Vec<WifiInfo>,
@@ -72,12 +346,113 @@ pub enum App {
     device_info: Option<WifiDeviceInfo>,
     state: AppState,
     show_detailed_view: bool,
+    /// SSIDs of known networks marked for batch operations
+    selected_ssids: HashSet<String>,
+    filter: ListFilter,
+    /// Survey mode: networks are still scanned but connecting is refused
+    scan_only: bool,
+    /// Whether the signal scanner replaces the network list
+    show_signal_scanner: bool,
+    /// Holds the displayed data still by skipping the periodic rescans
+    refresh_paused: bool,
+    /// Exponential moving average of each network's signal strength, by SSID
+    smoothed_strength: HashMap<String, f32>,
+    /// Recent signal strengths per SSID, oldest first. Scans where a network wasn't seen count as 0.
+    signal_history: HashMap<String, VecDeque<u8>>,
+    /// The active network and when a scan first showed it active. NetworkManager doesn't tell how long a connection has
+    /// been up, and scans rebuild the network list, so this is kept across them until the active network changes.
+    connected_since: Option<(String, Instant)>,
+    /// Why connecting to each network failed the last time, until it connects. Kept for the detail view after the
+    /// error dialog is gone.
+    last_errors: HashMap<String, String>,
+    /// Networks whose connection attempt was cancelled but hasn't reported back yet, oldest first. The network thread
+    /// reports every attempt in order, so the next outcome to arrive belongs to the first of these.
+    abandoned_connects: VecDeque<String>,
+    /// When the user last moved through the list with j/k
+    last_navigation: Option<Instant>,
+    /// How many j/k presses in a row came in quick succession, which speeds up scrolling
+    navigation_repeats: u32,
+    /// The latest scan result, held back while the user is navigating
+    pending_networks: Option<Vec<WifiInfo>>,
+    /// SSIDs with autoconnect turned off because their signal is too weak
+    autoconnect_guarded: BTreeSet<String>,
+    /// Autoconnect setting for the connection being set up: off when started with Alt+Enter, so NetworkManager won't
+    /// latch onto the network later, or explicitly on when the password was submitted with Ctrl+Enter. `None` leaves it
+    /// to the profile.
+    autoconnect_choice: Option<bool>,
+    /// In-flight operations by SSID, so the list shows them before the rescan that reflects their outcome
+    pending_ops: HashMap<String, PendingOp>,
+    /// A short-lived message for the footer and when it was set, eg. to confirm a forget that wasn't asked about
+    status: Option<(String, Instant)>,
+    /// The network whose forgetting can still be undone
+    undoable_forget: Option<String>,
+    /// The active network being forgotten. Once a scan shows it's gone, the selection moves to the next best network
+    /// rather than staying on it wherever it sorts to now.
+    refocus_after_forget: Option<String>,
+    /// Why the latest scan failed, cleared by the next successful one. Shown in the header rather than a dialog
+    /// since scans repeat every second.
+    scan_error: Option<String>,
+    /// Whether an empty first scan is being retried, until a scan result or failure comes in
+    scanning: bool,
+    /// When the scan in progress started, so that a slow one can be pointed out
+    scan_started: Option<Instant>,
+    config: Config,
+    persistent: PersistentState,
   },
   ShouldQuit,
 }
 
+/// The text fields of the enterprise login
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnterpriseField {
+  Identity,
+  Password,
+}
+
+impl AppState {
+  /// Asks for whatever an unknown network needs to connect: a login for enterprise networks, a password otherwise.
+  fn credentials_prompt(network: WifiInfo, mac_address: Option<MacAddressPolicy>) -> Self {
+    if network.security.contains("Ent") {
+      Self::EditingEnterprise {
+        network,
+        method: EapMethod::default(),
+        identity_input: Input::default(),
+        password_input: Input::default(),
+        field: EnterpriseField::Identity,
+        show_password: false,
+        mac_address,
+      }
+    } else {
+      Self::EditingPassword {
+        network,
+        password_input: Input::default(),
+        show_password: false,
+        mac_address,
+        update: false,
+      }
+    }
+  }
+
+  /// The text field keystrokes go to, if any
+  fn active_input(&mut self) -> Option<&mut Input> {
+    match self {
+      Self::EditingPassword { password_input, .. } => Some(password_input),
+      Self::EditingEnterprise {
+        identity_input,
+        password_input,
+        field,
+        ..
+      } => Some(match field {
+        EnterpriseField::Identity => identity_input,
+        EnterpriseField::Password => password_input,
+      }),
+      _ => None,
+    }
+  }
+}
+
 impl App {
-  pub fn new() -> Self {
+  pub fn new(config: Config, persistent: PersistentState) -> Self {
     let mut list_state = ListState::default();
     list_state.select(Some(0));
     Self::Running {
@@ -86,15 +461,106 @@ impl App {
       device_info: None,
       state: AppState::Normal,
       show_detailed_view: false,
+      selected_ssids: HashSet::new(),
+      filter: ListFilter {
+        max_networks: config.max_networks,
+        ..ListFilter::default()
+      },
+      scan_only: false,
+      show_signal_scanner: false,
+      refresh_paused: false,
+      smoothed_strength: HashMap::new(),
+      signal_history: HashMap::new(),
+      connected_since: None,
+      last_errors: HashMap::new(),
+      abandoned_connects: VecDeque::new(),
+      last_navigation: None,
+      navigation_repeats: 0,
+      pending_networks: None,
+      autoconnect_guarded: BTreeSet::new(),
+      autoconnect_choice: None,
+      pending_ops: HashMap::new(),
+      status: None,
+      undoable_forget: None,
+      refocus_after_forget: None,
+      scan_error: None,
+      scanning: false,
+      scan_started: None,
+      config,
+      persistent,
     }
   }
 
-  pub fn focused_network(&self) -> Option<WifiInfo> {
+  /// The autoconnect setting to write into the profile of the connection being set up, if any
+  pub fn autoconnect_choice(&self) -> Option<bool> {
+    match self {
+      Self::Running { autoconnect_choice, .. } => *autoconnect_choice,
+      Self::ShouldQuit => None,
+    }
+  }
+
+  /// The network whose forgetting can still be undone, if any
+  pub fn undoable_forget(&self) -> Option<&str> {
     match self {
+      Self::Running { undoable_forget, .. } => undoable_forget.as_deref(),
       Self::ShouldQuit => None,
+    }
+  }
+
+  pub fn refresh_paused(&self) -> bool {
+    matches!(
+      self,
       Self::Running {
-        networks, list_state, ..
-      } => list_state.selected().map(|ix| networks[ix].clone()),
+        refresh_paused: true,
+        ..
+      }
+    )
+  }
+
+  /// The networks as displayed, ie. after filtering. `list_state` indexes into this list.
+  pub fn visible_networks(&self) -> Vec<WifiInfo> {
+    match self {
+      Self::ShouldQuit => Vec::new(),
+      Self::Running {
+        networks,
+        filter,
+        persistent,
+        ..
+      } => visible_networks(networks, persistent, filter),
+    }
+  }
+
+  /// How many networks pass the filters, including those cut off by `max_networks`
+  pub fn matching_network_count(&self) -> usize {
+    match self {
+      Self::ShouldQuit => 0,
+      Self::Running {
+        networks,
+        filter,
+        persistent,
+        ..
+      } => sorted_networks(networks, persistent, filter).len(),
+    }
+  }
+
+  fn take_settled_networks(&mut self) -> Option<Vec<WifiInfo>> {
+    match self {
+      Self::Running {
+        last_navigation,
+        pending_networks,
+        config,
+        ..
+      } if !last_navigation.is_some_and(|t| t.elapsed() < config.navigation_settle()) => pending_networks.take(),
+      _ => None,
+    }
+  }
+
+  pub fn focused_network(&self) -> Option<WifiInfo> {
+    match self {
+      Self::ShouldQuit => None,
+      Self::Running { list_state, .. } => list_state
+        .selected()
+        .and_then(|ix| self.visible_networks().get(ix).cloned()),
     }
   }
 
@@ -104,14 +570,46 @@ impl App {
       return;
     }
 
+    // Catch up on a scan result that was held back during navigation once the user stops moving
+    if let Msg::Tick = msg
+      && let Some(new_networks) = self.take_settled_networks()
+    {
+      self.update(Msg::NetworksFound(new_networks));
+    }
+
     // Extract fields from Running variant for processing
     let focused_network = self.focused_network().clone();
+    let visible_count = self.visible_networks().len();
     let App::Running {
       networks,
       list_state,
       device_info,
       state,
       show_detailed_view,
+      selected_ssids,
+      filter,
+      scan_only,
+      show_signal_scanner,
+      refresh_paused,
+      smoothed_strength,
+      signal_history,
+      connected_since,
+      last_errors,
+      abandoned_connects,
+      last_navigation,
+      navigation_repeats,
+      pending_networks,
+      autoconnect_guarded,
+      autoconnect_choice,
+      pending_ops,
+      status,
+      undoable_forget,
+      refocus_after_forget,
+      scan_error,
+      scanning,
+      scan_started,
+      config,
+      persistent,
     } = self
     else {
       return;
@@ -119,103 +617,250 @@ impl App {
 
     match msg {
       Msg::Tick => {
-        if let AppState::Connecting { throbber_state, .. } = state {
-          throbber_state.calc_next();
+        // Undo is offered for as long as the footer says so
+        if status.as_ref().is_some_and(|(_, at)| at.elapsed() >= STATUS_DURATION) {
+          *status = None;
+          *undoable_forget = None;
+        }
+        match state {
+          // Frames that don't change aren't sent to the terminal at all, so a still throbber means no redraw traffic
+          AppState::Connecting { throbber_state, .. } if config.animate_throbber => throbber_state.calc_next(),
+          AppState::ShowingError { fade, .. } | AppState::ConfirmForget { fade, .. } => {
+            *fade = (*fade + 1).min(DIALOG_FADE_TICKS);
+          }
+          _ => {}
         }
       }
       Msg::Quit => {
         *self = App::ShouldQuit;
         return;
       }
+      Msg::MoveUp if matches!(state, AppState::PickingZone { .. }) => {
+        if let AppState::PickingZone { selected, .. } = state {
+          *selected = selected.saturating_sub(1);
+        }
+      }
+      Msg::MoveDown if matches!(state, AppState::PickingZone { .. }) => {
+        if let AppState::PickingZone { selected, .. } = state {
+          *selected = (*selected + 1).min(ZONE_CHOICES.len() - 1);
+        }
+      }
       Msg::MoveUp => {
-        // If nothing selected, select first network
-        list_state.select_previous();
+        *refresh_paused = false;
+        let step = navigation_step(last_navigation, navigation_repeats);
+        match list_state.selected() {
+          Some(0) if config.wrap_navigation && visible_count > 0 => list_state.select(Some(visible_count - 1)),
+          Some(ix) => list_state.select(Some(ix.saturating_sub(step))),
+          // If nothing selected, select first network
+          None => list_state.select_previous(),
+        }
       }
       Msg::MoveDown => {
+        *refresh_paused = false;
+        let step = navigation_step(last_navigation, navigation_repeats);
         match list_state.selected() {
-          Some(ix) if ix == networks.len() - 1 => {
+          Some(ix) if ix + 1 >= visible_count && config.wrap_navigation => list_state.select(Some(0)),
+          Some(ix) if ix + 1 >= visible_count => {
             // If we're focused on the last element, do nothing. Without this special case pressing down on the last element de-focuses it briefly.
           }
-          _ => list_state.select_next(),
+          Some(ix) => list_state.select(Some((ix + step).min(visible_count - 1))),
+          None => list_state.select_next(),
+        }
+      }
+      Msg::JumpTop | Msg::JumpBottom => {
+        *refresh_paused = false;
+        // Like j/k, hold back rescans so the list doesn't reorder under the new focus
+        *last_navigation = Some(Instant::now());
+        if visible_count > 0 {
+          list_state.select(Some(if matches!(msg, Msg::JumpTop) {
+            0
+          } else {
+            visible_count - 1
+          }));
         }
       }
       Msg::DeviceInfoUpdate(info) => {
         *device_info = Some(info);
       }
-      Msg::NetworksFound(new_networks) => {
+      Msg::ScanStarted => {
+        *scan_started = Some(Instant::now());
+      }
+      Msg::NetworksFound(new_networks) if last_navigation.is_some_and(|t| t.elapsed() < config.navigation_settle()) => {
+        // Only the newest scan matters, older ones are superseded
+        *scan_started = None;
+        *pending_networks = Some(new_networks);
+      }
+      Msg::ScanRetrying => {
+        *scanning = true;
+      }
+      Msg::ScanFailure(error) => {
+        *scanning = false;
+        *scan_started = None;
+        *scan_error = Some(format!("{:#}", error));
+      }
+      Msg::NetworksFound(mut new_networks) => {
+        *pending_networks = None;
+        *scan_error = None;
+        *scanning = false;
+        *scan_started = None;
+        record_signal_history(signal_history, &new_networks);
+        match new_networks.iter().find(|n| n.active) {
+          Some(active) if connected_since.as_ref().is_some_and(|(ssid, _)| *ssid == active.ssid) => {}
+          Some(active) => {
+            // However it got connected, eg. autoconnect, earlier failures no longer matter
+            last_errors.remove(&active.ssid);
+            *connected_since = Some((active.ssid.clone(), Instant::now()));
+          }
+          None => *connected_since = None,
+        }
+        smooth_strengths(smoothed_strength, &mut new_networks);
+
         // Preserve selection by SSID across rescans
         // TODO: should we use some other kind of network ID?
         if let Some(net) = focused_network {
           // Try to find the previously selected network in the new list
-          list_state.select(new_networks.iter().position(|n| n.ssid == net.ssid));
+          list_state.select(
+            visible_networks(&new_networks, persistent, filter)
+              .iter()
+              .position(|n| n.ssid == net.ssid),
+          );
         } else {
           list_state.select_first();
         }
 
+        if let Some(ssid) = refocus_after_forget.as_deref()
+          && !new_networks.iter().any(|n| n.known && n.ssid == ssid)
+        {
+          let visible = visible_networks(&new_networks, persistent, filter);
+          let candidates = || visible.iter().enumerate().filter(|(_, n)| n.ssid != ssid);
+          let strongest = |(_, n): &(usize, &WifiInfo)| n.strength;
+          if let Some((ix, _)) = candidates()
+            .filter(|(_, n)| n.known)
+            .max_by_key(strongest)
+            .or_else(|| candidates().max_by_key(strongest))
+          {
+            list_state.select(Some(ix));
+          }
+          *refocus_after_forget = None;
+        }
+
+        // Drop selections for networks that are no longer known, eg. forgotten from outside weefee
+        selected_ssids.retain(|ssid| new_networks.iter().any(|n| n.known && &n.ssid == ssid));
+
         *networks = new_networks;
       }
       Msg::DismissError => {
         *state = AppState::Normal;
       }
-      Msg::EnterInput => {
+      Msg::EnterInput | Msg::ConnectOnce => {
+        *autoconnect_choice = matches!(msg, Msg::ConnectOnce).then_some(false);
         if let Some(net) = focused_network {
           // If network is active (connected), show disconnect confirmation
           if net.active {
-            *state = AppState::ConfirmDisconnect { network: net };
+            match config.active_enter_action {
+              ActiveEnterAction::Disconnect => *state = AppState::ConfirmDisconnect { network: net },
+              ActiveEnterAction::Details => *show_detailed_view = true,
+              ActiveEnterAction::Ignore => {}
+            }
+          } else if *scan_only {
+            *state = AppState::ShowingError {
+              error: scan_only_error(config),
+              fade: 0,
+            };
           } else if net.weak_security {
             // Show warning for insecure networks before connecting (even if known)
             *state = AppState::ConfirmWeakSecurity { network: net };
+          } else if !net.known && config.confirm_new_connections {
+            *state = AppState::ConfirmNewNetwork { network: net };
           } else if net.known {
             // Known secure network - connect directly without password prompt
             *state = AppState::Connecting {
               network: net.clone(),
               throbber_state: ThrobberState::default(),
+              attempt: 1,
             };
           } else {
-            // Unknown secure network - proceed to password input
-            *state = AppState::EditingPassword {
-              network: net.clone(),
-              password_input: Input::default(),
-            };
+            // Unknown secure network - proceed to password (or enterprise login) input
+            *state = AppState::credentials_prompt(net.clone(), config.cloned_mac_address);
           }
         }
       }
       Msg::Input(c) => {
-        if let AppState::EditingPassword { password_input, .. } = state {
-          password_input.handle(tui_input::InputRequest::InsertChar(c));
+        if let Some(input) = state.active_input() {
+          input.handle(tui_input::InputRequest::InsertChar(c));
+        }
+      }
+      Msg::Paste(text) => {
+        if let Some(input) = state.active_input() {
+          // Copied passwords often come with a trailing newline that isn't part of the password
+          for c in text.chars().filter(|c| *c != '\n' && *c != '\r') {
+            input.handle(tui_input::InputRequest::InsertChar(c));
+          }
         }
       }
+      Msg::PasteFailure(error) => {
+        // Not an error dialog, which would throw away the password prompt
+        *status = Some((format!("Couldn't paste: {}", error), Instant::now()));
+      }
       Msg::Backspace => {
-        if let AppState::EditingPassword { password_input, .. } = state {
-          password_input.handle(tui_input::InputRequest::DeletePrevChar);
+        if let Some(input) = state.active_input() {
+          input.handle(tui_input::InputRequest::DeletePrevChar);
         }
       }
       Msg::MoveCursorLeft => {
-        if let AppState::EditingPassword { password_input, .. } = state {
-          password_input.handle(tui_input::InputRequest::GoToPrevChar);
+        if let Some(input) = state.active_input() {
+          input.handle(tui_input::InputRequest::GoToPrevChar);
         }
       }
       Msg::MoveCursorRight => {
-        if let AppState::EditingPassword { password_input, .. } = state {
-          password_input.handle(tui_input::InputRequest::GoToNextChar);
+        if let Some(input) = state.active_input() {
+          input.handle(tui_input::InputRequest::GoToNextChar);
         }
       }
       Msg::MoveCursorWordLeft => {
-        if let AppState::EditingPassword { password_input, .. } = state {
-          password_input.handle(tui_input::InputRequest::GoToPrevWord);
+        if let Some(input) = state.active_input() {
+          input.handle(tui_input::InputRequest::GoToPrevWord);
         }
       }
       Msg::MoveCursorWordRight => {
-        if let AppState::EditingPassword { password_input, .. } = state {
-          password_input.handle(tui_input::InputRequest::GoToNextWord);
+        if let Some(input) = state.active_input() {
+          input.handle(tui_input::InputRequest::GoToNextWord);
         }
       }
       Msg::DeletePrevWord => {
-        if let AppState::EditingPassword { password_input, .. } = state {
-          password_input.handle(tui_input::InputRequest::DeletePrevWord);
+        if let Some(input) = state.active_input() {
+          input.handle(tui_input::InputRequest::DeletePrevWord);
+        }
+      }
+      Msg::NextField => {
+        if let AppState::EditingEnterprise { field, .. } = state {
+          *field = match field {
+            EnterpriseField::Identity => EnterpriseField::Password,
+            EnterpriseField::Password => EnterpriseField::Identity,
+          };
+        }
+      }
+      Msg::ToggleShowPassword => {
+        if let AppState::EditingPassword { show_password, .. } | AppState::EditingEnterprise { show_password, .. } =
+          state
+        {
+          *show_password = !*show_password;
+        }
+      }
+      Msg::CycleMacAddress => {
+        if let AppState::EditingPassword { mac_address, .. } = state {
+          *mac_address = MacAddressPolicy::next(*mac_address);
+        }
+      }
+      Msg::CycleEapMethod => {
+        if let AppState::EditingEnterprise { method, .. } = state {
+          *method = method.next();
         }
       }
-      Msg::SubmitConnection => {
+      Msg::SubmitConnection | Msg::SubmitWithAutoconnect => {
+        if matches!(msg, Msg::SubmitWithAutoconnect) {
+          *autoconnect_choice = Some(true);
+        }
         // If we're in ConfirmWeakSecurity mode, check if network is known
         if let AppState::ConfirmWeakSecurity { network } = &*state {
           if network.known {
@@ -223,19 +868,63 @@ impl App {
             *state = AppState::Connecting {
               network: network.clone(),
               throbber_state: ThrobberState::default(),
+              attempt: 1,
             };
           } else {
             // Unknown insecure network - go to password input
             *state = AppState::EditingPassword {
               network: network.clone(),
               password_input: Input::default(),
+              show_password: false,
+              mac_address: config.cloned_mac_address,
+              update: false,
             };
           }
-        } else if let AppState::EditingPassword { network, .. } = &*state {
-          // Otherwise, we're submitting from Editing mode, so connect
+        } else if let AppState::ConfirmNewNetwork { network } = &*state {
+          *state = AppState::credentials_prompt(network.clone(), config.cloned_mac_address);
+        } else if let AppState::EditingEnterprise { field, .. } = state
+          && *field == EnterpriseField::Identity
+        {
+          // Enter after the identity moves on to the password, like Tab
+          *field = EnterpriseField::Password;
+        } else if let AppState::EditingEnterprise { network, .. } = &*state {
           *state = AppState::Connecting {
             network: network.clone(),
             throbber_state: ThrobberState::default(),
+            attempt: 1,
+          };
+        } else if let AppState::EditingPassword {
+          network,
+          password_input,
+          ..
+        } = &*state
+          && password_problem(network, password_input.value()).is_some()
+        {
+          // NetworkManager would only reject it, and the hint under the input already says why
+        } else if let AppState::EditingPassword {
+          password_input,
+          update: true,
+          ..
+        } = &*state
+          && password_input.value().is_empty()
+        {
+          // Unlike a first connect, a saved profile never has an empty password to change to
+          *status = Some(("The new password can't be empty".to_string(), Instant::now()));
+        } else if let AppState::EditingPassword { update: true, .. } = state {
+          // The main loop saves the new password
+          *state = AppState::Normal;
+        } else if let AppState::EditingPassword {
+          network,
+          password_input,
+          mac_address,
+          ..
+        } = &*state
+        {
+          // Connecting to an unknown network creates a profile, so show what will be saved first
+          *state = AppState::ConfirmNewProfile {
+            network: network.clone(),
+            password: password_input.value().to_string(),
+            mac_address: *mac_address,
           };
         } else {
           panic!("this should never happen");
@@ -245,10 +934,66 @@ impl App {
         *state = AppState::Normal;
       }
       Msg::ConnectionSuccess => {
-        *state = AppState::Normal;
+        if let Some(ssid) = abandoned_connects.pop_front() {
+          // It got through before the cancel did. Whatever is open now isn't about this attempt.
+          if pending_ops.get(&ssid) == Some(&PendingOp::Connecting) {
+            pending_ops.remove(&ssid);
+          }
+          last_errors.remove(&ssid);
+          *status = Some((
+            format!("Connected to {} before it could be cancelled", ssid),
+            Instant::now(),
+          ));
+        } else {
+          pending_ops.retain(|_, op| *op != PendingOp::Connecting);
+          if let AppState::Connecting { network, .. } = state {
+            last_errors.remove(&network.ssid);
+          }
+          *autoconnect_choice = None;
+          *state = AppState::Normal;
+        }
+      }
+      Msg::CancelConnect => {
+        // The list keeps showing it as connecting until NetworkManager has called it off
+        if let AppState::Connecting { network, .. } = state {
+          abandoned_connects.push_back(network.ssid.clone());
+          *status = Some((format!("Stopped connecting to {}", network.ssid), Instant::now()));
+          *autoconnect_choice = None;
+          *state = AppState::Normal;
+        }
+      }
+      Msg::ConnectionRetry(next) => {
+        // Retries of a cancelled attempt aren't about the one on screen
+        if let AppState::Connecting { attempt, .. } = state
+          && abandoned_connects.is_empty()
+        {
+          *attempt = next;
+        }
       }
       Msg::ConnectionFailure(error) => {
-        *state = AppState::ShowingError { error };
+        let cancelled = matches!(error.downcast_ref(), Some(ConnectionError::Cancelled));
+        if let Some(ssid) = abandoned_connects.pop_front() {
+          if pending_ops.get(&ssid) == Some(&PendingOp::Connecting) {
+            pending_ops.remove(&ssid);
+          }
+          // It failed on its own before the cancel got through, which is still worth knowing in the detail view
+          if !cancelled {
+            last_errors.insert(ssid, format!("{:#}", error));
+          }
+        } else {
+          pending_ops.retain(|_, op| *op != PendingOp::Connecting);
+          if let AppState::Connecting { network, .. } = state
+            && !cancelled
+          {
+            last_errors.insert(network.ssid.clone(), format!("{:#}", error));
+          }
+          *autoconnect_choice = None;
+          *state = if cancelled {
+            AppState::Normal
+          } else {
+            AppState::ShowingError { error, fade: 0 }
+          };
+        }
       }
       Msg::SubmitDisconnect => {
         *state = AppState::Normal;
@@ -257,24 +1002,281 @@ impl App {
         *state = AppState::Normal;
       }
       Msg::DisconnectFailure(error) => {
-        *state = AppState::ShowingError { error };
+        *state = AppState::ShowingError { error, fade: 0 };
       }
       Msg::ConfirmForget => {
-        if let Some(net) = focused_network {
-          *state = AppState::ConfirmForget { network: net };
+        if !selected_ssids.is_empty() {
+          let mut ssids: Vec<String> = selected_ssids.iter().cloned().collect();
+          ssids.sort();
+          // Taken now, while every selected network is still in the list. They can go out of range before the
+          // dialog is answered, and the displayed SSID alone can't be turned back into the broadcast one.
+          let ssid_bytes = networks
+            .iter()
+            .filter(|n| selected_ssids.contains(&n.ssid))
+            .map(|n| n.ssid_bytes.clone())
+            .collect();
+          *state = AppState::ConfirmBatchForget { ssids, ssid_bytes };
+        } else if let Some(net) = focused_network {
+          *state = AppState::ConfirmForget { network: net, fade: 0 };
         }
       }
       Msg::SubmitForget => {
+        match state {
+          AppState::ConfirmBatchForget { ssids, .. } => {
+            pending_ops.extend(ssids.iter().map(|ssid| (ssid.clone(), PendingOp::Forgetting)));
+            *refocus_after_forget = networks
+              .iter()
+              .find(|n| n.active && ssids.contains(&n.ssid))
+              .map(|n| n.ssid.clone());
+          }
+          AppState::ConfirmForgetAll { ssids, .. } => {
+            pending_ops.extend(
+              networks
+                .iter()
+                .filter(|n| ssids.contains(&n.ssid_bytes))
+                .map(|n| (n.ssid.clone(), PendingOp::Forgetting)),
+            );
+          }
+          AppState::ConfirmForget { network, .. } if network.known => {
+            pending_ops.insert(network.ssid.clone(), PendingOp::Forgetting);
+            *refocus_after_forget = network.active.then(|| network.ssid.clone());
+          }
+          _ => {}
+        }
         *state = AppState::Normal;
       }
       Msg::ForgetSuccess => {
+        pending_ops.retain(|_, op| *op != PendingOp::Forgetting);
         *state = AppState::Normal;
+        selected_ssids.clear();
       }
       Msg::ForgetFailure(error) => {
-        *state = AppState::ShowingError { error };
+        pending_ops.retain(|_, op| *op != PendingOp::Forgetting);
+        *refocus_after_forget = None;
+        *state = AppState::ShowingError { error, fade: 0 };
       }
-      Msg::DPressed => {
-        *show_detailed_view = !*show_detailed_view;
+      Msg::ForgetAllExceptActive => {
+        // No-op in app state - handled by network layer
+      }
+      Msg::ConfirmForgetAll(ssids, keep) => {
+        if matches!(state, AppState::Normal) {
+          if ssids.is_empty() {
+            *status = Some(("No other saved networks to forget".to_string(), Instant::now()));
+          } else {
+            *state = AppState::ConfirmForgetAll { ssids, keep };
+          }
+        }
+      }
+      Msg::ForgotMany(count) => {
+        pending_ops.retain(|_, op| *op != PendingOp::Forgetting);
+        selected_ssids.clear();
+        let plural = if count == 1 { "" } else { "s" };
+        *status = Some((format!("Forgot {} saved network{}", count, plural), Instant::now()));
+      }
+      Msg::ForgetNow => {
+        if let Some(net) = focused_network
+          && net.known
+        {
+          *refocus_after_forget = net.active.then(|| net.ssid.clone());
+          pending_ops.insert(net.ssid, PendingOp::Forgetting);
+        }
+      }
+      Msg::ForgottenWithUndo(ssid) => {
+        pending_ops.retain(|_, op| *op != PendingOp::Forgetting);
+        let message = match config.keybindings.first_key(Action::UndoForget) {
+          Some(key) => format!("Forgot {}. Press {} to undo", ssid, key),
+          None => format!("Forgot {}", ssid),
+        };
+        *status = Some((message, Instant::now()));
+        *undoable_forget = Some(ssid);
+      }
+      Msg::UndoForget => {
+        // No-op in app state - handled by network layer
+      }
+      Msg::ForgetUndone(ssid) => {
+        *status = Some((format!("Restored {}", ssid), Instant::now()));
+        *undoable_forget = None;
+      }
+      Msg::ResetPassword => {
+        // No-op in app state - handled by network layer
+      }
+      Msg::EditPassword => {
+        if *show_detailed_view
+          && matches!(state, AppState::Normal)
+          && let Some(network) = focused_network
+          && network.known
+        {
+          *state = if network.security == "Open" {
+            AppState::ShowingError {
+              error: anyhow::anyhow!("{} has no password to change", network.ssid),
+              fade: 0,
+            }
+          } else {
+            *autoconnect_choice = None;
+            AppState::EditingPassword {
+              network,
+              password_input: Input::default(),
+              show_password: false,
+              mac_address: None,
+              update: true,
+            }
+          };
+        }
+      }
+      Msg::PasswordUpdated(ssid) => {
+        *status = Some((format!("Saved the new password of {}", ssid), Instant::now()));
+        // Like other edited settings, the password only applies on the next activation
+        if let Some(net) = networks.iter().find(|n| n.active && n.ssid == ssid)
+          && matches!(state, AppState::Normal)
+          && !*scan_only
+        {
+          *state = AppState::ConfirmReapply { network: net.clone() };
+        }
+      }
+      Msg::ExportProfile => {
+        if let Some(network) = focused_network
+          && matches!(state, AppState::Normal)
+        {
+          *state = AppState::ConfirmExportSecrets { network };
+        }
+      }
+      Msg::SubmitExportProfile(_) => {
+        *state = AppState::Normal;
+      }
+      Msg::ProfileExported(..) => {
+        // No-op in app state - the file is written by the main loop
+      }
+      Msg::ProfileExportFailure(error) => {
+        *state = AppState::ShowingError { error, fade: 0 };
+      }
+      Msg::CopyPassword => {
+        if *show_detailed_view && matches!(state, AppState::Normal) {
+          *state = match networks.iter().find(|n| n.active && n.known) {
+            Some(network) => AppState::ConfirmRevealPassword {
+              network: network.clone(),
+            },
+            None => AppState::ShowingError {
+              error: anyhow::anyhow!("Not connected to a saved network"),
+              fade: 0,
+            },
+          };
+        }
+      }
+      Msg::SubmitCopyPassword => {
+        *state = AppState::Normal;
+      }
+      Msg::PasswordCopied(ssid) => {
+        *status = Some((
+          format!("Copied the password of {} to the clipboard", ssid),
+          Instant::now(),
+        ));
+      }
+      Msg::PasswordCopyFailure(error) => {
+        *state = AppState::ShowingError { error, fade: 0 };
+      }
+      Msg::ShowQrCode => {
+        // No-op in app state - the password is read by the network layer
+      }
+      Msg::SharedPassword(ssid, password) => {
+        if matches!(state, AppState::Normal)
+          && let Some(network) = networks.iter().find(|n| n.ssid == ssid)
+        {
+          let code = qr::wifi_payload(&ssid, &network.security, password.as_deref().unwrap_or(""))
+            .and_then(|payload| QrCode::encode(payload.as_bytes()));
+          *state = match code {
+            Ok(code) => AppState::ShowingQrCode { ssid, code },
+            Err(error) => AppState::ShowingError { error, fade: 0 },
+          };
+        }
+      }
+      Msg::ShareFailure(error) => {
+        *state = AppState::ShowingError { error, fade: 0 };
+      }
+      Msg::PasswordForgotten(ssid) => {
+        if matches!(state, AppState::Normal)
+          && let Some(net) = networks.iter().find(|n| n.ssid == ssid)
+        {
+          // The rescan that reflects the forget hasn't arrived yet, but the profile is gone either way. Whatever
+          // Alt+Enter or Ctrl+Enter chose for an earlier attempt doesn't carry over to the new profile.
+          *autoconnect_choice = None;
+          *state = AppState::credentials_prompt(
+            WifiInfo {
+              known: false,
+              active: false,
+              ..net.clone()
+            },
+            config.cloned_mac_address,
+          );
+        }
+      }
+      Msg::ToggleDetails => {
+        // Don't change the view behind an open dialog
+        if matches!(state, AppState::Normal) {
+          *show_detailed_view = !*show_detailed_view;
+          filter.sort_by_priority = *show_detailed_view && config.priority_order_in_details;
+          refocus(
+            list_state,
+            &visible_networks(networks, persistent, filter),
+            focused_network,
+          );
+        }
+      }
+      Msg::ToggleSelection => {
+        // Only known networks have a profile that can be forgotten
+        if let Some(net) = focused_network
+          && net.known
+          && !selected_ssids.remove(&net.ssid)
+        {
+          selected_ssids.insert(net.ssid);
+        }
+      }
+      Msg::ToggleIgnore => {
+        if let Some(net) = focused_network {
+          if !persistent.ignored_ssids.remove(&net.ssid) {
+            persistent.ignored_ssids.insert(net.ssid.clone());
+            selected_ssids.remove(&net.ssid);
+          }
+          if let Err(error) = persistent.save() {
+            *state = AppState::ShowingError { error, fade: 0 };
+          }
+          // The focused network may have just disappeared from the list
+          let visible = visible_networks(networks, persistent, filter);
+          if list_state.selected().is_some_and(|ix| ix >= visible.len()) {
+            list_state.select(visible.len().checked_sub(1));
+          }
+        }
+      }
+      Msg::ToggleShowIgnored => {
+        filter.show_ignored = !filter.show_ignored;
+        refocus(
+          list_state,
+          &visible_networks(networks, persistent, filter),
+          focused_network,
+        );
+      }
+      Msg::RaiseSignalFilter => {
+        filter.min_signal = (filter.min_signal + SIGNAL_FILTER_STEP).min(100);
+        refocus(
+          list_state,
+          &visible_networks(networks, persistent, filter),
+          focused_network,
+        );
+      }
+      Msg::LowerSignalFilter => {
+        filter.min_signal = filter.min_signal.saturating_sub(SIGNAL_FILTER_STEP);
+        refocus(
+          list_state,
+          &visible_networks(networks, persistent, filter),
+          focused_network,
+        );
+      }
+      Msg::CycleBandFilter => {
+        filter.band = Band::next(filter.band);
+        refocus(
+          list_state,
+          &visible_networks(networks, persistent, filter),
+          focused_network,
+        );
       }
       Msg::ToggleAutoconnect => {
         // No-op in app state - handled by network layer
@@ -283,8 +1285,1055 @@ impl App {
         // Auto-connect setting changed successfully - rescan will update UI
       }
       Msg::AutoconnectFailure(error) => {
-        *state = AppState::ShowingError { error };
+        *state = AppState::ShowingError { error, fade: 0 };
+      }
+      Msg::CycleIp4Method
+      | Msg::CycleIp6Privacy
+      | Msg::TogglePowersave
+      | Msg::ToggleNeverDefault
+      | Msg::CompareSettings
+      | Msg::ExportList => {
+        // No-op in app state - handled by network layer
+      }
+      Msg::ShowHelp => {
+        if matches!(state, AppState::Normal) {
+          *state = AppState::ShowingHelp { first_run: false };
+        }
+      }
+      Msg::ShowDeviceInfo => {
+        if matches!(state, AppState::Normal) {
+          *state = AppState::ShowingDeviceInfo;
+        }
+      }
+      Msg::ShowOnboarding => {
+        *state = AppState::ShowingHelp { first_run: true };
+      }
+      Msg::DismissHelp => {
+        let first_run = matches!(state, AppState::ShowingHelp { first_run: true });
+        *state = AppState::Normal;
+        // Writing the state file is what marks onboarding as done
+        if first_run && let Err(error) = persistent.save() {
+          *state = AppState::ShowingError { error, fade: 0 };
+        }
+      }
+      Msg::ToggleWifi => {
+        // Turning WiFi off while connected drops the connection, so that asks first. Everything else is up to the
+        // network layer.
+        let wifi_enabled = device_info.as_ref().is_some_and(|info| info.wifi_enabled);
+        if wifi_enabled
+          && matches!(state, AppState::Normal)
+          && let Some(network) = networks.iter().find(|n| n.active)
+        {
+          *state = AppState::ConfirmRadioOff {
+            network: network.clone(),
+          };
+        }
+      }
+      Msg::SubmitRadioOff => {
+        *state = AppState::Normal;
+      }
+      Msg::WifiToggled(enabled) => {
+        let message = if enabled {
+          "WiFi is on, scanning…".to_string()
+        } else {
+          match config.keybindings.first_key(Action::ToggleWifi) {
+            Some(key) => format!("WiFi is off. Press {} to turn it back on", key),
+            None => "WiFi is off".to_string(),
+          }
+        };
+        *status = Some((message, Instant::now()));
+      }
+      Msg::ToggleConnectivityCheck | Msg::ToggleDeviceAutoconnect => {
+        // No-op in app state - handled by network layer
+      }
+      Msg::AutoconnectGuarded(ssids) => {
+        *autoconnect_guarded = ssids;
+      }
+      Msg::AutoconnectGuardFailure(error) => {
+        // Comes with a background scan, so don't get in the way of whatever the user is doing
+        *status = Some((format!("{:#}", error), Instant::now()));
+      }
+      Msg::CycleWifiDevice => {
+        // Switching is up to the network layer, this only explains why nothing happens
+        if device_info.as_ref().is_some_and(|info| info.next_interface().is_none()) {
+          *status = Some(("There's no other WiFi adapter".to_string(), Instant::now()));
+        }
+      }
+      Msg::WifiToggleFailure(error) | Msg::SelectDeviceFailure(error) => {
+        if matches!(state, AppState::Normal) {
+          *state = AppState::ShowingError { error, fade: 0 };
+        }
+      }
+      Msg::SettingsCompared(ssid, comparison) => {
+        if matches!(state, AppState::Normal) {
+          *state = AppState::ShowingSettingsComparison { ssid, comparison };
+        }
+      }
+      Msg::CompareSettingsFailure(error) => {
+        *state = AppState::ShowingError { error, fade: 0 };
+      }
+      Msg::CaptivePortalDetected(ssid) => {
+        if matches!(state, AppState::Normal) {
+          *state = AppState::CaptivePortal { ssid };
+        }
+      }
+      Msg::OpenPortalLogin => {
+        if matches!(state, AppState::CaptivePortal { .. }) {
+          *state = AppState::Normal;
+        }
+      }
+      Msg::SettingsUpdateSuccess(ssid) => {
+        // NetworkManager only applies edited settings on the next activation, so offer to reconnect right away if the
+        // edited network is the one we're connected to. Otherwise the rescan will update the UI.
+        if let Some(net) = networks.iter().find(|n| n.active && n.ssid == ssid)
+          && matches!(state, AppState::Normal)
+          && !*scan_only
+        {
+          *state = AppState::ConfirmReapply { network: net.clone() };
+        }
+      }
+      Msg::PickZone => {
+        if let Some(net) = focused_network
+          && net.known
+          && matches!(state, AppState::Normal)
+        {
+          let selected = ZONE_CHOICES
+            .iter()
+            .position(|zone| *zone == net.zone.as_deref())
+            .unwrap_or(0);
+          *state = AppState::PickingZone { network: net, selected };
+        }
+      }
+      Msg::SubmitZone => {
+        // The network layer reports back with SettingsUpdateSuccess/Failure
+        *state = AppState::Normal;
+      }
+      Msg::CycleSortMode => {
+        filter.sort_mode = filter.sort_mode.next();
+        refocus(
+          list_state,
+          &visible_networks(networks, persistent, filter),
+          focused_network,
+        );
+      }
+      Msg::Refresh => {
+        // No-op in app state - handled by network layer
       }
+      Msg::ToggleRefreshPause => {
+        *refresh_paused = !*refresh_paused;
+      }
+      Msg::ToggleSignalScanner => {
+        if matches!(state, AppState::Normal) {
+          *show_signal_scanner = !*show_signal_scanner;
+        }
+      }
+      Msg::SubmitNewProfile => {
+        if let AppState::ConfirmNewProfile { network, .. } = &*state {
+          *state = AppState::Connecting {
+            network: network.clone(),
+            throbber_state: ThrobberState::default(),
+            attempt: 1,
+          };
+        }
+      }
+      Msg::ToggleScanOnly => {
+        *scan_only = !*scan_only;
+      }
+      Msg::SubmitReapply => {
+        if let AppState::ConfirmReapply { network } = &*state {
+          *state = AppState::Connecting {
+            network: network.clone(),
+            throbber_state: ThrobberState::default(),
+            attempt: 1,
+          };
+        }
+      }
+      Msg::SettingsUpdateFailure(error) => {
+        *state = AppState::ShowingError { error, fade: 0 };
+      }
+    }
+
+    // Every way into Connecting dispatches a connect, whichever message led there
+    if let AppState::Connecting { network, .. } = state {
+      pending_ops.insert(network.ssid.clone(), PendingOp::Connecting);
+    }
+  }
+}
+
+/// Why connecting is refused in scan-only mode, naming the key that leaves it
+pub fn scan_only_error(config: &Config) -> anyhow::Error {
+  match config.keybindings.first_key(Action::ToggleScanOnly) {
+    Some(key) => anyhow::anyhow!("Connecting is disabled in scan-only mode (press {} to leave it)", key),
+    None => anyhow::anyhow!("Connecting is disabled in scan-only mode"),
+  }
+}
+
+/// Why NetworkManager would reject `password` for `network`, if it would. An empty password is let through, for open
+/// networks whose security was misreported.
+pub fn password_problem(network: &WifiInfo, password: &str) -> Option<&'static str> {
+  if password.is_empty() || !uses_wpa_psk(&network.security) {
+    return None;
+  }
+  validate_psk(password).err()
+}
+
+fn visible_networks(networks: &[WifiInfo], persistent: &PersistentState, filter: &ListFilter) -> Vec<WifiInfo> {
+  let mut visible = sorted_networks(networks, persistent, filter);
+  if let Some(max) = filter.max_networks {
+    // Every order puts the connected network first, so the cap never hides it
+    visible.truncate(max);
+  }
+  visible
+}
+
+/// The networks that pass the filters, in display order
+fn sorted_networks(networks: &[WifiInfo], persistent: &PersistentState, filter: &ListFilter) -> Vec<WifiInfo> {
+  let mut visible = networks
+    .iter()
+    .filter(|n| filter.show_ignored || !persistent.ignored_ssids.contains(&n.ssid))
+    // Never hide the network we're connected to just because its signal dipped
+    .filter(|n| n.active || n.strength >= filter.min_signal)
+    .filter(|n| {
+      n.active
+        || filter
+          .band
+          .is_none_or(|band| n.frequency.and_then(Band::from_frequency) == Some(band))
+    })
+    .cloned()
+    .collect::<Vec<_>>();
+  // Scans arrive strongest first, and these sorts are stable, so ties stay in signal order
+  match filter.sort_mode {
+    SortMode::SignalDesc => {}
+    SortMode::Quality => visible.sort_by_key(|n| (!n.active, std::cmp::Reverse(quality_score(n)))),
+    SortMode::Alpha => visible.sort_by_key(|n| (!n.active, n.ssid.to_lowercase())),
+    SortMode::Band => {
+      visible.sort_by_key(|n| (!n.active, std::cmp::Reverse(n.frequency.and_then(Band::from_frequency))))
+    }
+    SortMode::Known => visible.sort_by_key(|n| (!n.active, !n.known)),
+  }
+  if filter.sort_by_priority {
+    // Unknown networks all tie, so they keep their order from above
+    visible.sort_by_key(|n| {
+      (
+        !n.active,
+        !n.known,
+        std::cmp::Reverse(n.known.then_some(n.priority.unwrap_or(0))),
+      )
+    });
+  }
+  visible
+}
+
+/// Appends the latest scan to the per-SSID signal history, forgetting networks that have been gone for the whole window.
+fn record_signal_history(history: &mut HashMap<String, VecDeque<u8>>, networks: &[WifiInfo]) {
+  for net in networks {
+    history.entry(net.ssid.clone()).or_default();
+  }
+  for (ssid, samples) in history.iter_mut() {
+    let strength = networks.iter().find(|n| &n.ssid == ssid).map_or(0, |n| n.raw_strength);
+    samples.push_back(strength);
+    if samples.len() > SIGNAL_HISTORY_LEN {
+      samples.pop_front();
+    }
+  }
+  history.retain(|_, samples| samples.iter().any(|&s| s > 0));
+}
+
+/// Replaces each network's scanned strength with a moving average over recent scans, so that signal bars and the sort
+/// order don't jump around with every scan. The scanned value is kept in `raw_strength`.
+fn smooth_strengths(smoothed: &mut HashMap<String, f32>, networks: &mut [WifiInfo]) {
+  smoothed.retain(|ssid, _| networks.iter().any(|n| &n.ssid == ssid));
+  for net in networks.iter_mut() {
+    let raw = net.raw_strength as f32;
+    let average = smoothed
+      .entry(net.ssid.clone())
+      .and_modify(|avg| *avg += SIGNAL_SMOOTHING * (raw - *avg))
+      .or_insert(raw);
+    net.strength = average.round() as u8;
+  }
+  // Same order as the network layer: active first, then strongest
+  networks.sort_by(|a, b| b.active.cmp(&a.active).then(b.strength.cmp(&a.strength)));
+}
+
+/// Records a j/k press and returns how many rows it should move. Holding the key down scrolls faster the longer it's
+/// held, up to `NAVIGATION_MAX_STEP`.
+fn navigation_step(last_navigation: &mut Option<Instant>, repeats: &mut u32) -> usize {
+  let now = Instant::now();
+  if last_navigation.is_some_and(|t| now.duration_since(t) < NAVIGATION_REPEAT_WINDOW) {
+    *repeats += 1;
+  } else {
+    *repeats = 0;
+  }
+  *last_navigation = Some(now);
+  (1 + (*repeats / NAVIGATION_ACCELERATION_EVERY) as usize).min(NAVIGATION_MAX_STEP)
+}
+
+/// Keeps focus on the same network when the visible list changes shape, falling back to the first network.
+fn refocus(list_state: &mut ListState, visible: &[WifiInfo], focused_network: Option<WifiInfo>) {
+  list_state.select(
+    focused_network
+      .and_then(|net| visible.iter().position(|n| n.ssid == net.ssid))
+      .or(if visible.is_empty() { None } else { Some(0) }),
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock::{MockClient, sample_networks};
+  use crate::network::NetworkClient;
+
+  fn app_with_networks() -> App {
+    let mut app = App::new(Config::default(), PersistentState::default());
+    app.update(Msg::NetworksFound(sample_networks()));
+    app
+  }
+
+  fn state(app: &App) -> &AppState {
+    match app {
+      App::Running { state, .. } => state,
+      App::ShouldQuit => panic!("app quit unexpectedly"),
+    }
+  }
+
+  fn focus(app: &mut App, ssid: &str) {
+    let ix = app.visible_networks().iter().position(|n| n.ssid == ssid);
+    assert!(ix.is_some(), "{ssid} is not visible");
+    if let App::Running { list_state, .. } = app {
+      list_state.select(ix);
+    }
+  }
+
+  #[test]
+  fn selection_follows_network_across_rescans() {
+    let mut app = app_with_networks();
+    focus(&mut app, "CoffeeShop");
+
+    let mut reordered = sample_networks();
+    reordered.reverse();
+    app.update(Msg::NetworksFound(reordered));
+
+    assert_eq!(app.focused_network().unwrap().ssid, "CoffeeShop");
+  }
+
+  #[test]
+  fn rescans_wait_until_navigation_pauses() {
+    let config = Config {
+      navigation_settle_ms: 50,
+      ..Config::default()
+    };
+    let mut app = App::new(config, PersistentState::default());
+    app.update(Msg::NetworksFound(sample_networks()));
+    app.update(Msg::MoveDown);
+    let focused = app.focused_network().unwrap().ssid;
+
+    let mut reordered = sample_networks();
+    reordered.reverse();
+    reordered.truncate(2);
+    app.update(Msg::NetworksFound(reordered));
+    app.update(Msg::Tick);
+    assert_eq!(app.visible_networks().len(), sample_networks().len());
+    assert_eq!(app.focused_network().unwrap().ssid, focused);
+
+    std::thread::sleep(Duration::from_millis(60));
+    app.update(Msg::Tick);
+    assert_eq!(app.visible_networks().len(), 2);
+  }
+
+  #[test]
+  fn holding_down_a_navigation_key_speeds_up() {
+    let networks = (0..30)
+      .map(|i| WifiInfo {
+        ssid: format!("Net{}", i),
+        ..WifiInfo::default()
+      })
+      .collect();
+    let mut app = App::new(Config::default(), PersistentState::default());
+    app.update(Msg::NetworksFound(networks));
+
+    // Key repeat fires far faster than the repeat window
+    for _ in 0..20 {
+      app.update(Msg::MoveDown);
+    }
+    // 8 single steps, 8 double steps, then triple steps until the end of the list
+    assert_eq!(app.focused_network().unwrap().ssid, "Net29");
+
+    std::thread::sleep(NAVIGATION_REPEAT_WINDOW);
+    app.update(Msg::MoveUp);
+    assert_eq!(app.focused_network().unwrap().ssid, "Net28");
+  }
+
+  #[test]
+  fn max_networks_keeps_the_connected_network() {
+    let config = Config {
+      max_networks: Some(2),
+      ..Config::default()
+    };
+    let mut networks = sample_networks();
+    // The connected network is the weakest, but still listed ahead of the rest
+    let home = networks.iter_mut().find(|n| n.active).unwrap();
+    home.strength = 5;
+    home.raw_strength = 5;
+    let mut app = App::new(config, PersistentState::default());
+    app.update(Msg::NetworksFound(networks));
+
+    let shown: Vec<String> = app.visible_networks().into_iter().map(|n| n.ssid).collect();
+    assert_eq!(shown, ["HomeNetwork", "Office"]);
+    assert_eq!(app.matching_network_count(), sample_networks().len());
+  }
+
+  #[test]
+  fn detail_view_orders_known_networks_by_priority() {
+    let mut networks = sample_networks();
+    // Give the weaker known network the higher priority
+    networks.iter_mut().find(|n| n.ssid == "Office").unwrap().priority = Some(20);
+    networks.push(WifiInfo {
+      ssid: "Backup".to_string(),
+      strength: 10,
+      known: true,
+      ..WifiInfo::default()
+    });
+    let mut app = App::new(Config::default(), PersistentState::default());
+    app.update(Msg::NetworksFound(networks));
+    app.update(Msg::ToggleDetails);
+
+    let order: Vec<String> = app.visible_networks().into_iter().map(|n| n.ssid).collect();
+    assert_eq!(
+      order,
+      [
+        "HomeNetwork",
+        "Office",
+        "Backup",
+        "CoffeeShop",
+        "Neighbor5G",
+        "OldRouter"
+      ]
+    );
+  }
+
+  #[test]
+  fn connection_duration_survives_rescans_until_the_active_network_changes() {
+    let since = |app: &App| match app {
+      App::Running { connected_since, .. } => connected_since.clone(),
+      App::ShouldQuit => None,
+    };
+    let mut app = app_with_networks();
+    let (ssid, first_seen) = since(&app).expect("HomeNetwork is active");
+    assert_eq!(ssid, "HomeNetwork");
+
+    app.update(Msg::NetworksFound(sample_networks()));
+    assert_eq!(since(&app), Some((ssid, first_seen)));
+
+    let mut networks = sample_networks();
+    networks.iter_mut().for_each(|n| n.active = n.ssid == "Office");
+    app.update(Msg::NetworksFound(networks.clone()));
+    assert!(since(&app).is_some_and(|(ssid, _)| ssid == "Office"));
+
+    networks.iter_mut().for_each(|n| n.active = false);
+    app.update(Msg::NetworksFound(networks));
+    assert_eq!(since(&app), None);
+  }
+
+  #[test]
+  fn band_filter_keeps_only_that_band_and_the_connected_network() {
+    let mut app = app_with_networks();
+    let visible = |app: &App| app.visible_networks().into_iter().map(|n| n.ssid).collect::<Vec<_>>();
+    let all = visible(&app);
+
+    app.update(Msg::CycleBandFilter);
+    // 2.4 GHz, plus the connected network on 5 GHz
+    assert_eq!(visible(&app), ["HomeNetwork", "Office", "CoffeeShop", "OldRouter"]);
+    app.update(Msg::CycleBandFilter);
+    assert_eq!(visible(&app), ["HomeNetwork", "Neighbor5G"]);
+    app.update(Msg::CycleBandFilter);
+    assert_eq!(visible(&app), ["HomeNetwork"]);
+    app.update(Msg::CycleBandFilter);
+    assert_eq!(visible(&app), all);
+  }
+
+  #[test]
+  fn last_connection_error_is_kept_until_connecting_works() {
+    let last_error = |app: &App| match app {
+      App::Running { last_errors, .. } => last_errors.get("Office").cloned(),
+      App::ShouldQuit => None,
+    };
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::EnterInput);
+    app.update(Msg::ConnectionFailure(ConnectionError::IncorrectPassword.into()));
+    app.update(Msg::DismissError);
+    assert_eq!(last_error(&app).as_deref(), Some("Incorrect password"));
+
+    app.update(Msg::EnterInput);
+    app.update(Msg::ConnectionSuccess);
+    assert_eq!(last_error(&app), None);
+  }
+
+  #[test]
+  fn jumps_to_the_ends_of_the_list() {
+    let mut app = app_with_networks();
+    let count = app.visible_networks().len();
+    let selected = |app: &App| match app {
+      App::Running { list_state, .. } => list_state.selected(),
+      App::ShouldQuit => None,
+    };
+
+    app.update(Msg::JumpBottom);
+    assert_eq!(selected(&app), Some(count - 1));
+    app.update(Msg::JumpTop);
+    assert_eq!(selected(&app), Some(0));
+  }
+
+  #[test]
+  fn navigation_wraps_around_only_when_configured() {
+    let selected = |app: &App| match app {
+      App::Running { list_state, .. } => list_state.selected(),
+      App::ShouldQuit => None,
+    };
+    let mut app = app_with_networks();
+    let count = app.visible_networks().len();
+    app.update(Msg::JumpBottom);
+    app.update(Msg::MoveDown);
+    assert_eq!(selected(&app), Some(count - 1));
+
+    let config = Config {
+      wrap_navigation: true,
+      ..Config::default()
+    };
+    let mut app = App::new(config, PersistentState::default());
+    app.update(Msg::NetworksFound(sample_networks()));
+    app.update(Msg::JumpBottom);
+    app.update(Msg::MoveDown);
+    assert_eq!(selected(&app), Some(0));
+    app.update(Msg::MoveUp);
+    assert_eq!(selected(&app), Some(count - 1));
+  }
+
+  #[test]
+  fn paste_into_password_drops_newlines() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Neighbor5G");
+    app.update(Msg::EnterInput);
+    app.update(Msg::Paste("hunter2\r\n".to_string()));
+
+    let AppState::EditingPassword { password_input, .. } = state(&app) else {
+      panic!("expected the password prompt");
+    };
+    assert_eq!(password_input.value(), "hunter2");
+    assert_eq!(password_input.cursor(), "hunter2".len());
+  }
+
+  #[test]
+  fn new_networks_can_require_confirmation() {
+    let config = Config {
+      confirm_new_connections: true,
+      ..Config::default()
+    };
+    let mut app = App::new(config, PersistentState::default());
+    app.update(Msg::NetworksFound(sample_networks()));
+    focus(&mut app, "Neighbor5G");
+    app.update(Msg::EnterInput);
+    assert!(matches!(state(&app), AppState::ConfirmNewNetwork { .. }));
+
+    app.update(Msg::SubmitConnection);
+    assert!(matches!(state(&app), AppState::EditingPassword { .. }));
+
+    // Known networks connect as before
+    app.update(Msg::CancelInput);
+    focus(&mut app, "Office");
+    app.update(Msg::EnterInput);
+    assert!(matches!(state(&app), AppState::Connecting { .. }));
+  }
+
+  #[test]
+  fn short_psk_is_not_submitted() {
+    let mut network = sample_networks().remove(0);
+    network.known = false;
+    network.active = false;
+    let mut app = app_with_networks();
+    app.update(Msg::NetworksFound(vec![network]));
+    app.update(Msg::EnterInput);
+    app.update(Msg::Paste("short".to_string()));
+    app.update(Msg::SubmitConnection);
+    assert!(matches!(state(&app), AppState::EditingPassword { .. }));
+
+    app.update(Msg::Paste("enough".to_string()));
+    app.update(Msg::SubmitConnection);
+    assert!(matches!(state(&app), AppState::ConfirmNewProfile { .. }));
+  }
+
+  #[test]
+  fn empty_password_is_passed_through() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Neighbor5G");
+    app.update(Msg::EnterInput);
+    app.update(Msg::SubmitConnection);
+
+    let AppState::ConfirmNewProfile { password, .. } = state(&app) else {
+      panic!("expected the new profile summary");
+    };
+    assert_eq!(password, "");
+  }
+
+  #[test]
+  fn forgotten_password_prompts_for_a_new_one() {
+    let mut app = app_with_networks();
+    app.update(Msg::PasswordForgotten("HomeNetwork".to_string()));
+
+    let AppState::EditingPassword { network, .. } = state(&app) else {
+      panic!("expected the password prompt");
+    };
+    assert_eq!(network.ssid, "HomeNetwork");
+    assert!(!network.known);
+  }
+
+  #[test]
+  fn move_down_stops_at_last_network() {
+    let mut app = app_with_networks();
+    for _ in 0..10 {
+      app.update(Msg::MoveDown);
     }
+    assert_eq!(app.focused_network().unwrap().ssid, "OldRouter");
+  }
+
+  #[test]
+  fn enter_routes_to_the_right_dialog() {
+    let mut app = app_with_networks();
+    app.update(Msg::EnterInput);
+    assert!(matches!(state(&app), AppState::ConfirmDisconnect { .. }));
+
+    app.update(Msg::CancelInput);
+    focus(&mut app, "CoffeeShop");
+    app.update(Msg::EnterInput);
+    assert!(matches!(state(&app), AppState::ConfirmWeakSecurity { .. }));
+
+    app.update(Msg::CancelInput);
+    focus(&mut app, "Neighbor5G");
+    app.update(Msg::EnterInput);
+    assert!(matches!(state(&app), AppState::EditingPassword { .. }));
+
+    app.update(Msg::SubmitConnection);
+    assert!(matches!(state(&app), AppState::ConfirmNewProfile { .. }));
+
+    app.update(Msg::CancelInput);
+    focus(&mut app, "Office");
+    app.update(Msg::EnterInput);
+    assert!(matches!(state(&app), AppState::Connecting { .. }));
+  }
+
+  #[test]
+  fn connection_failure_shows_error() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::EnterInput);
+    app.update(Msg::ConnectionFailure(anyhow::anyhow!("nope")));
+    assert!(matches!(state(&app), AppState::ShowingError { .. }));
+
+    app.update(Msg::DismissError);
+    assert!(matches!(state(&app), AppState::Normal));
+  }
+
+  #[test]
+  fn cancelled_connection_is_not_an_error() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::EnterInput);
+    app.update(Msg::CancelConnect);
+    assert!(matches!(state(&app), AppState::Normal));
+    assert!(matches!(&app, App::Running { status: Some(_), .. }));
+
+    // Connecting elsewhere before the cancelled attempt reports back, its outcome isn't mistaken for the new one's
+    let mut networks = sample_networks();
+    networks.iter_mut().for_each(|n| n.active = false);
+    app.update(Msg::NetworksFound(networks));
+    focus(&mut app, "HomeNetwork");
+    app.update(Msg::EnterInput);
+    app.update(Msg::ConnectionFailure(ConnectionError::Cancelled.into()));
+    let AppState::Connecting { network, .. } = state(&app) else {
+      panic!("expected to still be connecting");
+    };
+    assert_eq!(network.ssid, "HomeNetwork");
+    let App::Running {
+      last_errors,
+      pending_ops,
+      ..
+    } = &app
+    else {
+      panic!("app quit unexpectedly");
+    };
+    assert!(last_errors.is_empty());
+    assert!(!pending_ops.contains_key("Office"));
+    assert!(pending_ops.contains_key("HomeNetwork"));
+
+    app.update(Msg::ConnectionSuccess);
+    assert!(matches!(state(&app), AppState::Normal));
+  }
+
+  #[test]
+  fn alt_enter_connects_without_autoconnect_once() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::ConnectOnce);
+    assert!(matches!(state(&app), AppState::Connecting { .. }));
+    assert_eq!(app.autoconnect_choice(), Some(false));
+
+    app.update(Msg::ConnectionSuccess);
+    assert_eq!(app.autoconnect_choice(), None);
+  }
+
+  #[test]
+  fn autoconnect_choice_ends_with_the_attempt() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::ConnectOnce);
+    app.update(Msg::ConnectionFailure(anyhow::anyhow!("Incorrect password")));
+    assert_eq!(app.autoconnect_choice(), None);
+
+    // Logging in again makes a new profile, which gets NetworkManager's default again
+    app.update(Msg::DismissError);
+    app.update(Msg::PasswordForgotten("Office".to_string()));
+    assert!(matches!(state(&app), AppState::EditingEnterprise { .. }));
+    assert_eq!(app.autoconnect_choice(), None);
+  }
+
+  #[test]
+  fn ctrl_enter_saves_new_profiles_with_autoconnect() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Neighbor5G");
+    app.update(Msg::ConnectOnce);
+    app.update(Msg::Paste("hunter22".to_string()));
+    app.update(Msg::SubmitWithAutoconnect);
+    assert!(matches!(state(&app), AppState::ConfirmNewProfile { .. }));
+    assert_eq!(app.autoconnect_choice(), Some(true));
+
+    app.update(Msg::CancelInput);
+    app.update(Msg::EnterInput);
+    app.update(Msg::SubmitConnection);
+    assert_eq!(app.autoconnect_choice(), None);
+  }
+
+  #[test]
+  fn in_flight_operations_are_marked_until_they_report_back() {
+    let pending = |app: &App| match app {
+      App::Running { pending_ops, .. } => pending_ops.clone(),
+      App::ShouldQuit => HashMap::new(),
+    };
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::EnterInput);
+    assert_eq!(pending(&app).get("Office"), Some(&PendingOp::Connecting));
+    app.update(Msg::ConnectionFailure(anyhow::anyhow!("nope")));
+    assert!(pending(&app).is_empty());
+
+    app.update(Msg::DismissError);
+    app.update(Msg::ConfirmForget);
+    app.update(Msg::SubmitForget);
+    assert_eq!(pending(&app).get("Office"), Some(&PendingOp::Forgetting));
+    app.update(Msg::ForgetSuccess);
+    assert!(pending(&app).is_empty());
+  }
+
+  #[test]
+  fn forgetting_all_marks_listed_networks_until_done() {
+    let mut app = app_with_networks();
+    app.update(Msg::ConfirmForgetAll(
+      vec![b"Office".to_vec()],
+      Some("HomeNetwork".to_string()),
+    ));
+    assert!(matches!(state(&app), AppState::ConfirmForgetAll { keep: Some(keep), .. } if keep == "HomeNetwork"));
+    app.update(Msg::SubmitForget);
+    assert!(matches!(state(&app), AppState::Normal));
+    assert!(matches!(&app, App::Running { pending_ops, .. } if pending_ops.len() == 1));
+    app.update(Msg::ForgotMany(1));
+    assert!(matches!(&app, App::Running { pending_ops, status: Some(_), .. } if pending_ops.is_empty()));
+  }
+
+  #[test]
+  fn batch_forget_remembers_broadcast_ssids() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::ToggleSelection);
+    app.update(Msg::ConfirmForget);
+    let AppState::ConfirmBatchForget { ssids, ssid_bytes } = state(&app) else {
+      panic!("expected a batch confirmation");
+    };
+    assert_eq!(ssids, &["Office".to_string()]);
+    assert_eq!(ssid_bytes, &[b"Office".to_vec()]);
+  }
+
+  #[test]
+  fn fast_forget_offers_undo() {
+    let mut app = app_with_networks();
+    focus(&mut app, "Office");
+    app.update(Msg::ForgetNow);
+    assert!(matches!(state(&app), AppState::Normal));
+    app.update(Msg::ForgottenWithUndo("Office".to_string()));
+    assert_eq!(app.undoable_forget(), Some("Office"));
+
+    app.update(Msg::ForgetUndone("Office".to_string()));
+    assert_eq!(app.undoable_forget(), None);
+  }
+
+  #[test]
+  fn retried_empty_scan_shows_scanning_until_results_arrive() {
+    let scanning = |app: &App| matches!(app, App::Running { scanning: true, .. });
+    let mut app = App::new(Config::default(), PersistentState::default());
+    app.update(Msg::ScanRetrying);
+    assert!(scanning(&app));
+    app.update(Msg::NetworksFound(sample_networks()));
+    assert!(!scanning(&app));
+
+    app.update(Msg::ScanRetrying);
+    app.update(Msg::ScanFailure(anyhow::anyhow!("no device")));
+    assert!(!scanning(&app));
+  }
+
+  #[test]
+  fn scan_in_progress_is_tracked_until_it_reports_back() {
+    let scan_started = |app: &App| {
+      matches!(
+        app,
+        App::Running {
+          scan_started: Some(_),
+          ..
+        }
+      )
+    };
+    let mut app = app_with_networks();
+    app.update(Msg::ScanStarted);
+    assert!(scan_started(&app));
+    app.update(Msg::NetworksFound(sample_networks()));
+    assert!(!scan_started(&app));
+
+    app.update(Msg::ScanStarted);
+    app.update(Msg::ScanFailure(anyhow::anyhow!("no device")));
+    assert!(!scan_started(&app));
+  }
+
+  #[test]
+  fn forgetting_the_active_network_focuses_the_next_best_one() {
+    let mut app = app_with_networks();
+    focus(&mut app, "HomeNetwork");
+    app.update(Msg::ConfirmForget);
+    app.update(Msg::SubmitForget);
+    app.update(Msg::ForgetSuccess);
+
+    let networks: Vec<WifiInfo> = sample_networks()
+      .into_iter()
+      .map(|n| match n.ssid.as_str() {
+        "HomeNetwork" => WifiInfo {
+          known: false,
+          active: false,
+          uuid: None,
+          ..n
+        },
+        _ => n,
+      })
+      .collect();
+    app.update(Msg::NetworksFound(networks.clone()));
+    // The forgotten network is still the strongest around, but Office is the strongest one still saved
+    assert_eq!(app.focused_network().map(|n| n.ssid), Some("Office".to_string()));
+
+    // Later scans keep the selection where the user puts it
+    focus(&mut app, "CoffeeShop");
+    app.update(Msg::NetworksFound(networks));
+    assert_eq!(app.focused_network().map(|n| n.ssid), Some("CoffeeShop".to_string()));
+  }
+
+  #[test]
+  fn enterprise_networks_ask_for_a_login() {
+    let mut app = App::new(Config::default(), PersistentState::default());
+    app.update(Msg::NetworksFound(vec![WifiInfo {
+      ssid: "eduroam".to_string(),
+      strength: 70,
+      security: "WPA2-Ent".to_string(),
+      ..WifiInfo::default()
+    }]));
+    app.update(Msg::EnterInput);
+    for c in "jdoe".chars() {
+      app.update(Msg::Input(c));
+    }
+    // Enter after the identity moves on to the password rather than connecting
+    app.update(Msg::SubmitConnection);
+    app.update(Msg::Paste("hunter22\n".to_string()));
+    app.update(Msg::CycleEapMethod);
+    let AppState::EditingEnterprise {
+      method,
+      identity_input,
+      password_input,
+      ..
+    } = state(&app)
+    else {
+      panic!("expected the enterprise login");
+    };
+    assert_eq!(identity_input.value(), "jdoe");
+    assert_eq!(password_input.value(), "hunter22");
+    assert_eq!(*method, EapMethod::Ttls);
+
+    app.update(Msg::SubmitConnection);
+    assert!(matches!(state(&app), AppState::Connecting { .. }));
+  }
+
+  #[test]
+  fn passwords_are_masked_until_revealed() {
+    let mut app = app_with_networks();
+    focus(&mut app, "CoffeeShop");
+    app.update(Msg::EnterInput);
+    app.update(Msg::SubmitConnection);
+    app.update(Msg::Input('x'));
+    let showing = |app: &App| match state(app) {
+      AppState::EditingPassword {
+        show_password,
+        password_input,
+        ..
+      } => {
+        assert_eq!(password_input.value(), "x");
+        *show_password
+      }
+      _ => panic!("expected the password prompt"),
+    };
+    assert!(!showing(&app));
+    app.update(Msg::ToggleShowPassword);
+    assert!(showing(&app));
+    app.update(Msg::ToggleShowPassword);
+    assert!(!showing(&app));
+  }
+
+  #[test]
+  fn copying_the_password_asks_first_and_needs_a_connection() {
+    let mut app = app_with_networks();
+    // Only from the detail view
+    app.update(Msg::CopyPassword);
+    assert!(matches!(state(&app), AppState::Normal));
+
+    app.update(Msg::ToggleDetails);
+    app.update(Msg::CopyPassword);
+    let AppState::ConfirmRevealPassword { network } = state(&app) else {
+      panic!("expected a confirmation");
+    };
+    assert_eq!(network.ssid, "HomeNetwork");
+
+    app.update(Msg::CancelInput);
+    let mut networks = sample_networks();
+    networks.iter_mut().for_each(|n| n.active = false);
+    app.update(Msg::NetworksFound(networks));
+    app.update(Msg::CopyPassword);
+    assert!(matches!(state(&app), AppState::ShowingError { .. }));
+  }
+
+  #[test]
+  fn saved_passwords_are_changed_in_place() {
+    let mut app = app_with_networks();
+    app.update(Msg::ToggleDetails);
+    focus(&mut app, "HomeNetwork");
+    app.update(Msg::EditPassword);
+    let AppState::EditingPassword { network, update, .. } = state(&app) else {
+      panic!("expected the password prompt");
+    };
+    assert_eq!(network.ssid, "HomeNetwork");
+    assert!(*update);
+
+    // An empty password is never a valid change
+    app.update(Msg::SubmitConnection);
+    assert!(matches!(state(&app), AppState::EditingPassword { .. }));
+
+    // Submitting hands the password to the network layer instead of confirming a new profile
+    app.update(Msg::Paste("hunter22".to_string()));
+    app.update(Msg::SubmitConnection);
+    assert!(matches!(state(&app), AppState::Normal));
+
+    // The active connection only picks it up after reconnecting, which is offered rather than done behind its back
+    app.update(Msg::PasswordUpdated("HomeNetwork".to_string()));
+    assert!(matches!(&app, App::Running { status: Some(_), .. }));
+    let AppState::ConfirmReapply { network } = state(&app) else {
+      panic!("expected the reconnect offer");
+    };
+    assert_eq!(network.ssid, "HomeNetwork");
+    app.update(Msg::CancelInput);
+
+    // Unknown networks have no profile to update
+    focus(&mut app, "Neighbor5G");
+    app.update(Msg::EditPassword);
+    assert!(matches!(state(&app), AppState::Normal));
+  }
+
+  #[test]
+  fn turning_wifi_off_asks_only_while_connected() {
+    let mut app = app_with_networks();
+    let info = MockClient::new().get_device_info().unwrap();
+    app.update(Msg::DeviceInfoUpdate(info));
+
+    app.update(Msg::ToggleWifi);
+    let AppState::ConfirmRadioOff { network } = state(&app) else {
+      panic!("expected a confirmation");
+    };
+    assert_eq!(network.ssid, "HomeNetwork");
+    app.update(Msg::SubmitRadioOff);
+    assert!(matches!(state(&app), AppState::Normal));
+
+    let mut networks = sample_networks();
+    networks.iter_mut().for_each(|n| n.active = false);
+    app.update(Msg::NetworksFound(networks));
+    app.update(Msg::ToggleWifi);
+    assert!(matches!(state(&app), AppState::Normal));
+  }
+
+  #[test]
+  fn saved_networks_are_shared_as_qr_codes() {
+    let mut app = app_with_networks();
+    app.update(Msg::SharedPassword(
+      "HomeNetwork".to_string(),
+      Some("hunter22".to_string()),
+    ));
+    assert!(matches!(state(&app), AppState::ShowingQrCode { ssid, .. } if ssid == "HomeNetwork"));
+
+    app.update(Msg::DismissError);
+    app.update(Msg::SharedPassword("Office".to_string(), Some("hunter22".to_string())));
+    assert!(matches!(state(&app), AppState::ShowingError { .. }));
+  }
+
+  #[test]
+  fn mac_address_choice_carries_over_to_the_new_profile() {
+    let mut app = app_with_networks();
+    focus(&mut app, "CoffeeShop");
+    app.update(Msg::EnterInput);
+    app.update(Msg::SubmitConnection);
+    app.update(Msg::CycleMacAddress);
+    app.update(Msg::CycleMacAddress);
+    app.update(Msg::SubmitConnection);
+    let AppState::ConfirmNewProfile { mac_address, .. } = state(&app) else {
+      panic!("expected the profile summary");
+    };
+    assert_eq!(*mac_address, Some(MacAddressPolicy::Stable));
+  }
+
+  #[test]
+  fn sort_modes_keep_the_connected_network_first() {
+    let mut app = app_with_networks();
+    let order = |app: &App| app.visible_networks().into_iter().map(|n| n.ssid).collect::<Vec<_>>();
+    assert_eq!(
+      order(&app),
+      ["HomeNetwork", "Office", "CoffeeShop", "Neighbor5G", "OldRouter"]
+    );
+
+    // Signal -> quality -> name
+    app.update(Msg::CycleSortMode);
+    app.update(Msg::CycleSortMode);
+    assert_eq!(
+      order(&app),
+      ["HomeNetwork", "CoffeeShop", "Neighbor5G", "Office", "OldRouter"]
+    );
+
+    app.update(Msg::CycleSortMode);
+    assert_eq!(
+      order(&app),
+      ["HomeNetwork", "Neighbor5G", "Office", "CoffeeShop", "OldRouter"]
+    );
+
+    app.update(Msg::CycleSortMode);
+    assert_eq!(
+      order(&app),
+      ["HomeNetwork", "Office", "CoffeeShop", "Neighbor5G", "OldRouter"]
+    );
   }
 }
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::mock::MockClient;
+use crate::network::{ExportedProfile, NetworkClient, NmClient, WifiInfo};
+
+/// Subcommands that run without the TUI, for scripts
+pub const COMMANDS: &[&str] = &["scan", "connect", "status", "export", "import"];
+
+/// A network as printed by `weefee scan` and `weefee status`
+#[derive(Serialize)]
+struct NetworkJson<'a> {
+  ssid: &'a str,
+  /// Signal strength in percent, from the latest scan
+  strength: u8,
+  security: &'a str,
+  frequency: Option<u32>,
+  known: bool,
+  active: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  ip4: Option<Ip4Json<'a>>,
+}
+
+#[derive(Serialize)]
+struct Ip4Json<'a> {
+  addresses: &'a [String],
+  gateway: Option<&'a str>,
+  dns: &'a [String],
+}
+
+impl<'a> From<&'a WifiInfo> for NetworkJson<'a> {
+  fn from(net: &'a WifiInfo) -> Self {
+    Self {
+      ssid: &net.ssid,
+      strength: net.raw_strength,
+      security: &net.security,
+      frequency: net.frequency,
+      known: net.known,
+      active: net.active,
+      ip4: net.active_ip4.as_ref().map(|ip4| Ip4Json {
+        addresses: &ip4.addresses,
+        gateway: ip4.gateway.as_deref(),
+        dns: &ip4.dns,
+      }),
+    }
+  }
+}
+
+/// Runs `weefee <command> [args]`, printing results to stdout. Returns whether the command succeeded, for the exit
+/// code:
+///
+/// - `scan` prints every network in range as a JSON array
+/// - `connect <ssid> [password]` connects, creating a profile if the network isn't known yet
+/// - `status` prints the connected network as JSON, failing when there's none
+/// - `export <path>` writes every saved network, passwords included, to a JSON file
+/// - `import <path> [--overwrite]` saves the networks from such a file, failing if any couldn't be saved
+pub fn run(command: &str, args: &[String]) -> Result<bool> {
+  let client: Box<dyn NetworkClient> = if std::env::var("WEEFEE_MOCK").is_ok_and(|v| v == "1") {
+    Box::new(MockClient::new())
+  } else {
+    Box::new(NmClient::new()?)
+  };
+
+  match (command, args) {
+    ("scan", []) => {
+      let networks = client.get_wifi_networks()?;
+      let json: Vec<NetworkJson> = networks.iter().map(NetworkJson::from).collect();
+      println!("{}", serde_json::to_string_pretty(&json)?);
+      Ok(true)
+    }
+    ("connect", [ssid]) | ("connect", [ssid, _]) => {
+      let password = args.get(1).map_or("", String::as_str);
+      let mac_address = Config::load()?.cloned_mac_address;
+      client
+        .connect(ssid.as_bytes(), password, None, mac_address)
+        .with_context(|| format!("Failed to connect to {}", ssid))?;
+      println!("Connected to {}", ssid);
+      Ok(true)
+    }
+    ("status", []) => {
+      let networks = client.get_wifi_networks()?;
+      match networks.iter().find(|n| n.active) {
+        Some(net) => {
+          println!("{}", serde_json::to_string_pretty(&NetworkJson::from(net))?);
+          Ok(true)
+        }
+        None => {
+          println!("null");
+          Ok(false)
+        }
+      }
+    }
+    ("export", [path]) => {
+      let summary = client.export_profiles()?;
+      crate::write_private_file(path, &serde_json::to_string_pretty(&summary.profiles)?)
+        .with_context(|| format!("Failed to write {}", path))?;
+      println!("Exported {} saved networks to {}", summary.profiles.len(), path);
+      eprintln!(
+        "Warning: {} contains their passwords in plain text. Keep it private and delete it once it's served its purpose.",
+        path
+      );
+      for failure in &summary.failed {
+        eprintln!("Failed to export {}", failure);
+      }
+      Ok(summary.failed.is_empty())
+    }
+    ("import", [path]) | ("import", [path, _]) => {
+      let overwrite = match args.get(1).map(String::as_str) {
+        None => false,
+        Some("--overwrite") => true,
+        Some(flag) => anyhow::bail!("Unknown option {}", flag),
+      };
+      let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+      let profiles: Vec<ExportedProfile> =
+        serde_json::from_str(&text).with_context(|| format!("{} isn't a file written by weefee export", path))?;
+      let summary = client.import_profiles(profiles, overwrite)?;
+      println!(
+        "Imported {} saved networks, skipped {}",
+        summary.imported, summary.skipped
+      );
+      if summary.skipped > 0 && !overwrite {
+        println!("Skipped networks were already saved. Use --overwrite to replace them.");
+      }
+      for failure in &summary.failed {
+        eprintln!("Failed to import {}", failure);
+      }
+      Ok(summary.failed.is_empty())
+    }
+    _ => {
+      eprintln!(
+        "Usage: weefee [doctor | scan | connect <ssid> [password] | status | export <path> | import <path> [--overwrite]]"
+      );
+      Ok(false)
+    }
+  }
+}
@@ -0,0 +1,257 @@
+use crate::keymap::KeyMap;
+use crate::network::MacAddressPolicy;
+use anyhow::{Context, Result};
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// User configuration, read from `$XDG_CONFIG_HOME/weefee/config.toml` (usually `~/.config/weefee/config.toml`).
+/// Every field is optional so that a partial or missing config file falls back to the defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+  /// SSID of the user's "home" network, which gets a special indicator in the network list
+  pub home_network: Option<String>,
+  /// What Enter does on the network we're connected to
+  pub active_enter_action: ActiveEnterAction,
+  /// Scan results that arrive within this many milliseconds of pressing j/k are held back until navigation pauses,
+  /// so the list doesn't reorder under the cursor. 0 applies them immediately.
+  pub navigation_settle_ms: u64,
+  /// In the detail view, list known networks in autoconnect priority order (highest first) below the active one
+  pub priority_order_in_details: bool,
+  /// Ask before connecting to a network without a saved profile, even a secure one
+  pub confirm_new_connections: bool,
+  /// Known networks weaker than this (in percent) have autoconnect turned off until their signal recovers
+  pub min_autoconnect_signal: Option<u8>,
+  /// The `forget_now` key forgets the focused network without asking, offering an undo instead
+  pub fast_forget: bool,
+  /// Spin the throbber while connecting. Turning it off leaves a static "Connecting…", so nothing on screen changes
+  /// between events, which helps over slow SSH links.
+  pub animate_throbber: bool,
+  /// Show at most this many networks, the first ones in the current sort order
+  pub max_networks: Option<usize>,
+  /// Seconds between automatic rescans. 0 turns them off, leaving refreshing to the `refresh` key and to the rescan
+  /// after each action.
+  pub refresh_interval_secs: u64,
+  /// MAC address that profiles weefee creates use. Unset leaves it to NetworkManager's default, usually the adapter's
+  /// own address. The password dialog can change it per network.
+  pub cloned_mac_address: Option<MacAddressPolicy>,
+  /// How many more times to try when connecting fails for a reason other than the password, eg. on flaky access points
+  pub connect_retries: u32,
+  /// j on the last network moves to the first one, and k on the first to the last
+  pub wrap_navigation: bool,
+  /// Keys of the network list, by action under `[keybindings]`. Actions left out keep their default keys.
+  pub keybindings: KeyMap,
+  pub theme: Theme,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      home_network: None,
+      active_enter_action: ActiveEnterAction::default(),
+      navigation_settle_ms: 500,
+      priority_order_in_details: true,
+      confirm_new_connections: false,
+      min_autoconnect_signal: None,
+      fast_forget: false,
+      animate_throbber: true,
+      max_networks: None,
+      refresh_interval_secs: 1,
+      cloned_mac_address: None,
+      connect_retries: 2,
+      wrap_navigation: false,
+      keybindings: KeyMap::default(),
+      theme: Theme::default(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActiveEnterAction {
+  /// Ask whether to disconnect
+  #[default]
+  Disconnect,
+  /// Open the detail view instead
+  Details,
+  /// Do nothing, to avoid accidental disconnects
+  Ignore,
+}
+
+/// Colors of the UI's semantic roles, configured under `[theme]` as a built-in base with optional per-role overrides
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(from = "ThemeConfig")]
+pub struct Theme {
+  /// Header while connected
+  pub header_connected: Color,
+  /// Header while WiFi is on but not connected
+  pub header_disconnected: Color,
+  /// Header while connected without internet access, eg. behind a captive portal
+  pub header_no_internet: Color,
+  /// The focused network in the list
+  pub selection: Color,
+  /// The configured home network in the list
+  pub home: Color,
+  /// Confirmation dialogs and security warnings
+  pub warning: Color,
+  /// Errors, the "No" of prompts, and the header while WiFi is off
+  pub error: Color,
+  /// The "Yes" of prompts, and things that work
+  pub success: Color,
+  /// Borders of informational dialogs and the connecting throbber
+  pub dialog: Color,
+  /// Network names and keys singled out in dialogs, pending operations, and status messages
+  pub accent: Color,
+  /// Dialog text
+  pub text: Color,
+  /// Hints, list details, and whatever is behind an open dialog
+  pub dim: Color,
+}
+
+impl Theme {
+  pub const DEFAULT: Self = Self {
+    header_connected: Color::Cyan,
+    header_disconnected: Color::Rgb(255, 165, 0),
+    header_no_internet: Color::Magenta,
+    selection: Color::Yellow,
+    home: Color::Magenta,
+    warning: Color::Yellow,
+    error: Color::Red,
+    success: Color::Green,
+    dialog: Color::Cyan,
+    accent: Color::Yellow,
+    text: Color::White,
+    dim: Color::DarkGray,
+  };
+
+  /// Everything in the terminal's own foreground color, for terminals with few colors. Bold text and the header's
+  /// wording still tell the states apart. Only QR codes stay dark on light, or phones couldn't scan them.
+  pub const MONOCHROME: Self = Self {
+    header_connected: Color::Reset,
+    header_disconnected: Color::Reset,
+    header_no_internet: Color::Reset,
+    selection: Color::Reset,
+    home: Color::Reset,
+    warning: Color::Reset,
+    error: Color::Reset,
+    success: Color::Reset,
+    dialog: Color::Reset,
+    accent: Color::Reset,
+    text: Color::Reset,
+    dim: Color::Reset,
+  };
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+/// `[theme]` as written in the config file
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ThemeConfig {
+  base: ThemeBase,
+  #[serde(deserialize_with = "deserialize_color")]
+  header_connected: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  header_disconnected: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  header_no_internet: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  selection: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  home: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  warning: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  error: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  success: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  dialog: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  accent: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  text: Option<Color>,
+  #[serde(deserialize_with = "deserialize_color")]
+  dim: Option<Color>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ThemeBase {
+  #[default]
+  Default,
+  Monochrome,
+}
+
+impl From<ThemeConfig> for Theme {
+  fn from(config: ThemeConfig) -> Self {
+    let base = match config.base {
+      ThemeBase::Default => Theme::DEFAULT,
+      ThemeBase::Monochrome => Theme::MONOCHROME,
+    };
+    Self {
+      header_connected: config.header_connected.unwrap_or(base.header_connected),
+      header_disconnected: config.header_disconnected.unwrap_or(base.header_disconnected),
+      header_no_internet: config.header_no_internet.unwrap_or(base.header_no_internet),
+      selection: config.selection.unwrap_or(base.selection),
+      home: config.home.unwrap_or(base.home),
+      warning: config.warning.unwrap_or(base.warning),
+      error: config.error.unwrap_or(base.error),
+      success: config.success.unwrap_or(base.success),
+      dialog: config.dialog.unwrap_or(base.dialog),
+      accent: config.accent.unwrap_or(base.accent),
+      text: config.text.unwrap_or(base.text),
+      dim: config.dim.unwrap_or(base.dim),
+    }
+  }
+}
+
+/// Parses a color name ("cyan", "lightred"), a 256-color index ("208"), or a hex RGB value ("#ffa500")
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+  let name = String::deserialize(deserializer)?;
+  name
+    .parse()
+    .map(Some)
+    .map_err(|_| serde::de::Error::custom(format!("unknown color \"{}\"", name)))
+}
+
+impl Config {
+  pub fn load() -> Result<Self> {
+    let Some(path) = config_path() else {
+      return Ok(Self::default());
+    };
+
+    match std::fs::read_to_string(&path) {
+      Ok(contents) => toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display())),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+  }
+
+  pub fn is_home(&self, ssid: &str) -> bool {
+    self.home_network.as_deref() == Some(ssid)
+  }
+
+  pub fn navigation_settle(&self) -> Duration {
+    Duration::from_millis(self.navigation_settle_ms)
+  }
+
+  /// How often to rescan on our own, `None` for manual refresh only
+  pub fn refresh_interval(&self) -> Option<Duration> {
+    (self.refresh_interval_secs > 0).then(|| Duration::from_secs(self.refresh_interval_secs))
+  }
+}
+
+fn config_path() -> Option<PathBuf> {
+  let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+    .filter(|dir| !dir.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+  Some(config_dir.join("weefee").join("config.toml"))
+}
@@ -0,0 +1,236 @@
+use anyhow::Result;
+
+use crate::network::{NetworkClient, NmClient, ServiceUnavailable};
+
+/// Polkit permissions weefee needs, with what breaks without them
+const PERMISSIONS: &[(&str, &str)] = &[
+  (
+    "org.freedesktop.NetworkManager.network-control",
+    "connecting and disconnecting",
+  ),
+  (
+    "org.freedesktop.NetworkManager.enable-disable-wifi",
+    "turning WiFi on and off",
+  ),
+  (
+    "org.freedesktop.NetworkManager.settings.modify.system",
+    "saving and editing network profiles",
+  ),
+  ("org.freedesktop.NetworkManager.wifi.scan", "scanning for networks"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+  Pass,
+  Warn,
+  Fail,
+}
+
+struct Check {
+  status: Status,
+  name: &'static str,
+  detail: String,
+  hint: Option<String>,
+}
+
+impl Check {
+  fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+    Self {
+      status: Status::Pass,
+      name,
+      detail: detail.into(),
+      hint: None,
+    }
+  }
+
+  fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+    Self {
+      status: Status::Warn,
+      name,
+      detail: detail.into(),
+      hint: Some(hint.into()),
+    }
+  }
+
+  fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+    Self {
+      status: Status::Fail,
+      name,
+      detail: detail.into(),
+      hint: Some(hint.into()),
+    }
+  }
+
+  fn print(&self) {
+    let mark = match self.status {
+      Status::Pass => "✓",
+      Status::Warn => "!",
+      Status::Fail => "✗",
+    };
+    println!("{} {}: {}", mark, self.name, self.detail);
+    if let Some(hint) = &self.hint {
+      println!("    {}", hint);
+    }
+  }
+}
+
+/// Runs `weefee doctor`: checks everything weefee depends on and prints a checklist. Returns whether every check
+/// passed, warnings aside.
+pub fn run() -> Result<bool> {
+  let checks = checks();
+  for check in &checks {
+    check.print();
+  }
+  Ok(checks.iter().all(|c| c.status != Status::Fail))
+}
+
+fn checks() -> Vec<Check> {
+  let mut checks = vec![nmcli()];
+
+  // Everything else goes through NetworkManager on the system bus, so there's no point going on without it
+  let client = match NmClient::new() {
+    Ok(client) => client,
+    Err(e) if e.is::<ServiceUnavailable>() => {
+      checks.push(Check::pass("System bus", "connected"));
+      checks.push(Check::fail("NetworkManager", e.to_string(), ServiceUnavailable::HINT));
+      return checks;
+    }
+    Err(e) => {
+      checks.push(Check::fail(
+        "System bus",
+        format!("{:#}", e),
+        "weefee talks to NetworkManager over the D-Bus system bus. Is dbus running?",
+      ));
+      return checks;
+    }
+  };
+  checks.push(Check::pass("System bus", "connected"));
+
+  match client.nm_version() {
+    Ok((major, minor, micro)) => checks.push(Check::pass(
+      "NetworkManager",
+      format!("running, version {}.{}.{}", major, minor, micro),
+    )),
+    Err(e) => {
+      checks.push(Check::fail(
+        "NetworkManager",
+        format!("{:#}", e),
+        "Start it with `sudo systemctl start NetworkManager`.",
+      ));
+      return checks;
+    }
+  }
+
+  match client.wifi_device_status() {
+    Ok((interface, true)) => checks.push(Check::pass("WiFi device", format!("{}, managed", interface))),
+    Ok((interface, false)) => checks.push(Check::fail(
+      "WiFi device",
+      format!("{} is not managed by NetworkManager", interface),
+      format!(
+        "Hand it over with `nmcli device set {} managed yes`, and check that no other tool (eg. iwd) claims it.",
+        interface
+      ),
+    )),
+    Err(e) => {
+      checks.push(Check::fail(
+        "WiFi device",
+        format!("{:#}", e),
+        "Check that the adapter shows up in `ip link` and that its driver/firmware is loaded.",
+      ));
+      return checks;
+    }
+  }
+
+  match client.get_device_info() {
+    Ok(info) if !info.wifi_enabled => checks.push(Check::warn(
+      "WiFi radio",
+      "off",
+      "Turn it on with `nmcli radio wifi on`. If that fails, check `rfkill list` for a hardware switch.",
+    )),
+    Ok(info) if info.initializing => checks.push(Check::warn(
+      "WiFi radio",
+      "on, but the device is still initializing",
+      "Give it a few seconds and run this again.",
+    )),
+    Ok(_) => checks.push(Check::pass("WiFi radio", "on")),
+    Err(e) => checks.push(Check::fail(
+      "WiFi radio",
+      format!("{:#}", e),
+      "NetworkManager didn't report the radio state; see `nmcli general`.",
+    )),
+  }
+
+  match client.get_wifi_networks() {
+    Ok(networks) => checks.push(Check::pass(
+      "Scan",
+      format!(
+        "{} networks in range, {} of them saved",
+        networks.len(),
+        networks.iter().filter(|n| n.known).count()
+      ),
+    )),
+    Err(e) => checks.push(Check::fail(
+      "Scan",
+      format!("{:#}", e),
+      "Try `nmcli device wifi list` to see whether NetworkManager itself can scan.",
+    )),
+  }
+
+  checks.extend(permissions(&client));
+  checks
+}
+
+fn nmcli() -> Check {
+  match std::process::Command::new("nmcli").arg("--version").output() {
+    Ok(output) if output.status.success() => Check::pass("nmcli", String::from_utf8_lossy(&output.stdout).trim()),
+    Ok(output) => Check::fail(
+      "nmcli",
+      format!("exited with {}", output.status),
+      "weefee runs nmcli for some operations. Reinstall NetworkManager's command line tools.",
+    ),
+    Err(e) => Check::fail(
+      "nmcli",
+      e.to_string(),
+      "weefee runs nmcli for some operations. Install it, usually part of the NetworkManager package.",
+    ),
+  }
+}
+
+fn permissions(client: &NmClient) -> Vec<Check> {
+  let granted = match client.permissions() {
+    Ok(granted) => granted,
+    Err(e) => {
+      return vec![Check::fail(
+        "Permissions",
+        format!("{:#}", e),
+        "NetworkManager didn't say what polkit allows; see `nmcli general permissions`.",
+      )];
+    }
+  };
+
+  PERMISSIONS
+    .iter()
+    .map(|(permission, needed_for)| {
+      let name = permission.trim_start_matches("org.freedesktop.NetworkManager.");
+      match granted.get(*permission).map(String::as_str) {
+        Some("yes") => Check::pass("Permission", name),
+        Some("auth") => Check::warn(
+          "Permission",
+          format!("{} requires authentication", name),
+          format!(
+            "You'll be asked for a password when {}. A polkit rule can allow it outright.",
+            needed_for
+          ),
+        ),
+        _ => Check::fail(
+          "Permission",
+          format!("{} denied", name),
+          format!(
+            "Needed for {}. Add your user to the group your distribution's polkit rules allow (often `netdev`).",
+            needed_for
+          ),
+        ),
+      }
+    })
+    .collect()
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use crate::network::{NetworkClient, WifiInfo};
+
+/// Keeps NetworkManager from autoconnecting to known networks that are barely in reach. NetworkManager has no signal
+/// threshold for autoconnect, so we turn autoconnect off while a network is weaker than `min_signal` and back on once
+/// it recovers. Only NetworkManager's in-memory copy of the profile changes, so if weefee dies before turning it back
+/// on, the saved setting comes back with the next `nmcli connection reload` or restart.
+pub struct AutoconnectGuard {
+  min_signal: u8,
+  /// Networks whose autoconnect we turned off and still owe turning back on
+  guarded: BTreeSet<String>,
+  /// Networks whose autoconnect we failed to change and already said so, to not repeat that on every scan
+  failed: BTreeSet<String>,
+}
+
+impl AutoconnectGuard {
+  pub fn new(min_signal: u8) -> Self {
+    Self {
+      min_signal,
+      guarded: BTreeSet::new(),
+      failed: BTreeSet::new(),
+    }
+  }
+
+  pub fn guarded(&self) -> &BTreeSet<String> {
+    &self.guarded
+  }
+
+  /// Updates autoconnect on known networks according to the latest scan. Returns whether the guarded set changed, and
+  /// an error for networks whose autoconnect couldn't be changed that weren't reported before. Those are retried on
+  /// the next scan.
+  pub fn check(&mut self, client: &dyn NetworkClient, networks: &[WifiInfo]) -> (bool, Result<()>) {
+    let mut changed = false;
+    let mut failures = vec![];
+
+    // Forgotten networks have no autoconnect setting left to restore
+    let forgotten: Vec<String> = networks
+      .iter()
+      .filter(|n| !n.known && self.guarded.contains(&n.ssid))
+      .map(|n| n.ssid.clone())
+      .collect();
+    for ssid in forgotten {
+      self.guarded.remove(&ssid);
+      changed = true;
+    }
+
+    for net in networks.iter().filter(|n| n.known) {
+      // Leave the network we're on alone, it's already connected
+      let weak = !net.active && net.strength < self.min_signal;
+      let enable = match (self.guarded.contains(&net.ssid), weak) {
+        (true, false) => true,
+        // Networks the user turned autoconnect off for aren't ours to turn back on later
+        (false, true) if net.autoconnect != Some(false) => false,
+        _ => continue,
+      };
+
+      match client.set_autoconnect_temporarily(&net.ssid, enable) {
+        Ok(()) => {
+          if enable {
+            self.guarded.remove(&net.ssid);
+          } else {
+            self.guarded.insert(net.ssid.clone());
+          }
+          self.failed.remove(&net.ssid);
+          changed = true;
+        }
+        Err(e) => {
+          if self.failed.insert(net.ssid.clone()) {
+            failures.push(format!("{}: {:#}", net.ssid, e));
+          }
+        }
+      }
+    }
+
+    (changed, failures_to_result(failures))
+  }
+
+  /// Turns autoconnect back on for every guarded network, eg. before quitting. Networks where that fails stay guarded.
+  pub fn release(&mut self, client: &dyn NetworkClient) -> Result<()> {
+    let mut failures = vec![];
+    self
+      .guarded
+      .retain(|ssid| match client.set_autoconnect_temporarily(ssid, true) {
+        Ok(()) => false,
+        Err(e) => {
+          failures.push(format!("{}: {:#}", ssid, e));
+          true
+        }
+      });
+    failures_to_result(failures)
+  }
+}
+
+fn failures_to_result(failures: Vec<String>) -> Result<()> {
+  if failures.is_empty() {
+    Ok(())
+  } else {
+    // On one line, it's shown in the status bar
+    Err(anyhow::anyhow!(
+      "Failed to change autoconnect of weak networks: {}",
+      failures.join("; ")
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock::MockClient;
+
+  fn autoconnect(client: &MockClient, ssid: &str) -> Option<bool> {
+    let networks = client.get_wifi_networks().unwrap();
+    networks.into_iter().find(|n| n.ssid == ssid).unwrap().autoconnect
+  }
+
+  #[test]
+  fn weak_networks_lose_autoconnect_until_the_signal_recovers() {
+    let client = MockClient::new();
+    // Office is known at 64%, HomeNetwork is known but connected
+    let mut guard = AutoconnectGuard::new(70);
+
+    // Office starts with autoconnect off, which is the user's choice and not ours to undo later
+    assert!(!guard.check(&client, &client.get_wifi_networks().unwrap()).0);
+    assert!(guard.guarded().is_empty());
+
+    client.toggle_autoconnect("Office").unwrap();
+    assert!(guard.check(&client, &client.get_wifi_networks().unwrap()).0);
+    assert_eq!(guard.guarded().iter().collect::<Vec<_>>(), ["Office"]);
+    assert_eq!(autoconnect(&client, "Office"), Some(false));
+    assert_eq!(autoconnect(&client, "HomeNetwork"), Some(true));
+
+    let mut networks = client.get_wifi_networks().unwrap();
+    networks.iter_mut().find(|n| n.ssid == "Office").unwrap().strength = 80;
+    assert!(guard.check(&client, &networks).0);
+    assert!(guard.guarded().is_empty());
+    assert_eq!(autoconnect(&client, "Office"), Some(true));
+  }
+
+  #[test]
+  fn release_restores_autoconnect() {
+    let client = MockClient::new();
+    client.toggle_autoconnect("Office").unwrap();
+    let mut guard = AutoconnectGuard::new(70);
+    guard.check(&client, &client.get_wifi_networks().unwrap()).1.unwrap();
+
+    guard.release(&client).unwrap();
+    assert!(guard.guarded().is_empty());
+    assert_eq!(autoconnect(&client, "Office"), Some(true));
+  }
+}
@@ -0,0 +1,407 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a key can do in the network list, named as in the `[keybindings]` config section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+  MoveDown,
+  MoveUp,
+  /// Pressed twice in a row, like vim's gg
+  JumpTop,
+  JumpBottom,
+  Connect,
+  ConnectOnce,
+  ToggleDetails,
+  ToggleSelection,
+  Forget,
+  ForgetNow,
+  ForgetAll,
+  UndoForget,
+  ResetPassword,
+  EditPassword,
+  ToggleAutoconnect,
+  ToggleIgnore,
+  ShowIgnored,
+  RaiseSignalFilter,
+  LowerSignalFilter,
+  BandFilter,
+  CycleIp4Method,
+  CycleIp6Privacy,
+  TogglePowersave,
+  ToggleNeverDefault,
+  CompareSettings,
+  ToggleScanOnly,
+  SignalScanner,
+  PauseRefresh,
+  Refresh,
+  PickZone,
+  ToggleWifi,
+  ConnectivityCheck,
+  ExportList,
+  ExportProfile,
+  CopyPassword,
+  ShowQrCode,
+  CycleSort,
+  DeviceInfo,
+  SwitchDevice,
+  Help,
+  Quit,
+}
+
+/// The keys of every action unless configured otherwise. The first key is the one the footer shows.
+const DEFAULT_BINDINGS: &[(Action, &[&str])] = &[
+  (Action::MoveDown, &["down", "j"]),
+  (Action::MoveUp, &["up", "k"]),
+  (Action::JumpTop, &["g"]),
+  (Action::JumpBottom, &["G"]),
+  (Action::Connect, &["enter"]),
+  // Shift+Enter only arrives as such in terminals that report it, Alt+Enter works everywhere
+  (Action::ConnectOnce, &["alt+enter", "shift+enter"]),
+  (Action::ToggleDetails, &["d"]),
+  (Action::ToggleSelection, &["space"]),
+  (Action::Forget, &["f"]),
+  (Action::ForgetNow, &["F"]),
+  (Action::ForgetAll, &["ctrl+f"]),
+  (Action::UndoForget, &["u"]),
+  (Action::ResetPassword, &["r"]),
+  (Action::EditPassword, &["P"]),
+  (Action::ToggleAutoconnect, &["a", "A"]),
+  (Action::ToggleIgnore, &["x"]),
+  (Action::ShowIgnored, &["X"]),
+  (Action::RaiseSignalFilter, &["]"]),
+  (Action::LowerSignalFilter, &["["]),
+  (Action::BandFilter, &["b"]),
+  (Action::CycleIp4Method, &["4"]),
+  (Action::CycleIp6Privacy, &["6"]),
+  (Action::TogglePowersave, &["p"]),
+  (Action::ToggleNeverDefault, &["n"]),
+  (Action::CompareSettings, &["c"]),
+  (Action::ToggleScanOnly, &["s"]),
+  (Action::SignalScanner, &["v"]),
+  (Action::PauseRefresh, &["z"]),
+  (Action::Refresh, &["R"]),
+  (Action::PickZone, &["Z"]),
+  (Action::ToggleWifi, &["w"]),
+  (Action::ConnectivityCheck, &["C"]),
+  (Action::ExportList, &["e"]),
+  (Action::ExportProfile, &["E"]),
+  (Action::CopyPassword, &["y"]),
+  (Action::ShowQrCode, &["Q"]),
+  (Action::CycleSort, &["o"]),
+  (Action::DeviceInfo, &["i"]),
+  (Action::SwitchDevice, &["I"]),
+  (Action::Help, &["?"]),
+  (Action::Quit, &["q"]),
+];
+
+/// A key with modifiers, written like "j", "F", "ctrl+f" or "alt+enter" in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+  code: KeyCode,
+  modifiers: KeyModifiers,
+}
+
+impl KeySpec {
+  pub fn matches(&self, key: &KeyEvent) -> bool {
+    match (self.code, key.code) {
+      // Shift is already in the character (F rather than f, ? rather than /), and terminals don't agree on reporting it
+      (KeyCode::Char(expected), KeyCode::Char(pressed)) => {
+        expected == pressed && self.modifiers == key.modifiers.difference(KeyModifiers::SHIFT)
+      }
+      (expected, pressed) => expected == pressed && self.modifiers == key.modifiers,
+    }
+  }
+}
+
+impl FromStr for KeySpec {
+  type Err = String;
+
+  fn from_str(spec: &str) -> Result<Self, Self::Err> {
+    let mut parts: Vec<&str> = spec.split('+').collect();
+    // A lone "+" is the plus key, and "ctrl++" is Ctrl with it
+    if spec.ends_with("++") || spec == "+" {
+      parts.truncate(parts.len() - 2);
+      parts.push("+");
+    }
+    let (key, modifier_names) = parts.split_last().ok_or_else(|| "empty key".to_string())?;
+
+    let mut modifiers = KeyModifiers::NONE;
+    for name in modifier_names {
+      modifiers |= match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => KeyModifiers::CONTROL,
+        "alt" => KeyModifiers::ALT,
+        "shift" => KeyModifiers::SHIFT,
+        _ => return Err(format!("unknown modifier \"{}\" in \"{}\"", name, spec)),
+      };
+    }
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+      (Some(c), None) => KeyCode::Char(c),
+      _ => match key.to_ascii_lowercase().as_str() {
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+          Some(n @ 1..=12) => KeyCode::F(n),
+          _ => return Err(format!("unknown key \"{}\" in \"{}\"", key, spec)),
+        },
+      },
+    };
+
+    // Shift+f is F, which is what the terminal sends for it
+    Ok(match code {
+      KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) && c.is_alphabetic() => Self {
+        code: KeyCode::Char(c.to_ascii_uppercase()),
+        modifiers: modifiers.difference(KeyModifiers::SHIFT),
+      },
+      code => Self { code, modifiers },
+    })
+  }
+}
+
+impl fmt::Display for KeySpec {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (modifier, name) in [
+      (KeyModifiers::CONTROL, "Ctrl+"),
+      (KeyModifiers::ALT, "Alt+"),
+      (KeyModifiers::SHIFT, "Shift+"),
+    ] {
+      if self.modifiers.contains(modifier) {
+        write!(f, "{}", name)?;
+      }
+    }
+    match self.code {
+      KeyCode::Char(' ') => write!(f, "Space"),
+      KeyCode::Char(c) => write!(f, "{}", c),
+      KeyCode::Up => write!(f, "↑"),
+      KeyCode::Down => write!(f, "↓"),
+      KeyCode::Left => write!(f, "←"),
+      KeyCode::Right => write!(f, "→"),
+      code => write!(f, "{}", code),
+    }
+  }
+}
+
+/// How `action` is written in the config, eg. toggle_details
+fn config_name(action: Action) -> String {
+  let mut name = String::new();
+  for c in format!("{:?}", action).chars() {
+    if c.is_ascii_uppercase() && !name.is_empty() {
+      name.push('_');
+    }
+    name.push(c.to_ascii_lowercase());
+  }
+  name
+}
+
+/// One key or several for an action, as written in the config
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeySpecs {
+  One(String),
+  Many(Vec<String>),
+}
+
+/// Which keys do what in the network list: the defaults, with the actions from `[keybindings]` rebound
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMap {
+  bindings: Vec<(Action, Vec<KeySpec>)>,
+}
+
+impl KeyMap {
+  /// The action `key` is bound to, if any
+  pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+    self
+      .bindings
+      .iter()
+      .find(|(_action, keys)| keys.iter().any(|spec| spec.matches(key)))
+      .map(|(action, _keys)| *action)
+  }
+
+  pub fn keys(&self, action: Action) -> &[KeySpec] {
+    self
+      .bindings
+      .iter()
+      .find(|(bound, _keys)| *bound == action)
+      .map_or(&[], |(_action, keys)| keys.as_slice())
+  }
+
+  /// The first key of an action, the one the footer and hints point at, eg. "z" or "Ctrl+f". `None` if it has none.
+  pub fn first_key(&self, action: Action) -> Option<String> {
+    self.keys(action).first().map(KeySpec::to_string)
+  }
+
+  /// The keys of an action for display, eg. "↓ j", or "gg" for jumping to the top. Empty if it has none.
+  pub fn label(&self, action: Action) -> String {
+    let keys = self.keys(action).iter().map(|spec| match action {
+      Action::JumpTop => format!("{0}{0}", spec),
+      _ => spec.to_string(),
+    });
+    keys.collect::<Vec<_>>().join(" ")
+  }
+}
+
+impl Default for KeyMap {
+  fn default() -> Self {
+    Self::try_from(HashMap::new()).expect("default keybindings are valid")
+  }
+}
+
+impl TryFrom<HashMap<Action, KeySpecs>> for KeyMap {
+  type Error = String;
+
+  /// Keys bound in the config stop doing what they do by default, so eg. binding x to disconnect doesn't also need
+  /// ignoring moved elsewhere. Binding one key to two actions in the config is an error, though.
+  fn try_from(config: HashMap<Action, KeySpecs>) -> Result<Self, Self::Error> {
+    let mut configured: Vec<(Action, Vec<KeySpec>)> = vec![];
+    for (action, specs) in config {
+      let specs = match specs {
+        KeySpecs::One(spec) => vec![spec],
+        KeySpecs::Many(specs) => specs,
+      };
+      let keys = specs
+        .iter()
+        .map(|spec| spec.parse())
+        .collect::<Result<Vec<KeySpec>, _>>()?;
+      for key in &keys {
+        if let Some((other, _keys)) = configured.iter().find(|(_action, keys)| keys.contains(key)) {
+          return Err(format!(
+            "{} is bound to both {} and {}",
+            key,
+            config_name(*other),
+            config_name(action)
+          ));
+        }
+      }
+      configured.push((action, keys));
+    }
+
+    let bindings = DEFAULT_BINDINGS
+      .iter()
+      .map(
+        |(action, defaults)| match configured.iter().find(|(bound, _keys)| bound == action) {
+          Some((_action, keys)) => (*action, keys.clone()),
+          None => {
+            let keys = defaults
+              .iter()
+              .map(|spec| spec.parse().expect("default keybindings are valid"))
+              .filter(|key| !configured.iter().any(|(_action, keys)| keys.contains(key)))
+              .collect();
+            (*action, keys)
+          }
+        },
+      )
+      .collect();
+    Ok(Self { bindings })
+  }
+}
+
+impl<'de> Deserialize<'de> for KeyMap {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let config = HashMap::<Action, KeySpecs>::deserialize(deserializer)?;
+    Self::try_from(config).map_err(serde::de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+  }
+
+  fn keymap(toml: &str) -> Result<KeyMap, String> {
+    toml::from_str::<KeyMap>(toml).map_err(|e| e.message().to_string())
+  }
+
+  #[test]
+  fn key_specs() {
+    let ctrl_f: KeySpec = "ctrl+f".parse().unwrap();
+    assert!(ctrl_f.matches(&press(KeyCode::Char('f'), KeyModifiers::CONTROL)));
+    assert!(!ctrl_f.matches(&press(KeyCode::Char('f'), KeyModifiers::NONE)));
+    assert_eq!(ctrl_f.to_string(), "Ctrl+f");
+
+    // Whether the terminal reports Shift along with the character or not
+    let forget_now: KeySpec = "shift+f".parse().unwrap();
+    assert_eq!(forget_now, "F".parse().unwrap());
+    assert!(forget_now.matches(&press(KeyCode::Char('F'), KeyModifiers::SHIFT)));
+    assert!(forget_now.matches(&press(KeyCode::Char('F'), KeyModifiers::NONE)));
+
+    assert_eq!("Alt+Enter".parse::<KeySpec>().unwrap().to_string(), "Alt+Enter");
+    assert_eq!("space".parse::<KeySpec>().unwrap().to_string(), "Space");
+    assert_eq!("ctrl++".parse::<KeySpec>().unwrap().to_string(), "Ctrl++");
+    assert_eq!("f5".parse::<KeySpec>().unwrap().to_string(), "F5");
+    assert_eq!(
+      "hyper+x".parse::<KeySpec>(),
+      Err("unknown modifier \"hyper\" in \"hyper+x\"".to_string())
+    );
+    assert_eq!(
+      "ctrl+fn".parse::<KeySpec>(),
+      Err("unknown key \"fn\" in \"ctrl+fn\"".to_string())
+    );
+  }
+
+  #[test]
+  fn configured_keys_replace_defaults() {
+    let keys = keymap("toggle_ignore = \"i\"\nmove_down = [\"n\", \"down\"]\nforget_now = []").unwrap();
+    let key = |c| press(KeyCode::Char(c), KeyModifiers::NONE);
+    assert_eq!(keys.action(&key('i')), Some(Action::ToggleIgnore));
+    assert_eq!(keys.action(&key('x')), None);
+    assert_eq!(keys.action(&key('n')), Some(Action::MoveDown));
+    assert_eq!(keys.action(&key('j')), None);
+    assert_eq!(keys.action(&key('F')), None);
+    // Taken over from the device info key
+    assert!(keys.keys(Action::DeviceInfo).is_empty());
+    // And untouched
+    assert_eq!(keys.action(&key('f')), Some(Action::Forget));
+    assert_eq!(keys.label(Action::JumpTop), "gg");
+    assert_eq!(keys.label(Action::MoveDown), "n ↓");
+    // Shown as bound, not upper-cased
+    assert_eq!(keys.first_key(Action::Quit).as_deref(), Some("q"));
+    assert_eq!(keys.first_key(Action::ForgetNow), None);
+  }
+
+  #[test]
+  fn invalid_keybindings_are_rejected() {
+    assert!(
+      keymap("disconnect_all = \"x\"")
+        .unwrap_err()
+        .contains("unknown variant `disconnect_all`")
+    );
+    assert_eq!(keymap("quit = \"ctrl+\"").unwrap_err(), "unknown key \"\" in \"ctrl+\"");
+    assert!(
+      keymap("quit = \"x\"\nhelp = \"x\"")
+        .unwrap_err()
+        .contains("x is bound to both")
+    );
+  }
+
+  #[test]
+  fn defaults_are_unambiguous() {
+    let keys = KeyMap::default();
+    for (action, _specs) in DEFAULT_BINDINGS {
+      for spec in keys.keys(*action) {
+        let key = press(spec.code, spec.modifiers);
+        assert_eq!(keys.action(&key), Some(*action), "{}", spec);
+      }
+    }
+  }
+}
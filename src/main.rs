@@ -1,8 +1,14 @@
-use std::{io, time::Duration};
+use std::{
+  cell::RefCell,
+  collections::VecDeque,
+  io,
+  sync::atomic::{AtomicBool, Ordering},
+  time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::{
-  event::{self, Event, KeyCode, KeyModifiers},
+  event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyModifiers},
   execute,
   terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
@@ -10,11 +16,27 @@ use ratatui::{Terminal, backend::CrosstermBackend};
 use tokio::sync::mpsc;
 
 mod app;
+mod cli;
+mod config;
+mod doctor;
+mod guard;
+mod keymap;
+mod mock;
 mod network;
+mod qr;
+mod state;
 mod ui;
 
-use app::{App, AppState, Msg};
-use network::NetworkClient;
+use app::{App, AppState, Msg, ZONE_CHOICES, password_problem, scan_only_error};
+use config::Config;
+use guard::AutoconnectGuard;
+use keymap::Action;
+use mock::MockClient;
+use network::{
+  CancelFlag, ConnectionError, Connectivity, EnterpriseCredentials, Ip4Method, Ip6Privacy, MacAddressPolicy,
+  NetworkClient, NmClient, ProfileBackup, ServiceUnavailable,
+};
+use state::PersistentState;
 
 // TODO: can we get rid of this and use real app enums instead?
 // Simplified enum for input handling - doesn't carry state data
@@ -25,24 +47,93 @@ enum AppStateKind {
   Connecting,
   Error,
   ConfirmDisconnect,
+  ConfirmRadioOff,
+  ConfirmReapply,
   ConfirmForget,
   ConfirmWeakSecurity,
+  ConfirmNewNetwork,
+  ConfirmNewProfile,
+  CaptivePortal,
+  PickingZone,
+  ConfirmExportSecrets,
+  ConfirmRevealPassword,
+  Help,
+  DeviceInfo,
 }
 
 pub enum NetCmd {
   Scan,
-  Connect(String, String), // SSID, Password
+  // SSID as broadcast, Password, autoconnect, MAC address of new profiles
+  Connect(Vec<u8>, String, Option<bool>, Option<MacAddressPolicy>),
+  // SSID as broadcast, login, autoconnect, MAC address of the new profile
+  ConnectEnterprise(Vec<u8>, EnterpriseCredentials, Option<bool>, Option<MacAddressPolicy>),
   Disconnect,
-  Forget(String),            // SSID
-  ToggleAutoconnect(String), // SSID
+  Forget(Vec<u8>),          // SSID as broadcast
+  ForgetMany(Vec<Vec<u8>>), // SSIDs as broadcast
+  /// List what forgetting every saved network except the active one would remove, to confirm it
+  ListForgetAll,
+  ToggleAutoconnect(String),         // SSID
+  SetIp4Method(String, Ip4Method),   // SSID, new method
+  SetIp6Privacy(String, Ip6Privacy), // SSID, new value
+  SetPowersave(String, bool),        // SSID, enabled
+  SetNeverDefault(String, bool),     // SSID, never default
+  CompareSettings(String),
+  CheckCaptivePortal(String),      // SSID we just connected to
+  SetZone(String, Option<String>), // SSID, zone
+  Reactivate(Vec<u8>),             // SSID as broadcast
+  SetWifiEnabled(bool, bool),      // enabled, reconnect to the network that was up before WiFi went off
+  SelectWifiDevice(String),        // interface name
+  SetConnectivityCheck(bool),
+  SetDeviceAutoconnect(bool),
+  ForgetForNewPassword(Vec<u8>), // SSID as broadcast
+  /// Sent after raising the cancel flag, which the connection attempt itself watches. By the time this is handled
+  /// that attempt is over, so the flag can be lowered for the next one.
+  CancelConnect,
+  /// SSID and new password
+  UpdatePassword(String, String),
+  ForgetUndoable(Vec<u8>), // SSID as broadcast
+  UndoForget,
+  ExportProfile(String, bool), // SSID, include password
+  CopyPassword(String),        // SSID
+  SharePassword(String),       // SSID
+  /// Turn autoconnect back on for networks the guard turned it off for, then report back
+  ReleaseAutoconnectGuard(tokio::sync::oneshot::Sender<Result<()>>),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+  if std::env::args().nth(1).as_deref() == Some("doctor") {
+    if !doctor::run()? {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+  if let Some(command) = std::env::args()
+    .nth(1)
+    .filter(|arg| cli::COMMANDS.contains(&arg.as_str()))
+  {
+    let args: Vec<String> = std::env::args().skip(2).collect();
+    match cli::run(&command, &args) {
+      Ok(true) => return Ok(()),
+      Ok(false) => std::process::exit(1),
+      Err(e) => exit_with_error(e),
+    }
+  }
+
+  // Load config before touching the terminal so that errors are printed normally
+  let config = Config::load()?;
+  // Same for NetworkManager, whose absence would otherwise surface as a cryptic D-Bus error on every call
+  let nm_client = if std::env::var("WEEFEE_MOCK").is_ok_and(|v| v == "1") {
+    None
+  } else {
+    Some(NmClient::new().unwrap_or_else(|e| exit_with_error(e)))
+  };
+
   // Setup terminal
   enable_raw_mode()?;
   let mut stdout = io::stdout();
-  execute!(stdout, EnterAlternateScreen)?;
+  // Bracketed paste delivers a paste as one event instead of a burst of key presses, Enter included
+  execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
   let backend = CrosstermBackend::new(stdout);
   let mut terminal = Terminal::new(backend)?;
 
@@ -52,37 +143,135 @@ async fn main() -> Result<()> {
 
   // Network Task
   let tx_net = tx.clone();
+  let autoconnect_guard_threshold = config.min_autoconnect_signal;
+  let connect_retries = config.connect_retries;
+  // Raised from here to call off a connection attempt, while the network thread is busy with it
+  let connect_cancel = CancelFlag::default();
+  let net_connect_cancel = connect_cancel.clone();
+  // Vendor names only show up in the details view, so they can arrive a moment after the first draw
+  std::thread::spawn(network::load_mac_vendors);
   std::thread::spawn(move || {
     // We use std::thread because nm might use thread-local storage or glib contexts
     // that are simpler to manage in a dedicated OS thread than tokio's thread pool.
-    let client = NetworkClient::new().unwrap();
-
-    // Helpers to DRY up repeated sends
-    let rescan = || {
-      tx_net
-        .blocking_send(Msg::DeviceInfoUpdate(client.get_device_info().unwrap()))
-        .unwrap();
-      tx_net
-        .blocking_send(Msg::NetworksFound(client.get_wifi_networks().unwrap()))
-        .unwrap();
+    let client: Box<dyn NetworkClient> = match nm_client {
+      Some(nm_client) => Box::new(nm_client.with_cancel_flag(net_connect_cancel.clone())),
+      None => Box::new(MockClient::new().with_cancel_flag(net_connect_cancel.clone())),
+    };
+
+    let guard = RefCell::new(autoconnect_guard_threshold.map(AutoconnectGuard::new));
+
+    // Helpers to DRY up repeated sends. Unless `accept_empty`, an empty scan result is held back and reported as
+    // `Rescan::Empty` so the caller can retry.
+    let rescan = |accept_empty: bool| {
+      let device_info = client.get_device_info().unwrap();
+      let initializing = device_info.initializing;
+      // With the radio off the list is empty for good, show that right away
+      let accept_empty = accept_empty || !device_info.wifi_enabled;
+      tx_net.blocking_send(Msg::DeviceInfoUpdate(device_info)).unwrap();
+      if initializing {
+        // Scanning fails until the device is ready. The header says so, and the next scan picks up the networks once
+        // the device has settled.
+        return Rescan::NotReady;
+      }
+      tx_net.blocking_send(Msg::ScanStarted).unwrap();
+      let mut networks = match client.get_wifi_networks() {
+        Ok(networks) => networks,
+        Err(e) => {
+          tx_net.blocking_send(Msg::ScanFailure(e)).unwrap();
+          return Rescan::Done;
+        }
+      };
+      if networks.is_empty() && !accept_empty {
+        return Rescan::Empty;
+      }
+      if let Some(guard) = guard.borrow_mut().as_mut() {
+        let (changed, result) = guard.check(client.as_ref(), &networks);
+        if let Err(e) = result {
+          tx_net.blocking_send(Msg::AutoconnectGuardFailure(e)).unwrap();
+        }
+        if changed {
+          tx_net
+            .blocking_send(Msg::AutoconnectGuarded(guard.guarded().clone()))
+            .unwrap();
+          // Pick up the autoconnect settings the guard just changed
+          networks = client.get_wifi_networks().unwrap_or(networks);
+        }
+      }
+      tx_net.blocking_send(Msg::NetworksFound(networks)).unwrap();
+      Rescan::Done
     };
 
-    // Initial fetch
-    rescan();
+    // Initial fetch. Started right after WiFi was turned on, the device may still be coming up, so retry until it's
+    // ready rather than showing an empty list until the first periodic scan. An empty first scan usually means the
+    // device hasn't heard from any access points yet, so it's retried a few times before the list is shown empty.
+    let start = std::time::Instant::now();
+    let mut empty_retries = 0;
+    loop {
+      match rescan(empty_retries >= EMPTY_SCAN_RETRIES) {
+        Rescan::NotReady if start.elapsed() < DEVICE_READY_TIMEOUT => {
+          std::thread::sleep(Duration::from_millis(500));
+        }
+        Rescan::Empty => {
+          empty_retries += 1;
+          tx_net.blocking_send(Msg::ScanRetrying).unwrap();
+          std::thread::sleep(EMPTY_SCAN_BACKOFF * empty_retries);
+        }
+        _ => break,
+      }
+    }
+
+    // The SSID (as broadcast) of the network that was connected when the user last turned WiFi off, to bring back when
+    // it's turned on again
+    let mut ssid_before_wifi_off: Option<Vec<u8>> = None;
+
+    // The profile most recently forgotten with ForgetUndoable, until it's restored or replaced by the next one
+    let mut last_forgotten: Option<ProfileBackup> = None;
+
+    // Commands pulled off the channel early so that they run ahead of the periodic scans piled up behind a slow one
+    let mut queued: VecDeque<NetCmd> = VecDeque::new();
+
+    loop {
+      let cmd = match queued.pop_front() {
+        Some(cmd) => cmd,
+        None => match net_rx.blocking_recv() {
+          Some(cmd) => cmd,
+          None => break,
+        },
+      };
+      drain_scans(&mut net_rx, &mut queued);
+      if matches!(cmd, NetCmd::Scan) && !queued.is_empty() {
+        // A user command is waiting and will rescan anyway
+        continue;
+      }
 
-    while let Some(cmd) = net_rx.blocking_recv() {
       match cmd {
         NetCmd::Scan => {
           // We rescan after this match block
         }
-        NetCmd::Connect(ssid, password) => match client.connect(&ssid, &password) {
-          Ok(_) => {
-            tx_net.blocking_send(Msg::ConnectionSuccess).unwrap();
+        NetCmd::Connect(ssid, password, autoconnect, mac_address) => {
+          match connect_with_retries(connect_retries, &tx_net, || {
+            client.connect(&ssid, &password, autoconnect, mac_address)
+          }) {
+            Ok(_) => {
+              tx_net.blocking_send(Msg::ConnectionSuccess).unwrap();
+            }
+            Err(e) => {
+              tx_net.blocking_send(Msg::ConnectionFailure(e)).unwrap();
+            }
           }
-          Err(e) => {
-            tx_net.blocking_send(Msg::ConnectionFailure(e)).unwrap();
+        }
+        NetCmd::ConnectEnterprise(ssid, credentials, autoconnect, mac_address) => {
+          match connect_with_retries(connect_retries, &tx_net, || {
+            client.connect_enterprise(&ssid, &credentials, autoconnect, mac_address)
+          }) {
+            Ok(_) => {
+              tx_net.blocking_send(Msg::ConnectionSuccess).unwrap();
+            }
+            Err(e) => {
+              tx_net.blocking_send(Msg::ConnectionFailure(e)).unwrap();
+            }
           }
-        },
+        }
         NetCmd::Disconnect => match client.disconnect() {
           Ok(_) => {
             tx_net.blocking_send(Msg::DisconnectSuccess).unwrap();
@@ -99,6 +288,109 @@ async fn main() -> Result<()> {
             tx_net.blocking_send(Msg::ForgetFailure(e)).unwrap();
           }
         },
+        NetCmd::ForgetMany(ssids) => match client.forget_networks(&ssids) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::ForgotMany(ssids.len())).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::ForgetFailure(e)).unwrap();
+          }
+        },
+        NetCmd::ListForgetAll => {
+          let listed = client.get_wifi_networks().and_then(|networks| {
+            let keep = networks.into_iter().find(|n| n.active);
+            let ssids = client
+              .saved_ssids()?
+              .into_iter()
+              .filter(|ssid| keep.as_ref().is_none_or(|keep| *ssid != keep.ssid_bytes))
+              .collect();
+            Ok((ssids, keep.map(|n| n.ssid)))
+          });
+          match listed {
+            Ok((ssids, keep)) => tx_net.blocking_send(Msg::ConfirmForgetAll(ssids, keep)).unwrap(),
+            Err(e) => tx_net.blocking_send(Msg::ForgetFailure(e)).unwrap(),
+          }
+        }
+        NetCmd::ReleaseAutoconnectGuard(done) => {
+          let released = match guard.borrow_mut().as_mut() {
+            Some(guard) => guard.release(client.as_ref()),
+            None => Ok(()),
+          };
+          let _ = done.send(released);
+        }
+        NetCmd::CopyPassword(ssid) => {
+          let copied = client.saved_password(&ssid).and_then(|password| match password {
+            Some(password) => copy_to_clipboard(&password),
+            None => Err(anyhow::anyhow!("{} has no saved password", ssid)),
+          });
+          match copied {
+            Ok(()) => tx_net.blocking_send(Msg::PasswordCopied(ssid)).unwrap(),
+            Err(e) => tx_net
+              .blocking_send(Msg::PasswordCopyFailure(e.context("Failed to copy the password")))
+              .unwrap(),
+          }
+        }
+        NetCmd::SharePassword(ssid) => match client.saved_password(&ssid) {
+          Ok(password) => tx_net.blocking_send(Msg::SharedPassword(ssid, password)).unwrap(),
+          Err(e) => tx_net.blocking_send(Msg::ShareFailure(e)).unwrap(),
+        },
+        NetCmd::ExportProfile(ssid, include_secrets) => match client.export_profile(&ssid, include_secrets) {
+          Ok(text) => {
+            tx_net.blocking_send(Msg::ProfileExported(ssid, text)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::ProfileExportFailure(e)).unwrap();
+          }
+        },
+        NetCmd::ForgetUndoable(ssid_bytes) => {
+          let ssid = String::from_utf8_lossy(&ssid_bytes).into_owned();
+          let result = client
+            .backup_profile(&ssid)
+            .context("Not forgetting, since it couldn't be undone")
+            .and_then(|backup| client.forget_network(&ssid_bytes).map(|_| backup));
+          match result {
+            Ok(backup) => {
+              last_forgotten = Some(backup);
+              tx_net.blocking_send(Msg::ForgottenWithUndo(ssid)).unwrap();
+            }
+            Err(e) => {
+              tx_net.blocking_send(Msg::ForgetFailure(e)).unwrap();
+            }
+          }
+        }
+        NetCmd::UndoForget => {
+          if let Some(backup) = last_forgotten.take() {
+            let ssid = backup.ssid.clone();
+            match client.restore_profile(backup) {
+              Ok(()) => {
+                tx_net.blocking_send(Msg::ForgetUndone(ssid)).unwrap();
+              }
+              Err(e) => {
+                tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+              }
+            }
+          }
+        }
+        NetCmd::ForgetForNewPassword(ssid) => match client.forget_network(&ssid) {
+          Ok(_) => {
+            let ssid = String::from_utf8_lossy(&ssid).into_owned();
+            tx_net.blocking_send(Msg::PasswordForgotten(ssid)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::ForgetFailure(e)).unwrap();
+          }
+        },
+        NetCmd::CancelConnect => {
+          net_connect_cancel.clear();
+        }
+        NetCmd::UpdatePassword(ssid, password) => match client.update_password(&ssid, &password) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::PasswordUpdated(ssid)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        },
         NetCmd::ToggleAutoconnect(ssid) => match client.toggle_autoconnect(&ssid) {
           Ok(_) => {
             tx_net.blocking_send(Msg::AutoconnectSuccess).unwrap();
@@ -107,65 +399,193 @@ async fn main() -> Result<()> {
             tx_net.blocking_send(Msg::AutoconnectFailure(e)).unwrap();
           }
         },
+        NetCmd::SetIp4Method(ssid, method) => match client.set_ip4_method(&ssid, method) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::SettingsUpdateSuccess(ssid)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        },
+        NetCmd::SetIp6Privacy(ssid, value) => match client.set_ip6_privacy(&ssid, value) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::SettingsUpdateSuccess(ssid)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        },
+        NetCmd::SetPowersave(ssid, enabled) => match client.set_powersave(&ssid, enabled) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::SettingsUpdateSuccess(ssid)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        },
+        NetCmd::SetNeverDefault(ssid, never_default) => match client.set_never_default(&ssid, never_default) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::SettingsUpdateSuccess(ssid)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        },
+        NetCmd::CompareSettings(ssid) => match client.compare_settings(&ssid) {
+          Ok(comparison) => {
+            tx_net.blocking_send(Msg::SettingsCompared(ssid, comparison)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::CompareSettingsFailure(e)).unwrap();
+          }
+        },
+        NetCmd::SetZone(ssid, zone) => match client.set_zone(&ssid, zone.as_deref()) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::SettingsUpdateSuccess(ssid)).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        },
+        NetCmd::CheckCaptivePortal(ssid) => {
+          // Failing to check isn't worth interrupting the user for
+          if let Ok(Connectivity::Portal) = client.check_connectivity() {
+            tx_net.blocking_send(Msg::CaptivePortalDetected(ssid)).unwrap();
+          }
+        }
+        NetCmd::Reactivate(ssid) => match client.reactivate(&ssid) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::ConnectionSuccess).unwrap();
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::ConnectionFailure(e)).unwrap();
+          }
+        },
+        NetCmd::SetConnectivityCheck(enabled) => {
+          if let Err(e) = client.set_connectivity_check(enabled) {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        }
+        NetCmd::SetDeviceAutoconnect(enabled) => {
+          if let Err(e) = client.set_device_autoconnect(enabled) {
+            tx_net.blocking_send(Msg::SettingsUpdateFailure(e)).unwrap();
+          }
+        }
+        NetCmd::SelectWifiDevice(interface) => {
+          if let Err(e) = client.select_wifi_interface(&interface) {
+            tx_net.blocking_send(Msg::SelectDeviceFailure(e)).unwrap();
+          }
+        }
+        NetCmd::SetWifiEnabled(false, _) => {
+          ssid_before_wifi_off = client
+            .get_wifi_networks()
+            .ok()
+            .and_then(|networks| networks.into_iter().find(|n| n.active))
+            .map(|n| n.ssid_bytes);
+          match client.set_wifi_enabled(false) {
+            Ok(_) => tx_net.blocking_send(Msg::WifiToggled(false)).unwrap(),
+            Err(e) => tx_net.blocking_send(Msg::WifiToggleFailure(e)).unwrap(),
+          }
+        }
+        NetCmd::SetWifiEnabled(true, reconnect) => match client.set_wifi_enabled(true) {
+          Ok(_) => {
+            tx_net.blocking_send(Msg::WifiToggled(true)).unwrap();
+            if let Some(ssid) = ssid_before_wifi_off.take()
+              && reconnect
+              && !wait_for_autoconnect(client.as_ref())
+              && let Err(e) = client.reactivate(&ssid)
+            {
+              let e = e.context(format!(
+                "WiFi is back on, but reconnecting to {} failed",
+                String::from_utf8_lossy(&ssid)
+              ));
+              tx_net.blocking_send(Msg::WifiToggleFailure(e)).unwrap();
+            }
+          }
+          Err(e) => {
+            tx_net.blocking_send(Msg::WifiToggleFailure(e)).unwrap();
+          }
+        },
       }
 
-      // Rescan networks after sending messages to get the latest NetworkManager state.
-      rescan();
+      // Rescan networks after sending messages to get the latest NetworkManager state. If the user queued another
+      // command meanwhile, run that first; it rescans when done.
+      drain_scans(&mut net_rx, &mut queued);
+      if queued.is_empty() {
+        rescan(true);
+      }
     }
   });
 
-  // Auto-refresh Task - refresh data every second
+  // Auto-refresh Task - refresh data every `refresh_interval_secs`, unless the user paused it to read the current data
+  // or turned it off altogether
   let net_tx_refresh = net_tx.clone();
-  tokio::spawn(async move {
-    let mut interval = tokio::time::interval(Duration::from_secs(1));
-    loop {
-      interval.tick().await;
-      if net_tx_refresh.send(NetCmd::Scan).await.is_err() {
-        break;
+  let refresh_paused = std::sync::Arc::new(AtomicBool::new(false));
+  let refresh_paused_clone = refresh_paused.clone();
+  if let Some(refresh_interval) = config.refresh_interval() {
+    tokio::spawn(async move {
+      let mut interval = tokio::time::interval(refresh_interval);
+      loop {
+        interval.tick().await;
+        if refresh_paused_clone.load(Ordering::Relaxed) {
+          continue;
+        }
+        if net_tx_refresh.send(NetCmd::Scan).await.is_err() {
+          break;
+        }
       }
-    }
-  });
+    });
+  }
 
   // Input Task
   let tx_input = tx.clone();
   let app_input_state = std::sync::Arc::new(std::sync::Mutex::new(AppStateKind::Normal));
   let app_input_state_clone = app_input_state.clone();
+  let keymap = config.keybindings.clone();
 
   tokio::task::spawn_blocking(move || {
+    // When g was pressed, while waiting to see whether a second one makes it gg
+    let mut pending_g: Option<std::time::Instant> = None;
     loop {
       // Poll for events
       if event::poll(Duration::from_millis(200)).unwrap() {
-        if let Event::Key(key) = event::read().unwrap() {
+        let event = event::read().unwrap();
+        // Pasting only makes sense into the password field
+        if let Event::Paste(text) = &event
+          && *app_input_state_clone.lock().unwrap() == AppStateKind::Editing
+        {
+          tx_input.blocking_send(Msg::Paste(text.clone())).unwrap();
+        }
+        if let Event::Key(key) = event {
           let mode = *app_input_state_clone.lock().unwrap();
+          // Any key other than a second g cancels a pending gg
+          let g_pressed_at = pending_g.take();
           match mode {
-            AppStateKind::Normal => match key.code {
-              KeyCode::Char('d') => {
-                tx_input.blocking_send(Msg::DPressed).unwrap();
-              }
-              KeyCode::Char('q') => {
-                tx_input.blocking_send(Msg::Quit).unwrap();
-              }
-              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+            AppStateKind::Normal => match keymap.action(&key) {
+              // Ctrl+C quits whatever the keybindings say
+              _ if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL => {
                 tx_input.blocking_send(Msg::Quit).unwrap();
               }
-              KeyCode::Char('j') | KeyCode::Down => {
-                tx_input.blocking_send(Msg::MoveDown).unwrap();
-              }
-              KeyCode::Char('k') | KeyCode::Up => {
-                tx_input.blocking_send(Msg::MoveUp).unwrap();
-              }
-              KeyCode::Enter => {
-                tx_input.blocking_send(Msg::EnterInput).unwrap();
-              }
-              KeyCode::Char('f') => {
-                tx_input.blocking_send(Msg::ConfirmForget).unwrap();
+              Some(Action::JumpTop) => {
+                if g_pressed_at.is_some_and(|t| t.elapsed() < PENDING_KEY_TIMEOUT) {
+                  tx_input.blocking_send(Msg::JumpTop).unwrap();
+                } else {
+                  pending_g = Some(std::time::Instant::now());
+                }
               }
-              KeyCode::Char('a') | KeyCode::Char('A') => {
-                tx_input.blocking_send(Msg::ToggleAutoconnect).unwrap();
+              Some(action) => {
+                tx_input.blocking_send(action_msg(action)).unwrap();
               }
-              _ => {}
+              None => {}
             },
             AppStateKind::Editing => match key.code {
+              // Terminals that don't report Ctrl+Enter tend to send Ctrl+J for it
+              KeyCode::Enter if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::SubmitWithAutoconnect).unwrap();
+              }
+              KeyCode::Char('j') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::SubmitWithAutoconnect).unwrap();
+              }
               KeyCode::Enter => {
                 tx_input.blocking_send(Msg::SubmitConnection).unwrap();
               }
@@ -206,14 +626,70 @@ async fn main() -> Result<()> {
               KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
                 tx_input.blocking_send(Msg::Quit).unwrap();
               }
+              KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::ToggleShowPassword).unwrap();
+              }
+              KeyCode::Char('t') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::CycleEapMethod).unwrap();
+              }
+              KeyCode::Char('n') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::CycleMacAddress).unwrap();
+              }
+              // For terminals that send Ctrl+V (or Ctrl+Shift+V) as a key press rather than a bracketed paste
+              KeyCode::Char('v') | KeyCode::Char('V') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let msg = match read_clipboard() {
+                  Ok(text) => Msg::Paste(text),
+                  Err(e) => Msg::PasteFailure(e),
+                };
+                tx_input.blocking_send(msg).unwrap();
+              }
+              KeyCode::Tab | KeyCode::BackTab => {
+                tx_input.blocking_send(Msg::NextField).unwrap();
+              }
               KeyCode::Char(c) => {
                 tx_input.blocking_send(Msg::Input(c)).unwrap();
               }
               _ => {}
             },
             AppStateKind::Connecting => {
-              // Ignore input while connecting
+              if key.code == KeyCode::Esc {
+                tx_input.blocking_send(Msg::CancelConnect).unwrap();
+              }
             }
+            AppStateKind::ConfirmExportSecrets => match key.code {
+              KeyCode::Char('y') | KeyCode::Char('Y') => {
+                tx_input.blocking_send(Msg::SubmitExportProfile(true)).unwrap();
+              }
+              KeyCode::Enter | KeyCode::Char('n') | KeyCode::Char('N') => {
+                tx_input.blocking_send(Msg::SubmitExportProfile(false)).unwrap();
+              }
+              KeyCode::Esc => {
+                tx_input.blocking_send(Msg::CancelInput).unwrap();
+              }
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {}
+            },
+            AppStateKind::Help => match key.code {
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {
+                tx_input.blocking_send(Msg::DismissHelp).unwrap();
+              }
+            },
+            AppStateKind::DeviceInfo => match key.code {
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              KeyCode::Char('a') => {
+                tx_input.blocking_send(Msg::ToggleDeviceAutoconnect).unwrap();
+              }
+              _ => {
+                tx_input.blocking_send(Msg::DismissHelp).unwrap();
+              }
+            },
             AppStateKind::Error => match key.code {
               KeyCode::Enter | KeyCode::Esc => {
                 tx_input.blocking_send(Msg::DismissError).unwrap();
@@ -235,6 +711,42 @@ async fn main() -> Result<()> {
               }
               _ => {}
             },
+            AppStateKind::ConfirmRadioOff => match key.code {
+              KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+                tx_input.blocking_send(Msg::SubmitRadioOff).unwrap();
+              }
+              KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                tx_input.blocking_send(Msg::CancelInput).unwrap();
+              }
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {}
+            },
+            AppStateKind::ConfirmRevealPassword => match key.code {
+              KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+                tx_input.blocking_send(Msg::SubmitCopyPassword).unwrap();
+              }
+              KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                tx_input.blocking_send(Msg::CancelInput).unwrap();
+              }
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {}
+            },
+            AppStateKind::ConfirmReapply => match key.code {
+              KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+                tx_input.blocking_send(Msg::SubmitReapply).unwrap();
+              }
+              KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                tx_input.blocking_send(Msg::CancelInput).unwrap();
+              }
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {}
+            },
             AppStateKind::ConfirmForget => match key.code {
               KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
                 tx_input.blocking_send(Msg::SubmitForget).unwrap();
@@ -247,7 +759,49 @@ async fn main() -> Result<()> {
               }
               _ => {}
             },
-            AppStateKind::ConfirmWeakSecurity => match key.code {
+            AppStateKind::PickingZone => match key.code {
+              _ if matches!(keymap.action(&key), Some(Action::MoveDown)) => {
+                tx_input.blocking_send(Msg::MoveDown).unwrap();
+              }
+              _ if matches!(keymap.action(&key), Some(Action::MoveUp)) => {
+                tx_input.blocking_send(Msg::MoveUp).unwrap();
+              }
+              KeyCode::Enter => {
+                tx_input.blocking_send(Msg::SubmitZone).unwrap();
+              }
+              KeyCode::Esc => {
+                tx_input.blocking_send(Msg::CancelInput).unwrap();
+              }
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {}
+            },
+            AppStateKind::CaptivePortal => match key.code {
+              KeyCode::Char('o') | KeyCode::Enter => {
+                tx_input.blocking_send(Msg::OpenPortalLogin).unwrap();
+              }
+              KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                tx_input.blocking_send(Msg::CancelInput).unwrap();
+              }
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {}
+            },
+            AppStateKind::ConfirmNewProfile => match key.code {
+              KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+                tx_input.blocking_send(Msg::SubmitNewProfile).unwrap();
+              }
+              KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                tx_input.blocking_send(Msg::CancelInput).unwrap();
+              }
+              KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                tx_input.blocking_send(Msg::Quit).unwrap();
+              }
+              _ => {}
+            },
+            AppStateKind::ConfirmWeakSecurity | AppStateKind::ConfirmNewNetwork => match key.code {
               KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
                 tx_input.blocking_send(Msg::SubmitConnection).unwrap();
               }
@@ -268,7 +822,11 @@ async fn main() -> Result<()> {
   });
 
   // Main Loop
-  let mut app = App::new();
+  let first_run = !PersistentState::exists();
+  let mut app = App::new(config, PersistentState::load());
+  if first_run {
+    app.update(Msg::ShowOnboarding);
+  }
 
   loop {
     terminal.draw(|f| ui::draw(f, &mut app))?;
@@ -278,12 +836,27 @@ async fn main() -> Result<()> {
       *mode = match &app {
         App::Running { state, .. } => match state {
           AppState::Normal => AppStateKind::Normal,
-          AppState::EditingPassword { .. } => AppStateKind::Editing,
+          AppState::EditingPassword { .. } | AppState::EditingEnterprise { .. } => AppStateKind::Editing,
           AppState::Connecting { .. } => AppStateKind::Connecting,
-          AppState::ShowingError { .. } => AppStateKind::Error,
+          AppState::ShowingError { .. }
+          | AppState::ShowingInfo { .. }
+          | AppState::ShowingSettingsComparison { .. }
+          | AppState::ShowingQrCode { .. } => AppStateKind::Error,
           AppState::ConfirmDisconnect { .. } => AppStateKind::ConfirmDisconnect,
-          AppState::ConfirmForget { .. } => AppStateKind::ConfirmForget,
+          AppState::ConfirmRadioOff { .. } => AppStateKind::ConfirmRadioOff,
+          AppState::ConfirmReapply { .. } => AppStateKind::ConfirmReapply,
+          AppState::ConfirmForget { .. } | AppState::ConfirmBatchForget { .. } | AppState::ConfirmForgetAll { .. } => {
+            AppStateKind::ConfirmForget
+          }
           AppState::ConfirmWeakSecurity { .. } => AppStateKind::ConfirmWeakSecurity,
+          AppState::ConfirmNewNetwork { .. } => AppStateKind::ConfirmNewNetwork,
+          AppState::ConfirmNewProfile { .. } => AppStateKind::ConfirmNewProfile,
+          AppState::CaptivePortal { .. } => AppStateKind::CaptivePortal,
+          AppState::PickingZone { .. } => AppStateKind::PickingZone,
+          AppState::ConfirmExportSecrets { .. } => AppStateKind::ConfirmExportSecrets,
+          AppState::ConfirmRevealPassword { .. } => AppStateKind::ConfirmRevealPassword,
+          AppState::ShowingHelp { .. } => AppStateKind::Help,
+          AppState::ShowingDeviceInfo => AppStateKind::DeviceInfo,
         },
         App::ShouldQuit => AppStateKind::Normal, // Doesn't matter, we're quitting
       };
@@ -294,37 +867,135 @@ async fn main() -> Result<()> {
         Msg::Quit => {
           app = App::ShouldQuit;
         }
-        Msg::SubmitConnection => {
-          // This logic is cursed, and we should refactor the entire UI framework/setup to make this suck less
-
-          // Capture password and whether we're coming from EditingPassword BEFORE updating state
-          let (password, was_editing) = if let App::Running {
-            state: AppState::EditingPassword { password_input, .. },
+        Msg::SubmitConnection | Msg::SubmitWithAutoconnect => {
+          if let App::Running {
+            state:
+              AppState::EditingPassword {
+                network,
+                password_input,
+                update: true,
+                ..
+              },
             ..
           } = &app
+            && !password_input.value().is_empty()
+            && password_problem(network, password_input.value()).is_none()
           {
-            (password_input.value().to_string(), true)
-          } else {
-            (String::new(), false)
-          };
-
-          if let Some(net) = app.focused_network() {
-            app.update(Msg::SubmitConnection);
+            net_tx
+              .send(NetCmd::UpdatePassword(
+                network.ssid.clone(),
+                password_input.value().to_string(),
+              ))
+              .await
+              .unwrap();
+            app.update(msg);
+          } else if app.focused_network().is_some() {
+            // An enterprise login goes straight to connecting, so grab it before the dialog closes
+            let enterprise = match &app {
+              App::Running {
+                state:
+                  AppState::EditingEnterprise {
+                    method,
+                    identity_input,
+                    password_input,
+                    mac_address,
+                    ..
+                  },
+                ..
+              } => Some((
+                EnterpriseCredentials {
+                  method: *method,
+                  identity: identity_input.value().to_string(),
+                  password: password_input.value().to_string(),
+                },
+                *mac_address,
+              )),
+              _ => None,
+            };
+            app.update(msg);
 
-            // If we were editing a password, use that password
-            // Otherwise (known network or weak security confirmation), use empty password
-            // (NetworkManager will use the stored credentials)
-            if was_editing {
-              net_tx.send(NetCmd::Connect(net.ssid, password)).await.unwrap();
-            } else if let App::Running {
+            // Passwords for new networks are sent once the profile summary is confirmed. Getting here straight to
+            // Connecting means a known network, so NetworkManager will use the stored credentials, or a submitted
+            // enterprise login.
+            if let App::Running {
               state: AppState::Connecting { network, .. },
               ..
             } = &app
             {
-              net_tx
-                .send(NetCmd::Connect(network.ssid.clone(), String::new()))
-                .await
-                .unwrap();
+              let ssid = network.ssid_bytes.clone();
+              let autoconnect = app.autoconnect_choice();
+              let cmd = match enterprise {
+                Some((credentials, mac_address)) => {
+                  NetCmd::ConnectEnterprise(ssid, credentials, autoconnect, mac_address)
+                }
+                None => NetCmd::Connect(ssid, String::new(), autoconnect, None),
+              };
+              net_tx.send(cmd).await.unwrap();
+            }
+          }
+        }
+        Msg::SubmitNewProfile => {
+          if let App::Running {
+            state:
+              AppState::ConfirmNewProfile {
+                network,
+                password,
+                mac_address,
+              },
+            ..
+          } = &app
+          {
+            net_tx
+              .send(NetCmd::Connect(
+                network.ssid_bytes.clone(),
+                password.clone(),
+                app.autoconnect_choice(),
+                *mac_address,
+              ))
+              .await
+              .unwrap();
+          }
+          app.update(Msg::SubmitNewProfile);
+        }
+        Msg::SubmitReapply => {
+          if let App::Running {
+            state: AppState::ConfirmReapply { network },
+            ..
+          } = &app
+          {
+            net_tx
+              .send(NetCmd::Reactivate(network.ssid_bytes.clone()))
+              .await
+              .unwrap();
+          }
+          app.update(Msg::SubmitReapply);
+        }
+        Msg::ConnectionSuccess => {
+          // Open networks are where captive portals live, so check whether we actually got online. A cancelled attempt
+          // that got through anyway reports first, and isn't the network on screen.
+          if let App::Running {
+            state: AppState::Connecting { network, .. },
+            abandoned_connects,
+            ..
+          } = &app
+            && abandoned_connects.is_empty()
+            && network.security == "Open"
+          {
+            net_tx
+              .send(NetCmd::CheckCaptivePortal(network.ssid.clone()))
+              .await
+              .unwrap();
+          }
+          app.update(Msg::ConnectionSuccess);
+        }
+        Msg::OpenPortalLogin => {
+          // Any plain-HTTP page gets redirected to the portal's login page
+          match open_in_browser(CAPTIVE_PORTAL_PROBE_URL) {
+            Ok(()) => app.update(Msg::OpenPortalLogin),
+            Err(error) => {
+              if let App::Running { state, .. } = &mut app {
+                *state = AppState::ShowingError { error, fade: 0 };
+              }
             }
           }
         }
@@ -333,25 +1004,106 @@ async fn main() -> Result<()> {
           net_tx.send(NetCmd::Disconnect).await.unwrap();
         }
         Msg::ConfirmForget => {
-          // Only show forget dialog if the network is known
+          // Only show forget dialog if there is a multi-selection or the network is known
+          let has_selection = matches!(&app, App::Running { selected_ssids, .. } if !selected_ssids.is_empty());
+          if has_selection || app.focused_network().is_some_and(|net| net.known) {
+            app.update(Msg::ConfirmForget);
+          }
+        }
+        Msg::CancelConnect => {
+          if matches!(
+            &app,
+            App::Running {
+              state: AppState::Connecting { .. },
+              ..
+            }
+          ) {
+            connect_cancel.cancel();
+            net_tx.send(NetCmd::CancelConnect).await.unwrap();
+          }
+          app.update(Msg::CancelConnect);
+        }
+        Msg::ResetPassword => {
           if let Some(net) = app.focused_network()
+            && let App::Running {
+              state,
+              scan_only,
+              config,
+              ..
+            } = &mut app
+          {
+            if !net.active {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Re-entering the password only works on the connected network."),
+                fade: 0,
+              };
+            } else if *scan_only {
+              *state = AppState::ShowingError {
+                error: scan_only_error(config),
+                fade: 0,
+              };
+            } else {
+              // The password prompt opens once the old profile is actually gone
+              net_tx.send(NetCmd::ForgetForNewPassword(net.ssid_bytes)).await.unwrap();
+            }
+          }
+        }
+        Msg::ForgetNow => {
+          let fast_forget = matches!(&app, App::Running { config, .. } if config.fast_forget);
+          if !fast_forget {
+            // Without the opt-in, forgetting right away asks first like plain forgetting
+            let has_selection = matches!(&app, App::Running { selected_ssids, .. } if !selected_ssids.is_empty());
+            if has_selection || app.focused_network().is_some_and(|net| net.known) {
+              app.update(Msg::ConfirmForget);
+            }
+          } else if let Some(net) = app.focused_network()
             && net.known
           {
-            app.update(Msg::ConfirmForget);
+            net_tx.send(NetCmd::ForgetUndoable(net.ssid_bytes)).await.unwrap();
+            app.update(Msg::ForgetNow);
+          }
+        }
+        Msg::ForgetAllExceptActive => {
+          if matches!(
+            &app,
+            App::Running {
+              state: AppState::Normal,
+              ..
+            }
+          ) {
+            net_tx.send(NetCmd::ListForgetAll).await.unwrap();
+          }
+        }
+        Msg::UndoForget => {
+          if app.undoable_forget().is_some() {
+            net_tx.send(NetCmd::UndoForget).await.unwrap();
           }
         }
         Msg::SubmitForget => {
           // Capture network info before updating app state
-          if let Some(net) = app.focused_network()
+          if let App::Running {
+            state: AppState::ConfirmBatchForget { ssid_bytes, .. },
+            ..
+          } = &app
+          {
+            net_tx.send(NetCmd::ForgetMany(ssid_bytes.clone())).await.unwrap();
+          } else if let App::Running {
+            state: AppState::ConfirmForgetAll { ssids, .. },
+            ..
+          } = &app
+          {
+            // Exactly what was confirmed, even if profiles were added since
+            net_tx.send(NetCmd::ForgetMany(ssids.clone())).await.unwrap();
+          } else if let Some(net) = app.focused_network()
             && net.known
           {
-            net_tx.send(NetCmd::Forget(net.ssid)).await.unwrap();
+            net_tx.send(NetCmd::Forget(net.ssid_bytes)).await.unwrap();
           }
 
           app.update(Msg::SubmitForget);
         }
-        Msg::EnterInput => {
-          app.update(Msg::EnterInput);
+        Msg::EnterInput | Msg::ConnectOnce => {
+          app.update(msg);
           // If we're now in Connecting mode, it means it's a known network
           // and we should connect without asking for password
           if let App::Running {
@@ -361,7 +1113,12 @@ async fn main() -> Result<()> {
           {
             // Empty password for known networks (stored password will be used)
             net_tx
-              .send(NetCmd::Connect(network.ssid.clone(), String::new()))
+              .send(NetCmd::Connect(
+                network.ssid_bytes.clone(),
+                String::new(),
+                app.autoconnect_choice(),
+                None,
+              ))
               .await
               .unwrap();
           }
@@ -384,6 +1141,310 @@ async fn main() -> Result<()> {
               // Show error if network is not known
               *state = AppState::ShowingError {
                 error: anyhow::anyhow!("Cannot toggle auto-connect: network is not saved/known. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::ExportList => {
+          let visible_networks = app.visible_networks();
+          if let App::Running { config, state, .. } = &mut app {
+            let text = ui::network_list_text(&visible_networks, config);
+            *state = match std::fs::write(EXPORT_FILE_NAME, text) {
+              Ok(()) => AppState::ShowingInfo {
+                message: format!("Network list written to {}", EXPORT_FILE_NAME),
+              },
+              Err(e) => AppState::ShowingError {
+                error: anyhow::Error::new(e).context(format!("Failed to write {}", EXPORT_FILE_NAME)),
+                fade: 0,
+              },
+            };
+          }
+        }
+        Msg::ExportProfile => {
+          // Only export when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              app.update(Msg::ExportProfile);
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Cannot export profile: network is not saved/known. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::ShowQrCode => {
+          // Only share when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state: state @ AppState::Normal,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              net_tx.send(NetCmd::SharePassword(net.ssid)).await.unwrap();
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Only saved networks can be shared. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::SubmitExportProfile(include_secrets) => {
+          if let App::Running {
+            state: AppState::ConfirmExportSecrets { network },
+            ..
+          } = &app
+          {
+            net_tx
+              .send(NetCmd::ExportProfile(network.ssid.clone(), include_secrets))
+              .await
+              .unwrap();
+          }
+          app.update(Msg::SubmitExportProfile(include_secrets));
+        }
+        Msg::SubmitCopyPassword => {
+          if let App::Running {
+            state: AppState::ConfirmRevealPassword { network },
+            ..
+          } = &app
+          {
+            net_tx.send(NetCmd::CopyPassword(network.ssid.clone())).await.unwrap();
+          }
+          app.update(Msg::SubmitCopyPassword);
+        }
+        Msg::ProfileExported(ssid, text) => {
+          if let App::Running { state, .. } = &mut app {
+            let path = profile_file_name(&ssid);
+            *state = match write_private_file(&path, &text) {
+              Ok(()) => AppState::ShowingInfo {
+                message: format!(
+                  "Profile written to {0}. To import it: sudo install -m 600 {0} /etc/NetworkManager/system-connections/ \
+                   && sudo nmcli connection reload",
+                  path
+                ),
+              },
+              Err(e) => AppState::ShowingError {
+                error: e.context(format!("Failed to write {}", path)),
+                fade: 0,
+              },
+            };
+          }
+        }
+        Msg::PickZone => {
+          // Only pick a zone when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              app.update(Msg::PickZone);
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Cannot change firewall zone: network is not saved/known. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::SubmitZone => {
+          if let App::Running {
+            state: AppState::PickingZone { network, selected },
+            ..
+          } = &app
+          {
+            let zone = ZONE_CHOICES[*selected].map(str::to_string);
+            net_tx.send(NetCmd::SetZone(network.ssid.clone(), zone)).await.unwrap();
+          }
+          app.update(Msg::SubmitZone);
+        }
+        Msg::CycleIp4Method => {
+          // Only change the IPv4 method when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              let method = net.ip4_method.unwrap_or(Ip4Method::Auto).next();
+              app.update(Msg::CycleIp4Method);
+              net_tx.send(NetCmd::SetIp4Method(net.ssid, method)).await.unwrap();
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Cannot change IPv4 method: network is not saved/known. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::CycleIp6Privacy => {
+          // Only change IPv6 privacy when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              let value = net.ip6_privacy.unwrap_or(Ip6Privacy::Default).next();
+              app.update(Msg::CycleIp6Privacy);
+              net_tx.send(NetCmd::SetIp6Privacy(net.ssid, value)).await.unwrap();
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Cannot change IPv6 privacy: network is not saved/known. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::CompareSettings => {
+          // Only compare settings when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              net_tx.send(NetCmd::CompareSettings(net.ssid)).await.unwrap();
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Cannot compare settings: network is not saved/known. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::Refresh => {
+          net_tx.send(NetCmd::Scan).await.unwrap();
+        }
+        Msg::ToggleWifi => {
+          app.update(Msg::ToggleWifi);
+          // Ask for the opposite of what NetworkManager last reported, the header updates on the next rescan. Unless
+          // that drops the connection, which needs confirming first.
+          if let App::Running {
+            device_info: Some(info),
+            state: AppState::Normal,
+            scan_only,
+            ..
+          } = &app
+          {
+            // Scan-only mode never connects, not even back to where we were
+            net_tx
+              .send(NetCmd::SetWifiEnabled(!info.wifi_enabled, !*scan_only))
+              .await
+              .unwrap();
+          }
+        }
+        Msg::SubmitRadioOff => {
+          app.update(Msg::SubmitRadioOff);
+          net_tx.send(NetCmd::SetWifiEnabled(false, false)).await.unwrap();
+        }
+        Msg::CycleWifiDevice => {
+          // The header shows the new device once NetworkManager's answers come from it
+          if let App::Running {
+            device_info: Some(info),
+            ..
+          } = &app
+            && let Some(interface) = info.next_interface()
+          {
+            net_tx
+              .send(NetCmd::SelectWifiDevice(interface.to_string()))
+              .await
+              .unwrap();
+          }
+          app.update(Msg::CycleWifiDevice);
+        }
+        Msg::ToggleDeviceAutoconnect => {
+          if let App::Running {
+            device_info: Some(info),
+            ..
+          } = &app
+            && let Some(enabled) = info.device_autoconnect
+          {
+            net_tx.send(NetCmd::SetDeviceAutoconnect(!enabled)).await.unwrap();
+          }
+        }
+        Msg::ToggleConnectivityCheck => {
+          if let App::Running {
+            device_info: Some(info),
+            state,
+            ..
+          } = &mut app
+          {
+            match info.connectivity_check_enabled {
+              Some(enabled) => net_tx.send(NetCmd::SetConnectivityCheck(!enabled)).await.unwrap(),
+              None => {
+                *state = AppState::ShowingError {
+                  error: anyhow::anyhow!(
+                    "Connectivity checking is unavailable: NetworkManager has no check URL configured."
+                  ),
+                  fade: 0,
+                };
+              }
+            }
+          }
+        }
+        Msg::TogglePowersave => {
+          // Only change power saving when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              // Power saving is usually on by default, so the first toggle from "default" turns it off
+              let enabled = !net.powersave.unwrap_or(true);
+              app.update(Msg::TogglePowersave);
+              net_tx.send(NetCmd::SetPowersave(net.ssid, enabled)).await.unwrap();
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!("Cannot change power saving: network is not saved/known. Connect to it first."),
+                fade: 0,
+              };
+            }
+          }
+        }
+        Msg::ToggleNeverDefault => {
+          // Only change the default route setting when detail view is active
+          if let Some(net) = app.focused_network()
+            && let App::Running {
+              show_detailed_view: true,
+              state,
+              ..
+            } = &mut app
+          {
+            if net.known {
+              let never_default = !net.never_default.unwrap_or(false);
+              app.update(Msg::ToggleNeverDefault);
+              net_tx
+                .send(NetCmd::SetNeverDefault(net.ssid, never_default))
+                .await
+                .unwrap();
+            } else {
+              *state = AppState::ShowingError {
+                error: anyhow::anyhow!(
+                  "Cannot change the default route: network is not saved/known. Connect to it first."
+                ),
+                fade: 0,
               };
             }
           }
@@ -393,16 +1454,266 @@ async fn main() -> Result<()> {
         }
       }
 
+      refresh_paused.store(app.refresh_paused(), Ordering::Relaxed);
+
       if matches!(app, App::ShouldQuit) {
         break;
       }
     }
   }
 
+  // Hand autoconnect back to NetworkManager for networks the guard held back, without hanging on a stuck scan
+  let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+  let mut released = Ok(());
+  if net_tx.send(NetCmd::ReleaseAutoconnectGuard(done_tx)).await.is_ok()
+    && let Ok(Ok(result)) = tokio::time::timeout(Duration::from_secs(3), done_rx).await
+  {
+    released = result;
+  }
+
   // Restore terminal
   disable_raw_mode()?;
-  execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+  execute!(terminal.backend_mut(), DisableBracketedPaste, LeaveAlternateScreen)?;
   terminal.show_cursor()?;
 
+  // Only NetworkManager's in-memory profiles were changed, so reloading them from disk undoes what's left
+  if let Err(e) = released {
+    eprintln!(
+      "{:#}\nRun `nmcli connection reload` to restore their saved autoconnect settings.",
+      e
+    );
+  }
+
   std::process::exit(0);
 }
+
+/// Where `e` writes the plain-text network list, relative to the directory weefee was started in
+const EXPORT_FILE_NAME: &str = "weefee-networks.txt";
+
+/// Moves waiting commands into `queued`, dropping periodic scans: every command rescans afterwards, so extra scans add
+/// nothing but delay.
+fn drain_scans(net_rx: &mut mpsc::Receiver<NetCmd>, queued: &mut VecDeque<NetCmd>) {
+  while let Ok(next) = net_rx.try_recv() {
+    if !matches!(next, NetCmd::Scan) {
+      queued.push_back(next);
+    }
+  }
+}
+
+/// How long a first g waits for the second of gg
+const PENDING_KEY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What a key bound to `action` does in the network list. Jumping to the top takes two presses, so the input task
+/// handles it itself.
+fn action_msg(action: Action) -> Msg {
+  match action {
+    Action::MoveDown => Msg::MoveDown,
+    Action::MoveUp => Msg::MoveUp,
+    Action::JumpTop => Msg::JumpTop,
+    Action::JumpBottom => Msg::JumpBottom,
+    Action::Connect => Msg::EnterInput,
+    Action::ConnectOnce => Msg::ConnectOnce,
+    Action::ToggleDetails => Msg::ToggleDetails,
+    Action::ToggleSelection => Msg::ToggleSelection,
+    Action::Forget => Msg::ConfirmForget,
+    Action::ForgetNow => Msg::ForgetNow,
+    Action::ForgetAll => Msg::ForgetAllExceptActive,
+    Action::UndoForget => Msg::UndoForget,
+    Action::ResetPassword => Msg::ResetPassword,
+    Action::EditPassword => Msg::EditPassword,
+    Action::ToggleAutoconnect => Msg::ToggleAutoconnect,
+    Action::ToggleIgnore => Msg::ToggleIgnore,
+    Action::ShowIgnored => Msg::ToggleShowIgnored,
+    Action::RaiseSignalFilter => Msg::RaiseSignalFilter,
+    Action::LowerSignalFilter => Msg::LowerSignalFilter,
+    Action::BandFilter => Msg::CycleBandFilter,
+    Action::CycleIp4Method => Msg::CycleIp4Method,
+    Action::CycleIp6Privacy => Msg::CycleIp6Privacy,
+    Action::TogglePowersave => Msg::TogglePowersave,
+    Action::ToggleNeverDefault => Msg::ToggleNeverDefault,
+    Action::CompareSettings => Msg::CompareSettings,
+    Action::ToggleScanOnly => Msg::ToggleScanOnly,
+    Action::SignalScanner => Msg::ToggleSignalScanner,
+    Action::PauseRefresh => Msg::ToggleRefreshPause,
+    Action::Refresh => Msg::Refresh,
+    Action::PickZone => Msg::PickZone,
+    Action::ToggleWifi => Msg::ToggleWifi,
+    Action::ConnectivityCheck => Msg::ToggleConnectivityCheck,
+    Action::ExportList => Msg::ExportList,
+    Action::ExportProfile => Msg::ExportProfile,
+    Action::CopyPassword => Msg::CopyPassword,
+    Action::ShowQrCode => Msg::ShowQrCode,
+    Action::CycleSort => Msg::CycleSortMode,
+    Action::DeviceInfo => Msg::ShowDeviceInfo,
+    Action::SwitchDevice => Msg::CycleWifiDevice,
+    Action::Help => Msg::ShowHelp,
+    Action::Quit => Msg::Quit,
+  }
+}
+
+/// How long the initial fetch waits for a device that's still initializing
+const DEVICE_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times the initial fetch retries an empty scan before showing the list empty
+const EMPTY_SCAN_RETRIES: u32 = 3;
+
+/// How long the initial fetch waits before retrying an empty scan, multiplied by the retry number
+const EMPTY_SCAN_BACKOFF: Duration = Duration::from_millis(500);
+
+/// How a rescan went, for the initial fetch to decide whether to retry
+enum Rescan {
+  /// The device is still initializing, nothing was sent
+  NotReady,
+  /// The scan found no networks and the caller asked to hold that back, nothing was sent
+  Empty,
+  /// The networks or the scan failure were sent
+  Done,
+}
+
+/// How long NetworkManager gets to autoconnect on its own after WiFi comes back before we reconnect ourselves
+const AUTOCONNECT_GRACE: Duration = Duration::from_secs(5);
+
+/// Waits up to `AUTOCONNECT_GRACE` for any network to become active, returning whether one did.
+fn wait_for_autoconnect(client: &dyn NetworkClient) -> bool {
+  let start = std::time::Instant::now();
+  while start.elapsed() < AUTOCONNECT_GRACE {
+    if client
+      .get_wifi_networks()
+      .is_ok_and(|networks| networks.iter().any(|n| n.active))
+    {
+      return true;
+    }
+    std::thread::sleep(Duration::from_millis(500));
+  }
+  false
+}
+
+/// File name for an exported profile, in the directory weefee was started in
+fn profile_file_name(ssid: &str) -> String {
+  // SSIDs can contain anything, but not every character makes a good file name
+  let name: String = ssid
+    .chars()
+    .map(|c| {
+      if c.is_alphanumeric() || "-_.".contains(c) {
+        c
+      } else {
+        '_'
+      }
+    })
+    .collect();
+  format!("{}.nmconnection", name.trim_start_matches('.'))
+}
+
+/// Writes a file only the current user can read, since it may contain a password. NetworkManager also refuses keyfiles
+/// that others can read.
+fn write_private_file(path: &str, contents: &str) -> Result<()> {
+  use std::io::Write;
+  use std::os::unix::fs::OpenOptionsExt;
+
+  let mut file = std::fs::OpenOptions::new()
+    .write(true)
+    .create(true)
+    .truncate(true)
+    .mode(0o600)
+    .open(path)?;
+  file.write_all(contents.as_bytes())?;
+  Ok(())
+}
+
+/// A plain-HTTP site that captive portals can intercept, unlike HTTPS
+const CAPTIVE_PORTAL_PROBE_URL: &str = "http://neverssl.com";
+
+fn open_in_browser(url: &str) -> Result<()> {
+  // Detach the browser's output from our terminal UI
+  std::process::Command::new("xdg-open")
+    .arg(url)
+    .stdout(std::process::Stdio::null())
+    .stderr(std::process::Stdio::null())
+    .spawn()
+    .context("Failed to open a browser with xdg-open")?;
+  Ok(())
+}
+
+/// Runs `connect`, trying again up to `retries` times while it fails for reasons other than the password, and telling
+/// the UI which attempt is underway. New profiles are deleted after a failed attempt, so each retry starts afresh.
+fn connect_with_retries(retries: u32, tx: &mpsc::Sender<Msg>, connect: impl Fn() -> Result<()>) -> Result<()> {
+  let mut attempt = 1;
+  loop {
+    match connect() {
+      Err(e)
+        if attempt <= retries
+          && e
+            .downcast_ref::<ConnectionError>()
+            .is_some_and(ConnectionError::is_transient) =>
+      {
+        attempt += 1;
+        tx.blocking_send(Msg::ConnectionRetry(attempt)).unwrap();
+      }
+      result => return result,
+    }
+  }
+}
+
+/// Hands `text` to whichever clipboard tool is around: wl-copy on Wayland, else xclip or xsel on X11. The clipboard's
+/// contents belong to the process that set them, and these tools stay in the background to serve them, so a copied
+/// password stays pasteable after weefee quits.
+fn copy_to_clipboard(text: &str) -> Result<()> {
+  use std::io::Write;
+  use std::process::{Command, Stdio};
+
+  let tools: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+  ];
+  for (tool, args) in tools {
+    // Quiet, as the tool's output would end up all over the terminal UI
+    let Ok(mut child) = Command::new(tool)
+      .args(*args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .spawn()
+    else {
+      continue;
+    };
+    child.stdin.take().context("no stdin")?.write_all(text.as_bytes())?;
+    if child.wait()?.success() {
+      return Ok(());
+    }
+  }
+  Err(anyhow::anyhow!(
+    "No working clipboard tool found (install wl-clipboard, xclip or xsel)"
+  ))
+}
+
+/// Reads the clipboard with whichever tool is around, the counterpart to `copy_to_clipboard`
+fn read_clipboard() -> Result<String> {
+  use std::process::{Command, Stdio};
+
+  let tools: &[(&str, &[&str])] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+  ];
+  for (tool, args) in tools {
+    let Ok(output) = Command::new(tool).args(*args).stdin(Stdio::null()).output() else {
+      continue;
+    };
+    if output.status.success() {
+      return String::from_utf8(output.stdout).context("Clipboard doesn't contain text");
+    }
+  }
+  Err(anyhow::anyhow!(
+    "No working clipboard tool found (install wl-clipboard, xclip or xsel)"
+  ))
+}
+
+/// Prints an error that keeps weefee from starting, with a hint where we have one, and exits
+fn exit_with_error(e: anyhow::Error) -> ! {
+  eprintln!("weefee: {:#}", e);
+  if e.is::<ServiceUnavailable>() {
+    eprintln!("{}", ServiceUnavailable::HINT);
+  }
+  std::process::exit(1)
+}
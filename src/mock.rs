@@ -0,0 +1,549 @@
+use anyhow::{Context, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::network::{
+  ActiveIp4, CancelFlag, ConnectionError, Connectivity, DeviceCapabilities, EnterpriseCredentials, ExportSummary,
+  ExportedProfile, ImportSummary, Ip4Method, Ip6Privacy, MacAddressPolicy, NetworkClient, Persistence, ProfileBackup,
+  SettingComparison, WifiDeviceInfo, WifiInfo, import_settings,
+};
+
+/// Password that the mock accepts for every secured network it doesn't already know
+pub const MOCK_PASSWORD: &str = "password";
+
+/// The mock pretends to have two adapters, to try switching between them. Both see the same networks.
+const MOCK_INTERFACES: &[&str] = &["wlan0", "wlan1"];
+
+/// A fake network stack for developing the UI without WiFi hardware. Enable it by running with `WEEFEE_MOCK=1`.
+pub struct MockClient {
+  networks: RefCell<Vec<WifiInfo>>,
+  wifi_enabled: Cell<bool>,
+  connectivity_check_enabled: Cell<bool>,
+  device_autoconnect: Cell<bool>,
+  interface: RefCell<String>,
+  cancel: CancelFlag,
+}
+
+impl MockClient {
+  pub fn new() -> Self {
+    Self {
+      networks: RefCell::new(sample_networks()),
+      wifi_enabled: Cell::new(true),
+      connectivity_check_enabled: Cell::new(true),
+      device_autoconnect: Cell::new(true),
+      interface: RefCell::new(MOCK_INTERFACES[0].to_string()),
+      cancel: CancelFlag::default(),
+    }
+  }
+
+  pub fn with_cancel_flag(self, cancel: CancelFlag) -> Self {
+    Self { cancel, ..self }
+  }
+
+  fn with_network<T>(&self, ssid: &str, f: impl FnOnce(&mut WifiInfo) -> T) -> Result<T> {
+    let mut networks = self.networks.borrow_mut();
+    let net = networks
+      .iter_mut()
+      .find(|n| n.ssid == ssid)
+      .with_context(|| format!("Network {} not found", ssid))?;
+    Ok(f(net))
+  }
+}
+
+impl NetworkClient for MockClient {
+  fn get_device_info(&self) -> Result<WifiDeviceInfo> {
+    Ok(WifiDeviceInfo {
+      wifi_enabled: self.wifi_enabled.get(),
+      connectivity_check_enabled: Some(self.connectivity_check_enabled.get()),
+      connectivity: self.check_connectivity()?,
+      initializing: false,
+      capabilities: Some(DeviceCapabilities {
+        device: 0x3,
+        // A typical laptop card: WPA/WPA2 with TKIP and CCMP, AP and ad-hoc modes, 2.4 and 5 GHz
+        wireless: 0x7fc,
+      }),
+      device_autoconnect: Some(self.device_autoconnect.get()),
+      // A locally administered address, as NetworkManager generates when randomizing
+      hw_address: Some("5E:A1:0C:3B:77:92".to_string()),
+      permanent_hw_address: Some("A4:C3:F0:85:1D:2E".to_string()),
+      interface: Some(self.interface.borrow().clone()),
+      interfaces: MOCK_INTERFACES.iter().map(|name| name.to_string()).collect(),
+    })
+  }
+
+  fn get_wifi_networks(&self) -> Result<Vec<WifiInfo>> {
+    if !self.wifi_enabled.get() {
+      return Ok(vec![]);
+    }
+    let mut networks = self.networks.borrow().clone();
+    for net in networks.iter_mut().filter(|n| n.active) {
+      net.persistence = Some(Persistence::Saved);
+      net.active_ip4 = Some(ActiveIp4 {
+        addresses: vec!["192.168.1.23/24".to_string()],
+        gateway: Some("192.168.1.1".to_string()),
+        dns: vec!["192.168.1.1".to_string()],
+      });
+      net.bitrate = Some(433_300);
+    }
+    // Same order as NmClient: active networks first, then by strength
+    networks.sort_by(|a, b| b.active.cmp(&a.active).then(b.strength.cmp(&a.strength)));
+    Ok(networks)
+  }
+
+  fn connect(
+    &self,
+    ssid: &[u8],
+    password: &str,
+    autoconnect: Option<bool>,
+    _mac_address: Option<MacAddressPolicy>,
+  ) -> Result<()> {
+    let ssid = &*String::from_utf8_lossy(ssid);
+    // Give the UI a chance to show the connecting state, and to cancel
+    for _ in 0..10 {
+      if self.cancel.is_cancelled() {
+        return Err(ConnectionError::Cancelled.into());
+      }
+      std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let accepted = self.with_network(ssid, |net| net.known || net.weak_security || password == MOCK_PASSWORD)?;
+    if !accepted {
+      return Err(ConnectionError::IncorrectPassword.into());
+    }
+
+    for net in self.networks.borrow_mut().iter_mut() {
+      net.active = net.ssid == ssid;
+    }
+    self.with_network(ssid, |net| {
+      if !net.known {
+        net.known = true;
+        net.uuid = Some(format!("00000000-0000-4000-8000-{:012x}", net.ssid.len()));
+        net.autoconnect = Some(autoconnect.unwrap_or(true));
+      } else if autoconnect.is_some() {
+        net.autoconnect = autoconnect;
+      }
+    })
+  }
+
+  fn connect_enterprise(
+    &self,
+    ssid: &[u8],
+    credentials: &EnterpriseCredentials,
+    autoconnect: Option<bool>,
+    mac_address: Option<MacAddressPolicy>,
+  ) -> Result<()> {
+    // Any identity will do, as long as the password is the mock's
+    if credentials.identity.is_empty() || credentials.password != MOCK_PASSWORD {
+      std::thread::sleep(Duration::from_millis(500));
+      return Err(ConnectionError::IncorrectPassword.into());
+    }
+    self.connect(ssid, &credentials.password, autoconnect, mac_address)
+  }
+
+  fn reactivate(&self, ssid: &[u8]) -> Result<()> {
+    self.connect(ssid, "", None, None)
+  }
+
+  fn disconnect(&self) -> Result<()> {
+    for net in self.networks.borrow_mut().iter_mut() {
+      net.active = false;
+    }
+    Ok(())
+  }
+
+  fn forget_network(&self, ssid: &[u8]) -> Result<()> {
+    self.with_network(&String::from_utf8_lossy(ssid), |net| {
+      *net = WifiInfo {
+        ssid: net.ssid.clone(),
+        ssid_bytes: net.ssid_bytes.clone(),
+        strength: net.strength,
+        raw_strength: net.raw_strength,
+        security: net.security.clone(),
+        weak_security: net.weak_security,
+        frequency: net.frequency,
+        bssid: net.bssid.clone(),
+        ..WifiInfo::default()
+      };
+    })
+  }
+
+  fn saved_ssids(&self) -> Result<Vec<Vec<u8>>> {
+    let networks = self.networks.borrow();
+    Ok(
+      networks
+        .iter()
+        .filter(|net| net.known)
+        .map(|net| net.ssid_bytes.clone())
+        .collect(),
+    )
+  }
+
+  fn backup_profile(&self, ssid: &str) -> Result<ProfileBackup> {
+    let known = self.with_network(ssid, |net| net.known)?;
+    anyhow::ensure!(known, "No saved connection for {}", ssid);
+    Ok(ProfileBackup {
+      ssid: ssid.to_string(),
+      settings: HashMap::new(),
+    })
+  }
+
+  fn restore_profile(&self, backup: ProfileBackup) -> Result<()> {
+    self.with_network(&backup.ssid, |net| {
+      net.known = true;
+      net.uuid = Some(format!("00000000-0000-4000-8000-{:012x}", net.ssid.len()));
+      net.autoconnect = Some(true);
+    })
+  }
+
+  fn toggle_autoconnect(&self, ssid: &str) -> Result<()> {
+    self.with_network(ssid, |net| net.autoconnect = Some(!net.autoconnect.unwrap_or(true)))
+  }
+
+  fn set_autoconnect(&self, ssid: &str, enabled: bool) -> Result<()> {
+    self.with_network(ssid, |net| net.autoconnect = Some(enabled))
+  }
+
+  fn set_autoconnect_temporarily(&self, ssid: &str, enabled: bool) -> Result<()> {
+    // Nothing is saved to disk here anyway
+    self.set_autoconnect(ssid, enabled)
+  }
+
+  fn set_ip4_method(&self, ssid: &str, method: Ip4Method) -> Result<()> {
+    self.with_network(ssid, |net| net.ip4_method = Some(method))
+  }
+
+  fn set_ip6_privacy(&self, ssid: &str, value: Ip6Privacy) -> Result<()> {
+    self.with_network(ssid, |net| net.ip6_privacy = Some(value))
+  }
+
+  fn set_powersave(&self, ssid: &str, enabled: bool) -> Result<()> {
+    self.with_network(ssid, |net| net.powersave = Some(enabled))
+  }
+
+  fn set_never_default(&self, ssid: &str, never_default: bool) -> Result<()> {
+    self.with_network(ssid, |net| net.never_default = Some(never_default))
+  }
+
+  fn set_zone(&self, ssid: &str, zone: Option<&str>) -> Result<()> {
+    self.with_network(ssid, |net| net.zone = zone.map(str::to_string))
+  }
+
+  fn set_wifi_enabled(&self, enabled: bool) -> Result<()> {
+    // Like a real radio, turning it off drops the connection. Turning it back on doesn't autoconnect, so the
+    // reconnect fallback gets exercised.
+    if !enabled {
+      self.disconnect()?;
+    }
+    self.wifi_enabled.set(enabled);
+    Ok(())
+  }
+
+  fn select_wifi_interface(&self, interface: &str) -> Result<()> {
+    if !MOCK_INTERFACES.contains(&interface) {
+      anyhow::bail!("No WiFi device {}", interface);
+    }
+    *self.interface.borrow_mut() = interface.to_string();
+    Ok(())
+  }
+
+  fn set_connectivity_check(&self, enabled: bool) -> Result<()> {
+    self.connectivity_check_enabled.set(enabled);
+    Ok(())
+  }
+
+  fn set_device_autoconnect(&self, enabled: bool) -> Result<()> {
+    self.device_autoconnect.set(enabled);
+    Ok(())
+  }
+
+  fn check_connectivity(&self) -> Result<Connectivity> {
+    let networks = self.networks.borrow();
+    Ok(match networks.iter().find(|n| n.active) {
+      // The café WiFi wants you to accept its terms first
+      Some(net) if net.ssid == "CoffeeShop" => Connectivity::Portal,
+      Some(_) => Connectivity::Full,
+      None => Connectivity::None,
+    })
+  }
+
+  fn export_profile(&self, ssid: &str, include_secrets: bool) -> Result<String> {
+    self.with_network(ssid, |net| {
+      let mut text = format!(
+        "[connection]\nid={0}\ntype=802-11-wireless\n\n[802-11-wireless]\nmode=infrastructure\nssid={0}\n\n",
+        net.ssid
+      );
+      if !net.weak_security {
+        text.push_str("[802-11-wireless-security]\nkey-mgmt=wpa-psk\n");
+        if include_secrets {
+          text.push_str(&format!("psk={}\n", MOCK_PASSWORD));
+        }
+        text.push('\n');
+      }
+      text
+    })
+  }
+
+  fn saved_password(&self, ssid: &str) -> Result<Option<String>> {
+    let (known, open) = self.with_network(ssid, |net| (net.known, net.weak_security))?;
+    if !known {
+      anyhow::bail!("No saved connection for {}", ssid);
+    }
+    Ok((!open).then(|| MOCK_PASSWORD.to_string()))
+  }
+
+  fn export_profiles(&self) -> Result<ExportSummary> {
+    let networks = self.networks.borrow();
+    Ok(ExportSummary {
+      profiles: networks
+        .iter()
+        .filter(|net| net.known)
+        .map(|net| ExportedProfile {
+          ssid: net.ssid.clone(),
+          security: match net.security.as_str() {
+            "Open" => None,
+            "WEP" => Some("none".to_string()),
+            security if security.contains("Ent") => Some("wpa-eap".to_string()),
+            _ => Some("wpa-psk".to_string()),
+          },
+          password: (net.security != "Open").then(|| MOCK_PASSWORD.to_string()),
+          priority: net.priority.unwrap_or(0),
+          autoconnect: net.autoconnect.unwrap_or(true),
+        })
+        .collect(),
+      failed: Vec::new(),
+    })
+  }
+
+  fn import_profiles(&self, profiles: Vec<ExportedProfile>, overwrite: bool) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    for profile in profiles {
+      // Only networks in range exist here, so others fail to import
+      let result = self
+        .with_network(&profile.ssid, |net| -> Result<bool> {
+          if import_settings(&profile, net.known, overwrite)?.is_none() {
+            return Ok(false);
+          }
+          net.known = true;
+          net.autoconnect = Some(profile.autoconnect);
+          net.priority = Some(profile.priority);
+          Ok(true)
+        })
+        .and_then(|imported| imported);
+      match result {
+        Ok(true) => summary.imported += 1,
+        Ok(false) => summary.skipped += 1,
+        Err(e) => summary.failed.push(format!("{}: {:#}", profile.ssid, e)),
+      }
+    }
+    Ok(summary)
+  }
+
+  fn update_password(&self, ssid: &str, _password: &str) -> Result<()> {
+    // The mock accepts only its one password anyway, so there is nothing to store
+    match self.saved_password(ssid)? {
+      Some(_) => Ok(()),
+      None => anyhow::bail!("This network has no password to change"),
+    }
+  }
+
+  fn compare_settings(&self, ssid: &str) -> Result<Vec<SettingComparison>> {
+    self.with_network(ssid, |net| {
+      // Pretend the active connection still runs with DHCP from before a static address was saved
+      let applied = |value: &str| net.active.then(|| value.to_string());
+      vec![
+        SettingComparison {
+          name: "IPv4 method",
+          saved: "manual".to_string(),
+          applied: applied("auto"),
+        },
+        SettingComparison {
+          name: "Security",
+          saved: net.security.clone(),
+          applied: applied(&net.security),
+        },
+      ]
+    })
+  }
+}
+
+/// A small neighbourhood of networks covering the interesting cases: connected, known, open, WEP, and 5 GHz.
+pub fn sample_networks() -> Vec<WifiInfo> {
+  vec![
+    WifiInfo {
+      ssid: "HomeNetwork".to_string(),
+      ssid_bytes: b"HomeNetwork".to_vec(),
+      strength: 82,
+      raw_strength: 82,
+      security: "WPA2".to_string(),
+      active: true,
+      known: true,
+      uuid: Some("5f0c1a2e-8d3b-4c6a-9e71-2b4d6f8a0c13".to_string()),
+      priority: Some(10),
+      autoconnect: Some(true),
+      zone: Some("home".to_string()),
+      ip4_method: Some(Ip4Method::Auto),
+      ip6_privacy: Some(Ip6Privacy::Default),
+      never_default: Some(false),
+      frequency: Some(5180),
+      bssid: Some("3C:84:6A:1F:2E:01".to_string()),
+      ..WifiInfo::default()
+    },
+    WifiInfo {
+      ssid: "Office".to_string(),
+      ssid_bytes: b"Office".to_vec(),
+      strength: 64,
+      raw_strength: 64,
+      security: "WPA/WPA2-Ent".to_string(),
+      supports_tkip: true,
+      known: true,
+      uuid: Some("c7e2b9d4-1f6a-4b8e-a3c5-7d9e0f1a2b34".to_string()),
+      autoconnect: Some(false),
+      ip4_method: Some(Ip4Method::Auto),
+      ip6_privacy: Some(Ip6Privacy::Enabled),
+      never_default: Some(false),
+      frequency: Some(2437),
+      bssid: Some("00:18:0A:4C:7D:22".to_string()),
+      ..WifiInfo::default()
+    },
+    WifiInfo {
+      ssid: "CoffeeShop".to_string(),
+      ssid_bytes: b"CoffeeShop".to_vec(),
+      strength: 55,
+      raw_strength: 55,
+      security: "Open".to_string(),
+      weak_security: true,
+      frequency: Some(2412),
+      bssid: Some("F0:9F:C2:63:A8:10".to_string()),
+      ..WifiInfo::default()
+    },
+    WifiInfo {
+      ssid: "Neighbor5G".to_string(),
+      ssid_bytes: b"Neighbor5G".to_vec(),
+      strength: 41,
+      raw_strength: 41,
+      security: "WPA3".to_string(),
+      frequency: Some(5745),
+      bssid: Some("DE:84:6A:1F:2E:03".to_string()),
+      ..WifiInfo::default()
+    },
+    WifiInfo {
+      ssid: "OldRouter".to_string(),
+      ssid_bytes: b"OldRouter".to_vec(),
+      strength: 18,
+      raw_strength: 18,
+      security: "WEP".to_string(),
+      weak_security: true,
+      frequency: Some(2462),
+      bssid: Some("00:14:BF:9B:05:7E".to_string()),
+      ..WifiInfo::default()
+    },
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn importing_skips_saved_networks_unless_overwriting() {
+    let client = MockClient::new();
+    let profiles = client.export_profiles().unwrap().profiles;
+    assert_eq!(profiles.len(), 2);
+    client.forget_network(profiles[0].ssid.as_bytes()).unwrap();
+
+    let summary = client.import_profiles(profiles.clone(), false).unwrap();
+    assert_eq!((summary.imported, summary.skipped), (1, 1));
+    assert!(
+      client
+        .get_wifi_networks()
+        .unwrap()
+        .iter()
+        .any(|n| n.ssid == profiles[0].ssid && n.known)
+    );
+
+    // Enterprise logins can't be imported, as with NetworkManager
+    let summary = client.import_profiles(profiles.clone(), true).unwrap();
+    assert_eq!((summary.imported, summary.skipped, summary.failed.len()), (1, 0, 1));
+
+    let elsewhere = ExportedProfile {
+      ssid: "Elsewhere".to_string(),
+      ..profiles[0].clone()
+    };
+    let summary = client.import_profiles(vec![elsewhere], false).unwrap();
+    assert_eq!((summary.imported, summary.failed.len()), (0, 1));
+  }
+
+  #[test]
+  fn cancelled_connect_leaves_networks_alone() {
+    let cancel = CancelFlag::default();
+    let client = MockClient::new().with_cancel_flag(cancel.clone());
+    cancel.cancel();
+    let err = client.connect(b"Neighbor5G", MOCK_PASSWORD, None, None).unwrap_err();
+    assert!(matches!(
+      err.downcast_ref::<ConnectionError>(),
+      Some(ConnectionError::Cancelled)
+    ));
+    assert!(
+      !client
+        .get_wifi_networks()
+        .unwrap()
+        .iter()
+        .any(|n| n.ssid == "Neighbor5G" && n.known)
+    );
+
+    cancel.clear();
+    client.connect(b"Neighbor5G", MOCK_PASSWORD, None, None).unwrap();
+  }
+
+  #[test]
+  fn connect_to_unknown_network_requires_password() {
+    let client = MockClient::new();
+    let err = client.connect(b"Neighbor5G", "nope", None, None).unwrap_err();
+    assert!(matches!(
+      err.downcast_ref::<ConnectionError>(),
+      Some(ConnectionError::IncorrectPassword)
+    ));
+
+    client.connect(b"Neighbor5G", MOCK_PASSWORD, None, None).unwrap();
+    let networks = client.get_wifi_networks().unwrap();
+    assert_eq!(networks[0].ssid, "Neighbor5G");
+    assert!(networks[0].active && networks[0].known);
+    assert_eq!(networks.iter().filter(|n| n.active).count(), 1);
+  }
+
+  #[test]
+  fn forgetting_many_keeps_the_rest() {
+    let client = MockClient::new();
+    let active = client
+      .get_wifi_networks()
+      .unwrap()
+      .into_iter()
+      .find(|n| n.active)
+      .unwrap();
+    let others: Vec<Vec<u8>> = client
+      .saved_ssids()
+      .unwrap()
+      .into_iter()
+      .filter(|ssid| *ssid != active.ssid_bytes)
+      .collect();
+    assert_eq!(others.len(), 1);
+    client.forget_networks(&others).unwrap();
+    assert_eq!(client.saved_ssids().unwrap(), vec![active.ssid_bytes]);
+  }
+
+  #[test]
+  fn forget_clears_saved_settings() {
+    let client = MockClient::new();
+    client.forget_network(b"Office").unwrap();
+    let office = client
+      .get_wifi_networks()
+      .unwrap()
+      .into_iter()
+      .find(|n| n.ssid == "Office")
+      .unwrap();
+    assert!(!office.known);
+    assert_eq!(office.autoconnect, None);
+    assert_eq!(office.strength, 64);
+  }
+}
@@ -1,425 +1,3183 @@
 use anyhow::{Context, Result};
-use dbus::blocking::Connection;
+use dbus::arg::{ArgType, PropMap, RefArg, Variant, prop_cast};
+use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+use dbus::blocking::{Connection, Proxy};
 use networkmanager::NetworkManager;
 use networkmanager::devices::{Any, Device, Wireless};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+const NM_BUS: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const NM_INTERFACE: &str = "org.freedesktop.NetworkManager";
+const NM_DEVICE_INTERFACE: &str = "org.freedesktop.NetworkManager.Device";
+const NM_WIRELESS_INTERFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const NM_ACCESS_POINT_INTERFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const NM_ACTIVE_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const NM_IP4_CONFIG_INTERFACE: &str = "org.freedesktop.NetworkManager.IP4Config";
+const NM_SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+const NM_SETTINGS_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings";
+const NM_SETTINGS_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+
+const NM_DEVICE_TYPE_WIFI: u32 = 2;
+const NM_DEVICE_STATE_UNAVAILABLE: u32 = 20;
+const NM_DEVICE_STATE_FAILED: u32 = 120;
+const NM_ACTIVE_CONNECTION_STATE_ACTIVATED: u32 = 2;
+const NM_ACTIVE_CONNECTION_STATE_DEACTIVATED: u32 = 4;
+const NM_DEVICE_STATE_REASON_NO_SECRETS: u32 = 7;
+const NM_DEVICE_STATE_REASON_SUPPLICANT_DISCONNECT: u32 = 8;
+const NM_DEVICE_STATE_REASON_REMOVED: u32 = 36;
+const NM_DEVICE_STATE_REASON_CONNECTION_REMOVED: u32 = 38;
+const NM_DEVICE_STATE_REASON_USER_REQUESTED: u32 = 39;
+const NM_DEVICE_STATE_REASON_IP_ADDRESS_DUPLICATE: u32 = 64;
+
+// NMSettingsConnectionFlags
+const NM_SETTINGS_CONNECTION_FLAG_UNSAVED: u32 = 0x1;
+const NM_SETTINGS_CONNECTION_FLAG_VOLATILE: u32 = 0x4;
+
+// NMDeviceCapabilities and NMDeviceWifiCapabilities flags
+const NM_DEVICE_CAP_IS_SOFTWARE: u32 = 0x4;
+const NM_WIFI_DEVICE_CAP_CIPHER_TKIP: u32 = 0x4;
+const NM_WIFI_DEVICE_CAP_CIPHER_CCMP: u32 = 0x8;
+const NM_WIFI_DEVICE_CAP_WPA: u32 = 0x10;
+const NM_WIFI_DEVICE_CAP_RSN: u32 = 0x20;
+const NM_WIFI_DEVICE_CAP_AP: u32 = 0x40;
+const NM_WIFI_DEVICE_CAP_ADHOC: u32 = 0x80;
+const NM_WIFI_DEVICE_CAP_FREQ_VALID: u32 = 0x100;
+const NM_WIFI_DEVICE_CAP_FREQ_2GHZ: u32 = 0x200;
+const NM_WIFI_DEVICE_CAP_FREQ_5GHZ: u32 = 0x400;
+const NM_WIFI_DEVICE_CAP_FREQ_6GHZ: u32 = 0x800;
+const NM_WIFI_DEVICE_CAP_MESH: u32 = 0x1000;
+
+// NM80211ApFlags and NM80211ApSecurityFlags, see nm-dbus-interface.h
+const NM_802_11_AP_FLAGS_PRIVACY: u32 = 0x1;
+const NM_802_11_AP_SEC_PAIR_TKIP: u32 = 0x4;
+const NM_802_11_AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+const NM_802_11_AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+const NM_802_11_AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+const NM_802_11_AP_SEC_KEY_MGMT_OWE: u32 = 0x800;
+const NM_802_11_AP_SEC_KEY_MGMT_OWE_TM: u32 = 0x1000;
+const NM_802_11_AP_SEC_KEY_MGMT_EAP_SUITE_B_192: u32 = 0x2000;
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Minimum time between asking the WiFi device for a fresh scan
+const SCAN_REQUEST_INTERVAL: Duration = Duration::from_secs(10);
+/// How long to wait for a requested scan to finish before reading the cached results anyway. Scans that take longer are
+/// picked up by a later refresh.
+const SCAN_RESULTS_WAIT: Duration = Duration::from_millis(250);
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(25);
+/// How long the radio gets to come back up after turning WiFi on
+const DEVICE_AVAILABLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why an activation attempt didn't end up connected.
+#[derive(Debug)]
+pub enum ConnectionError {
+  IncorrectPassword,
+  /// Another device on the network already uses our (usually static) address, if we know it
+  IpConflict(Option<String>),
+  /// Failed with the given NMDeviceStateReason
+  Failed(u32),
+  TimedOut,
+  /// Called off through the client's `CancelFlag`
+  Cancelled,
+}
 
-#[derive(Debug, Clone)]
+impl std::fmt::Display for ConnectionError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::IncorrectPassword => write!(f, "Incorrect password"),
+      Self::IpConflict(Some(address)) => write!(f, "IP address conflict—another device is using {}", address),
+      Self::IpConflict(None) => write!(f, "IP address conflict—another device is using the same address"),
+      // The code is kept for looking things up, eg. in NetworkManager's logs
+      Self::Failed(reason) => write!(f, "Connection failed: {} (code {})", reason_to_string(*reason), reason),
+      Self::TimedOut => write!(f, "Timed out waiting for the connection to activate"),
+      Self::Cancelled => write!(f, "Connection cancelled"),
+    }
+  }
+}
+
+impl std::error::Error for ConnectionError {}
+
+impl ConnectionError {
+  /// Translates the NMDeviceStateReason a failed activation left on the device
+  fn from_state_reason(reason: u32) -> Self {
+    match reason {
+      NM_DEVICE_STATE_REASON_NO_SECRETS | NM_DEVICE_STATE_REASON_SUPPLICANT_DISCONNECT => Self::IncorrectPassword,
+      // New profiles use DHCP, so there's no address of ours to report
+      NM_DEVICE_STATE_REASON_IP_ADDRESS_DUPLICATE => Self::IpConflict(None),
+      reason => Self::Failed(reason),
+    }
+  }
+
+  /// Whether trying again might succeed, eg. after a DHCP timeout. A wrong password stays wrong, an address conflict
+  /// doesn't resolve itself within seconds, and a profile, device or user that called the attempt off meant it.
+  pub fn is_transient(&self) -> bool {
+    match self {
+      Self::Failed(
+        NM_DEVICE_STATE_REASON_REMOVED
+        | NM_DEVICE_STATE_REASON_CONNECTION_REMOVED
+        | NM_DEVICE_STATE_REASON_USER_REQUESTED,
+      ) => false,
+      Self::Failed(_) => true,
+      _ => false,
+    }
+  }
+}
+
+/// NetworkManager doesn't answer on the system bus, eg. because it's stopped or the system uses iwd or
+/// systemd-networkd instead
+#[derive(Debug)]
+pub struct ServiceUnavailable;
+
+impl ServiceUnavailable {
+  pub const HINT: &str = "weefee manages WiFi through NetworkManager. Start it with `sudo systemctl start \
+                          NetworkManager`; systems that only run iwd or systemd-networkd aren't supported.";
+}
+
+impl std::fmt::Display for ServiceUnavailable {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "NetworkManager service not available")
+  }
+}
+
+impl std::error::Error for ServiceUnavailable {}
+
+#[derive(Debug, Clone, Default)]
 pub struct WifiInfo {
+  /// The SSID for display, with bytes that aren't UTF-8 replaced
   pub ssid: String,
+  /// The SSID as broadcast, which can be any bytes (eg. Latin-1). Connecting and forgetting go by this.
+  pub ssid_bytes: Vec<u8>,
+  /// Signal strength in percent, smoothed over recent scans by the app
   pub strength: u8,
+  /// Signal strength in percent from the latest scan alone
+  pub raw_strength: u8,
   pub security: String,
   pub active: bool,
   pub weak_security: bool,
+  /// Whether the AP offers TKIP as a pairwise cipher, which a client may end up negotiating even on WPA2
+  pub supports_tkip: bool,
   pub known: bool,
+  /// UUID of the saved connection profile, as used by `nmcli connection show <uuid>`
+  pub uuid: Option<String>,
   pub priority: Option<i32>,
   pub autoconnect: Option<bool>,
   pub autoconnect_retries: Option<i32>,
+  pub ip4_method: Option<Ip4Method>,
+  pub ip6_privacy: Option<Ip6Privacy>,
+  /// `connection.metered`, where `None` means NetworkManager guesses
+  pub metered: Option<bool>,
+  /// Traffic-control (qdisc and filter) rules configured on the connection
+  pub traffic_control: Vec<String>,
+  /// `802-11-wireless.powersave`, where `None` means the global default (or NM leaving it alone)
+  pub powersave: Option<bool>,
+  /// `ipv4.never-default`/`ipv6.never-default`: the connection never gets the default route, so internet traffic goes
+  /// elsewhere. True if either is set.
+  pub never_default: Option<bool>,
+  /// firewalld zone (`connection.zone`), where `None` means firewalld's default zone
+  pub zone: Option<String>,
+  /// Whether the profile of the active connection outlives it. Only read for the active network.
+  pub persistence: Option<Persistence>,
+  /// The IPv4 configuration in use, whether configured or handed out by DHCP. Only read for the active network.
+  pub active_ip4: Option<ActiveIp4>,
+  /// The bitrate negotiated with the access point in kb/s, from the device. Only read for the active network.
+  pub bitrate: Option<u32>,
   pub frequency: Option<u32>,
+  /// MAC address of the access point (`HwAddress`), eg. "AA:BB:CC:DD:EE:FF". Of the one we're connected to for the
+  /// active network, otherwise of one of the access points sharing the SSID.
+  pub bssid: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct WifiDeviceInfo {
-  pub wifi_enabled: bool,
-}
-
-#[derive(Debug, Clone)]
-struct ConnectionInfo {
-  priority: Option<i32>,
-  autoconnect: Option<bool>,
-  autoconnect_retries: Option<i32>,
+/// The IPv4 configuration of the active connection, from its `Ip4Config` object.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActiveIp4 {
+  /// Addresses with their prefix length, eg. "192.168.1.23/24"
+  pub addresses: Vec<String>,
+  pub gateway: Option<String>,
+  /// DNS servers actually in use, as opposed to the configured `ipv4.dns`
+  pub dns: Vec<String>,
 }
 
-pub struct NetworkClient {
-  connection: Connection,
+/// How long a connection profile sticks around, from NetworkManager's settings connection flags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Persistence {
+  /// Written to disk, so it survives disconnects and reboots
+  Saved,
+  /// Only kept in memory until NetworkManager restarts
+  Unsaved,
+  /// Deleted as soon as the connection goes down
+  Volatile,
 }
 
-impl NetworkClient {
-  pub fn new() -> Result<Self> {
-    let connection = Connection::new_system().context("Failed to connect to system bus")?;
-    Ok(Self { connection })
+impl Persistence {
+  fn from_flags(flags: u32) -> Self {
+    if flags & NM_SETTINGS_CONNECTION_FLAG_VOLATILE != 0 {
+      Self::Volatile
+    } else if flags & NM_SETTINGS_CONNECTION_FLAG_UNSAVED != 0 {
+      Self::Unsaved
+    } else {
+      Self::Saved
+    }
   }
 
-  pub fn get_device_info(&self) -> Result<WifiDeviceInfo> {
-    let nm = NetworkManager::new(&self.connection);
-    let wifi_enabled = nm.wireless_enabled().context("Failed to get WiFi state")?;
-    Ok(WifiDeviceInfo { wifi_enabled })
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Saved => "saved",
+      Self::Unsaved => "temporary (until NetworkManager restarts)",
+      Self::Volatile => "temporary (removed on disconnect)",
+    }
   }
+}
 
-  pub fn get_wifi_networks(&self) -> Result<Vec<WifiInfo>> {
-    let nm = NetworkManager::new(&self.connection);
-    let devices = nm.get_devices().context("Failed to get devices")?;
-
-    // Batch load all connection info upfront to avoid repeated nmcli calls
-    let connection_info_map = self.get_all_connection_info()?;
+/// NetworkManager's view of internet reachability (NMConnectivityState).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connectivity {
+  Unknown,
+  None,
+  /// Behind a captive portal, eg. a café's login page
+  Portal,
+  Limited,
+  Full,
+}
 
-    let mut networks = Vec::new();
+impl Connectivity {
+  fn from_nm(state: u32) -> Self {
+    match state {
+      1 => Self::None,
+      2 => Self::Portal,
+      3 => Self::Limited,
+      4 => Self::Full,
+      _ => Self::Unknown,
+    }
+  }
+}
 
-    for device in devices {
-      if let Device::WiFi(wifi_device) = device {
-        // Request a scan to refresh the cache
-        let _ = wifi_device.request_scan(HashMap::new());
+/// One setting of a saved connection next to the value NetworkManager is actually using on the device.
+#[derive(Debug, Clone)]
+pub struct SettingComparison {
+  pub name: &'static str,
+  pub saved: String,
+  /// `None` when the connection isn't active, so nothing is applied
+  pub applied: Option<String>,
+}
 
-        // Small delay to allow scan results to populate
-        std::thread::sleep(Duration::from_millis(100));
+impl SettingComparison {
+  pub fn differs(&self) -> bool {
+    self.applied.as_ref().is_some_and(|applied| applied != &self.saved)
+  }
+}
 
-        // Get all access points
-        let access_points = wifi_device
-          .get_all_access_points()
-          .context("Failed to get access points")?;
+#[derive(Debug, Clone)]
+pub struct WifiDeviceInfo {
+  pub wifi_enabled: bool,
+  /// NetworkManager's global connectivity checking, or `None` when no check URL is configured so it can't run at all
+  pub connectivity_check_enabled: Option<bool>,
+  /// Whether NetworkManager last found the internet reachable. Stays `Unknown` with connectivity checking off.
+  pub connectivity: Connectivity,
+  /// WiFi is on but the device isn't ready yet, eg. right after turning it on. Scans fail until it is.
+  pub initializing: bool,
+  /// What the adapter supports, `None` if NetworkManager didn't say
+  pub capabilities: Option<DeviceCapabilities>,
+  /// Whether the device autoconnects at all. When off, no profile autoconnects on it, whatever its own setting.
+  pub device_autoconnect: Option<bool>,
+  /// The MAC address the device is using right now, randomized or not
+  pub hw_address: Option<String>,
+  /// The adapter's burned-in MAC address
+  pub permanent_hw_address: Option<String>,
+  /// Interface name of the device that scans and connections go to
+  pub interface: Option<String>,
+  /// Interface names of all WiFi devices, for switching between them
+  pub interfaces: Vec<String>,
+}
 
-        // Check if device is active
-        let is_device_active = wifi_device.state().unwrap_or(0) == 100; // 100 = ACTIVATED
+impl WifiDeviceInfo {
+  /// Whether the MAC address in use differs from the permanent one, ie. MAC randomization is in effect. `None` if
+  /// either address is unknown.
+  pub fn mac_randomized(&self) -> Option<bool> {
+    let current = self.hw_address.as_deref().filter(|mac| !mac.is_empty())?;
+    let permanent = self.permanent_hw_address.as_deref().filter(|mac| !mac.is_empty())?;
+    Some(!current.eq_ignore_ascii_case(permanent))
+  }
 
-        // Get active access point if connected
-        let active_ap = if is_device_active {
-          wifi_device.active_access_point().ok()
-        } else {
-          None
-        };
+  /// The WiFi device after the selected one, wrapping around. `None` with fewer than two devices.
+  pub fn next_interface(&self) -> Option<&str> {
+    if self.interfaces.len() < 2 {
+      return None;
+    }
+    let current = self
+      .interfaces
+      .iter()
+      .position(|name| Some(name) == self.interface.as_ref());
+    let next = current.map_or(0, |ix| (ix + 1) % self.interfaces.len());
+    Some(&self.interfaces[next])
+  }
+}
 
-        for ap in access_points {
-          let ssid = ap.ssid().unwrap_or_default();
+/// What the WiFi adapter supports, from the device's `Capabilities` and `WirelessCapabilities` bitmasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DeviceCapabilities {
+  pub device: u32,
+  pub wireless: u32,
+}
 
-          if ssid.is_empty() {
-            continue;
-          }
+impl DeviceCapabilities {
+  /// Whether the adapter can run an access point, eg. for a hotspot
+  pub fn access_point(&self) -> bool {
+    self.wireless & NM_WIFI_DEVICE_CAP_AP != 0
+  }
 
-          let strength = ap.strength().unwrap_or(0);
-          let frequency = ap.frequency().ok();
+  /// Whether the adapter can use the band. Older drivers don't report bands at all, in which case this is `None`.
+  pub fn band(&self, band: Band) -> Option<bool> {
+    if self.wireless & NM_WIFI_DEVICE_CAP_FREQ_VALID == 0 {
+      return None;
+    }
+    let flag = match band {
+      Band::Ghz2_4 => NM_WIFI_DEVICE_CAP_FREQ_2GHZ,
+      Band::Ghz5 => NM_WIFI_DEVICE_CAP_FREQ_5GHZ,
+      Band::Ghz6 => NM_WIFI_DEVICE_CAP_FREQ_6GHZ,
+    };
+    Some(self.wireless & flag != 0)
+  }
 
-          // Determine security
-          let wpa_flags = ap.wpa_flags().unwrap_or(0);
-          let rsn_flags = ap.rsn_flags().unwrap_or(0);
-          let (security, weak_security) = decode_security(wpa_flags, rsn_flags);
-
-          // Check if this AP is the active one - compare SSIDs since we don't have path method
-          let is_active = if let Some(ref active) = active_ap {
-            let active_ssid = active.ssid().unwrap_or_default();
-            ssid == active_ssid
-          } else {
-            false
-          };
+  /// Every capability worth showing, with whether the adapter has it. Missing ones are listed too, since knowing that
+  /// eg. 5 GHz isn't supported explains why those networks never show up.
+  pub fn features(&self) -> Vec<(&'static str, bool)> {
+    let has = |flag: u32| self.wireless & flag != 0;
+    let mut features: Vec<(&'static str, bool)> = [Band::Ghz2_4, Band::Ghz5, Band::Ghz6]
+      .into_iter()
+      .filter_map(|band| Some((band.label(), self.band(band)?)))
+      .collect();
+    features.extend([
+      ("WPA", has(NM_WIFI_DEVICE_CAP_WPA)),
+      ("WPA2/RSN", has(NM_WIFI_DEVICE_CAP_RSN)),
+      ("TKIP", has(NM_WIFI_DEVICE_CAP_CIPHER_TKIP)),
+      ("CCMP (AES)", has(NM_WIFI_DEVICE_CAP_CIPHER_CCMP)),
+      ("access point (hotspot)", self.access_point()),
+      ("ad-hoc", has(NM_WIFI_DEVICE_CAP_ADHOC)),
+      ("mesh", has(NM_WIFI_DEVICE_CAP_MESH)),
+    ]);
+    if self.device & NM_DEVICE_CAP_IS_SOFTWARE != 0 {
+      features.push(("software device", true));
+    }
+    features
+  }
+}
 
-          // Look up connection info from the cache
-          let (known, priority, autoconnect, autoconnect_retries) = connection_info_map
-            .get(&ssid)
-            .map(|info| (true, info.priority, info.autoconnect, info.autoconnect_retries))
-            .unwrap_or((false, None, None, None));
+/// WiFi frequency bands, slowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Band {
+  Ghz2_4,
+  Ghz5,
+  Ghz6,
+}
 
-          networks.push(WifiInfo {
-            ssid,
-            strength,
-            security,
-            active: is_active,
-            weak_security,
-            known,
-            priority,
-            autoconnect,
-            autoconnect_retries,
-            frequency,
-          });
-        }
-      }
+impl Band {
+  /// The band a channel's center frequency (in MHz) lies in
+  pub fn from_frequency(frequency: u32) -> Option<Self> {
+    match frequency {
+      2412..=2484 => Some(Self::Ghz2_4),
+      5170..=5835 => Some(Self::Ghz5),
+      5945..=7125 => Some(Self::Ghz6),
+      _ => None,
     }
+  }
 
-    // Sort by SSID first to ensure duplicates are consecutive, but put active ones first
-    networks.sort_by(|a, b| match a.ssid.cmp(&b.ssid) {
-      std::cmp::Ordering::Equal => {
-        if a.active {
-          std::cmp::Ordering::Less
-        } else if b.active {
-          std::cmp::Ordering::Greater
-        } else {
-          std::cmp::Ordering::Equal
-        }
-      }
-      other => other,
-    });
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Ghz2_4 => "2.4 GHz",
+      Self::Ghz5 => "5 GHz",
+      Self::Ghz6 => "6 GHz",
+    }
+  }
 
-    // Deduplicate - keeps the first occurrence (which is active if any duplicate is active)
-    networks.dedup_by(|a, b| a.ssid == b.ssid);
+  /// Cycles through the bands for the list filter, `None` meaning all of them
+  pub fn next(band: Option<Self>) -> Option<Self> {
+    match band {
+      None => Some(Self::Ghz2_4),
+      Some(Self::Ghz2_4) => Some(Self::Ghz5),
+      Some(Self::Ghz5) => Some(Self::Ghz6),
+      Some(Self::Ghz6) => None,
+    }
+  }
+}
 
-    // Final sort: active networks first, then by strength
-    networks.sort_by(|a, b| {
-      if a.active {
-        std::cmp::Ordering::Less
-      } else if b.active {
-        std::cmp::Ordering::Greater
-      } else {
-        b.strength.cmp(&a.strength)
-      }
-    });
+/// The channel number of a center frequency in MHz, for the 2.4, 5 and 6 GHz bands (IEEE 802.11 numbering)
+pub fn freq_to_channel(freq: u32) -> Option<u16> {
+  let channel = match freq {
+    // Japan only, and off the 5 MHz grid of the rest of the band
+    2484 => 14,
+    2412..=2472 if (freq - 2407).is_multiple_of(5) => (freq - 2407) / 5,
+    5160..=5885 if freq.is_multiple_of(5) => (freq - 5000) / 5,
+    // 6 GHz channel 2 sits below channel 1
+    5935 => 2,
+    5955..=7115 if (freq - 5950).is_multiple_of(5) => (freq - 5950) / 5,
+    _ => return None,
+  };
+  u16::try_from(channel).ok()
+}
 
-    Ok(networks)
-  }
+/// Where distributions install IEEE's registry of MAC address prefixes (OUIs), from the hwdata or ieee-data packages
+const OUI_REGISTRIES: &[&str] = &[
+  "/usr/share/hwdata/oui.txt",
+  "/usr/share/ieee-data/oui.txt",
+  "/usr/share/misc/oui.txt",
+];
+
+/// Manufacturers by OUI, filled in once by `load_mac_vendors`
+static VENDORS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Reads the system's OUI registry for `mac_vendor`. The registry runs to several megabytes, so this is meant for a
+/// background thread at startup rather than anywhere near drawing.
+pub fn load_mac_vendors() {
+  VENDORS.get_or_init(|| {
+    OUI_REGISTRIES
+      .iter()
+      .find_map(|path| std::fs::read_to_string(path).ok())
+      .map(|registry| registry.lines().filter_map(parse_oui_line).collect())
+      .unwrap_or_default()
+  });
+}
 
-  fn get_all_connection_info(&self) -> Result<HashMap<String, ConnectionInfo>> {
-    // Batch load all connection info with minimal nmcli calls
-    let mut result = HashMap::new();
+/// The manufacturer of the device with this MAC address, looked up in the system's OUI registry if there is one and
+/// `load_mac_vendors` has finished reading it. Locally administered addresses, eg. randomized ones or extra access
+/// points a router makes up, have no manufacturer.
+pub fn mac_vendor(mac: &str) -> Option<&'static str> {
+  VENDORS.get()?.get(&oui(mac)?).map(String::as_str)
+}
 
-    // Get all connection names in one call
-    let output = std::process::Command::new("nmcli")
-      .args(&["--terse", "--fields", "NAME,TYPE", "connection", "show"])
-      .output()
-      .context("Failed to execute nmcli")?;
+/// Whether a MAC address was assigned locally rather than from a manufacturer's OUI
+pub fn is_locally_administered(mac: &str) -> bool {
+  u8::from_str_radix(mac.get(..2).unwrap_or(""), 16).is_ok_and(|first| first & 0x02 != 0)
+}
 
-    if !output.status.success() {
-      return Ok(result);
-    }
+/// The OUI of a globally unique MAC address as six uppercase hex digits, eg. "AABBCC"
+fn oui(mac: &str) -> Option<String> {
+  let digits: String = mac.chars().filter(char::is_ascii_hexdigit).take(6).collect();
+  (digits.len() == 6 && !is_locally_administered(mac)).then(|| digits.to_ascii_uppercase())
+}
 
-    let connections = String::from_utf8_lossy(&output.stdout);
-    let wifi_connections: Vec<String> = connections
-      .lines()
-      .filter_map(|line| {
-        let parts: Vec<&str> = line.split(':').collect();
-        if parts.len() >= 2 && parts[1] == "802-11-wireless" {
-          Some(parts[0].to_string())
-        } else {
-          None
-        }
-      })
-      .collect();
+/// Reads an OUI and its organization from a line of IEEE's registry, eg. "00-00-0C   (hex)\t\tCisco Systems, Inc"
+fn parse_oui_line(line: &str) -> Option<(String, String)> {
+  let (prefix, organization) = line.split_once("(hex)")?;
+  let prefix: String = prefix.trim().split('-').collect();
+  let organization = organization.trim();
+  (prefix.len() == 6 && !organization.is_empty()).then(|| (prefix.to_ascii_uppercase(), organization.to_string()))
+}
 
-    if wifi_connections.is_empty() {
-      return Ok(result);
-    }
-
-    // Batch get all properties for each connection in one call per connection
-    for ssid in &wifi_connections {
-      let mut autoconnect = Some(true);
-      let mut priority = None;
-      let mut autoconnect_retries = None;
-
-      // Get all fields for this connection in one call
-      let output = std::process::Command::new("nmcli")
-        .args(&[
-          "--terse",
-          "--fields",
-          "connection.autoconnect,connection.autoconnect-priority,connection.autoconnect-retries",
-          "connection",
-          "show",
-          ssid,
-        ])
-        .output()
-        .ok();
-
-      if let Some(output) = output {
-        if output.status.success() {
-          let values = String::from_utf8_lossy(&output.stdout);
-          let lines: Vec<&str> = values.lines().collect();
-
-          // Parse autoconnect
-          if let Some(line) = lines.get(0) {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() >= 2 {
-              let value = parts[1].trim().to_lowercase();
-              autoconnect = match value.as_str() {
-                "yes" | "true" | "1" => Some(true),
-                "no" | "false" | "0" => Some(false),
-                "" => Some(true),
-                _ => Some(true),
-              };
-            }
-          }
+/// Checks a WPA-PSK password the way NetworkManager will: an 8 to 63 character passphrase, or the 64 hex digit key
+/// itself.
+pub fn validate_psk(password: &str) -> Result<(), &'static str> {
+  match password.chars().count() {
+    0..8 => Err("too short (min 8)"),
+    64 if password.chars().all(|c| c.is_ascii_hexdigit()) => Ok(()),
+    64.. => Err("too long (max 63)"),
+    _ => Ok(()),
+  }
+}
 
-          // Parse priority
-          if let Some(line) = lines.get(1) {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() >= 2 {
-              let value = parts[1].trim();
-              if !value.is_empty() {
-                priority = value.parse::<i32>().ok();
-              }
-            }
-          }
+/// Whether a network with this security label gets a `wpa-psk` profile, whose password `validate_psk` applies to.
+/// WPA3-only networks use SAE, which takes passwords of any length.
+pub fn uses_wpa_psk(security: &str) -> bool {
+  security.split('/').any(|mode| mode == "WPA" || mode == "WPA2")
+}
 
-          // Parse autoconnect-retries
-          if let Some(line) = lines.get(2) {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() >= 2 {
-              let value = parts[1].trim();
-              if !value.is_empty() {
-                autoconnect_retries = value.parse::<i32>().ok();
-              }
-            }
-          }
-        }
-      }
+/// NetworkManager's `ipv6.ip6-privacy` setting (RFC 4941 temporary addresses).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ip6Privacy {
+  /// Use the global default
+  Default,
+  /// Stable addresses only
+  Disabled,
+  /// Temporary addresses are generated but the stable address is preferred
+  Enabled,
+  /// Temporary addresses are generated and preferred
+  Preferred,
+}
 
-      result.insert(
-        ssid.clone(),
-        ConnectionInfo {
-          priority,
-          autoconnect,
-          autoconnect_retries,
-        },
-      );
+impl Ip6Privacy {
+  /// Parses nmcli's output, eg. "1 (enabled, prefer public IP)".
+  fn parse(value: &str) -> Option<Self> {
+    match value.split_whitespace().next()? {
+      "-1" => Some(Self::Default),
+      "0" => Some(Self::Disabled),
+      "1" => Some(Self::Enabled),
+      "2" => Some(Self::Preferred),
+      _ => None,
     }
+  }
 
-    Ok(result)
+  fn nmcli_value(self) -> &'static str {
+    match self {
+      Self::Default => "-1",
+      Self::Disabled => "0",
+      Self::Enabled => "1",
+      Self::Preferred => "2",
+    }
   }
 
-  fn get_connection_info(&self, ssid: &str) -> Result<(bool, Option<i32>, Option<bool>, Option<i32>)> {
-    // Simplified version for single lookups (used in connect/toggle_autoconnect)
-    let all_info = self.get_all_connection_info()?;
-    if let Some(info) = all_info.get(ssid) {
-      Ok((true, info.priority, info.autoconnect, info.autoconnect_retries))
-    } else {
-      Ok((false, None, None, None))
+  pub fn next(self) -> Self {
+    match self {
+      Self::Default => Self::Disabled,
+      Self::Disabled => Self::Enabled,
+      Self::Enabled => Self::Preferred,
+      Self::Preferred => Self::Default,
     }
   }
 
-  pub fn connect(&self, ssid: &str, password: &str) -> Result<()> {
-    let nm = NetworkManager::new(&self.connection);
-    let devices = nm.get_devices().context("Failed to get devices")?;
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Default => "default",
+      Self::Disabled => "disabled",
+      Self::Enabled => "enabled",
+      Self::Preferred => "preferred",
+    }
+  }
+}
 
-    // Find the WiFi device to ensure it exists
-    let _wifi_device = devices
-      .into_iter()
-      .find_map(|d| if let Device::WiFi(w) = d { Some(w) } else { None })
-      .context("No WiFi device found")?;
+/// NetworkManager's `ipv4.method` setting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ip4Method {
+  /// DHCP
+  Auto,
+  /// Static addresses from `ipv4.addresses`
+  Manual,
+  /// Self-assigned 169.254.x.x address (zeroconf), for talking directly to eg. cameras and printers
+  LinkLocal,
+  /// Sharing this connection with other devices
+  Shared,
+  Disabled,
+}
 
-    // Check if this is a known network
-    let (known, _, _, _) = self.get_connection_info(ssid)?;
+impl Ip4Method {
+  fn parse(value: &str) -> Option<Self> {
+    match value {
+      "auto" => Some(Self::Auto),
+      "manual" => Some(Self::Manual),
+      "link-local" => Some(Self::LinkLocal),
+      "shared" => Some(Self::Shared),
+      "disabled" => Some(Self::Disabled),
+      _ => None,
+    }
+  }
 
-    if known {
-      // Known network - use nmcli to activate (networkmanager-rs doesn't expose easy activation API)
-      let output = std::process::Command::new("nmcli")
-        .args(&["connection", "up", ssid])
-        .output()
-        .context("Failed to execute nmcli")?;
+  fn nmcli_value(self) -> &'static str {
+    match self {
+      Self::Auto => "auto",
+      Self::Manual => "manual",
+      Self::LinkLocal => "link-local",
+      Self::Shared => "shared",
+      Self::Disabled => "disabled",
+    }
+  }
 
-      if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if stderr.contains("AlreadyActive") || stderr.contains("already active") {
-          std::thread::sleep(Duration::from_millis(500));
-          return Ok(());
-        }
-        // For known networks, keep the profile even if connection fails
-        return Err(anyhow::anyhow!("Failed to activate: {:?}", output));
-      }
-      Ok(())
-    } else {
-      // New network - use nmcli to create and connect
-      let mut args = vec!["device", "wifi", "connect", ssid];
-      if !password.is_empty() {
-        args.push("password");
-        args.push(password);
-      }
+  /// Cycles through the methods that need no further configuration. Manual needs addresses, so it isn't offered.
+  pub fn next(self) -> Self {
+    match self {
+      Self::Auto => Self::LinkLocal,
+      Self::LinkLocal => Self::Disabled,
+      Self::Manual | Self::Shared | Self::Disabled => Self::Auto,
+    }
+  }
 
-      let output = std::process::Command::new("nmcli")
-        .args(&args)
-        .output()
-        .context("Failed to execute nmcli")?;
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Auto => "DHCP",
+      Self::Manual => "manual",
+      Self::LinkLocal => "link-local",
+      Self::Shared => "shared",
+      Self::Disabled => "disabled",
+    }
+  }
+}
 
-      if !output.status.success() {
-        // For unknown networks that fail to connect, delete the connection profile
-        // that was created by nmcli. This prevents the network from being marked
-        // as "known" after a failed connection attempt.
-        self.forget_network(ssid).context("failed to forget network")?;
+/// The outer EAP method of an 802.1X (WPA-Enterprise) network, with the inner authentication that usually goes with it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EapMethod {
+  /// PEAP with MSCHAPv2 inside, what most campus and corporate networks use
+  #[default]
+  Peap,
+  /// TTLS with PAP inside
+  Ttls,
+}
 
-        Err(anyhow::anyhow!("Failed to connect: {:?}", output))
-      } else {
-        // Wait a bit to let connection establish
-        std::thread::sleep(Duration::from_millis(500));
-        Ok(())
-      }
+impl EapMethod {
+  fn eap(self) -> &'static str {
+    match self {
+      Self::Peap => "peap",
+      Self::Ttls => "ttls",
     }
   }
 
-  pub fn disconnect(&self) -> Result<()> {
-    let nm = NetworkManager::new(&self.connection);
-    let devices = nm.get_devices().context("Failed to get devices")?;
+  fn phase2_auth(self) -> &'static str {
+    match self {
+      Self::Peap => "mschapv2",
+      Self::Ttls => "pap",
+    }
+  }
 
-    // Find the WiFi device
-    for device in devices {
-      if let Device::WiFi(wifi_device) = device {
-        wifi_device.disconnect().context("Failed to disconnect")?;
-      }
+  pub fn next(self) -> Self {
+    match self {
+      Self::Peap => Self::Ttls,
+      Self::Ttls => Self::Peap,
     }
+  }
 
-    Ok(())
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Peap => "PEAP (MSCHAPv2)",
+      Self::Ttls => "TTLS (PAP)",
+    }
   }
+}
 
-  pub fn forget_network(&self, ssid: &str) -> Result<()> {
-    // Use nmcli to delete the connection
-    let output = std::process::Command::new("nmcli")
-      .args(&["connection", "delete", ssid])
-      .output()
-      .context("Failed to execute nmcli")?;
+/// What MAC address new profiles use (`802-11-wireless.cloned-mac-address`), instead of the adapter's own
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MacAddressPolicy {
+  /// A new random address every time the network is connected to
+  Random,
+  /// A random address that stays the same for each network, so it can't be tracked across networks but the network
+  /// still recognizes the device
+  Stable,
+}
 
-    // In some cases, eg RSN networks, nmcli does not create a network profile after a failed connection attempt. We
-    // consider a forgetting successful as long as no network profile exists afterwards.
-    if output.status.success() || String::from_utf8_lossy(&output.stderr).contains("cannot delete unknown connection") {
-      Ok(())
-    } else {
-      Err(anyhow::anyhow!("Failed to forget network: {:?}", output))
+impl MacAddressPolicy {
+  fn value(self) -> &'static str {
+    match self {
+      Self::Random => "random",
+      Self::Stable => "stable",
     }
   }
 
-  pub fn toggle_autoconnect(&self, ssid: &str) -> Result<()> {
-    // Get current value
-    let (known, _, autoconnect, _) = self.get_connection_info(ssid)?;
+  /// Cycles through the policies, `None` meaning NetworkManager's default
+  pub fn next(policy: Option<Self>) -> Option<Self> {
+    match policy {
+      None => Some(Self::Random),
+      Some(Self::Random) => Some(Self::Stable),
+      Some(Self::Stable) => None,
+    }
+  }
 
-    if !known {
-      return Err(anyhow::anyhow!("Network not found in saved connections"));
+  /// What the policy means, for the password dialog
+  pub fn describe(policy: Option<Self>) -> &'static str {
+    match policy {
+      None => "NetworkManager's default",
+      Some(Self::Random) => "random (new on every connection)",
+      Some(Self::Stable) => "stable (fixed per network)",
     }
+  }
+}
 
-    let current = autoconnect.unwrap_or(true);
-    let new_value = if current { "no" } else { "yes" };
+/// What it takes to log into an 802.1X network
+#[derive(Debug, Clone)]
+pub struct EnterpriseCredentials {
+  pub method: EapMethod,
+  pub identity: String,
+  pub password: String,
+}
 
-    // Use nmcli to modify the connection
-    let output = std::process::Command::new("nmcli")
-      .args(&["connection", "modify", ssid, "connection.autoconnect", new_value])
-      .output()
-      .context("Failed to execute nmcli")?;
+/// A saved connection as (settings path, SSID, settings)
+type SavedSettings = (dbus::Path<'static>, String, HashMap<String, PropMap>);
 
-    if output.status.success() {
+#[derive(Debug, Clone)]
+struct ConnectionInfo {
+  uuid: String,
+  priority: Option<i32>,
+  autoconnect: Option<bool>,
+  autoconnect_retries: Option<i32>,
+  ip4_method: Option<Ip4Method>,
+  ip6_privacy: Option<Ip6Privacy>,
+  metered: Option<bool>,
+  traffic_control: Vec<String>,
+  powersave: Option<bool>,
+  never_default: Option<bool>,
+  zone: Option<String>,
+}
+
+/// Settings shown when comparing a saved connection against the applied one, as (label, setting, key)
+const COMPARED_SETTINGS: &[(&str, &str, &str)] = &[
+  ("IPv4 method", "ipv4", "method"),
+  ("IPv4 addresses", "ipv4", "address-data"),
+  ("IPv4 DNS", "ipv4", "dns"),
+  ("IPv6 method", "ipv6", "method"),
+  ("IPv6 privacy", "ipv6", "ip6-privacy"),
+  ("Security", "802-11-wireless-security", "key-mgmt"),
+  ("Power save", "802-11-wireless", "powersave"),
+  ("IPv4 never default", "ipv4", "never-default"),
+  ("IPv6 never default", "ipv6", "never-default"),
+  ("Metered", "connection", "metered"),
+  ("Auto-connect", "connection", "autoconnect"),
+];
+
+/// Connection settings we read for every saved WiFi profile
+const CONNECTION_FIELDS: &[&str] = &[
+  "connection.autoconnect",
+  "connection.autoconnect-priority",
+  "connection.autoconnect-retries",
+  "connection.metered",
+  "ipv4.method",
+  "ipv6.ip6-privacy",
+  "802-11-wireless.powersave",
+  "ipv4.never-default",
+  "ipv6.never-default",
+  "connection.zone",
+  "tc.qdiscs",
+  "tc.tfilters",
+];
+
+/// Everything the UI needs from the network stack. `NmClient` talks to NetworkManager; `MockClient` serves canned data
+/// for UI development and tests.
+pub trait NetworkClient {
+  fn get_device_info(&self) -> Result<WifiDeviceInfo>;
+  fn get_wifi_networks(&self) -> Result<Vec<WifiInfo>>;
+  /// Connects to `ssid`, creating a profile if there's none. `autoconnect` is written into the profile when given: with
+  /// `Some(false)` NetworkManager won't connect to the network again on its own afterwards, `Some(true)` turns that on
+  /// for good. `None` leaves the profile as it is, or NetworkManager's default for a new one. `mac_address` only
+  /// applies to a newly created profile. `ssid` is the SSID as broadcast (`WifiInfo::ssid_bytes`).
+  fn connect(
+    &self,
+    ssid: &[u8],
+    password: &str,
+    autoconnect: Option<bool>,
+    mac_address: Option<MacAddressPolicy>,
+  ) -> Result<()>;
+  /// Like `connect` for an 802.1X (WPA-Enterprise) network we have no profile for yet.
+  fn connect_enterprise(
+    &self,
+    ssid: &[u8],
+    credentials: &EnterpriseCredentials,
+    autoconnect: Option<bool>,
+    mac_address: Option<MacAddressPolicy>,
+  ) -> Result<()>;
+  /// Re-activates a saved connection so that edits to its settings take effect. `ssid` is the SSID as broadcast.
+  fn reactivate(&self, ssid: &[u8]) -> Result<()>;
+  fn disconnect(&self) -> Result<()>;
+  /// Deletes every saved connection to the network with this SSID as broadcast (`WifiInfo::ssid_bytes`).
+  fn forget_network(&self, ssid: &[u8]) -> Result<()>;
+  /// SSIDs as broadcast of every saved WiFi connection, in range or not.
+  fn saved_ssids(&self) -> Result<Vec<Vec<u8>>>;
+  /// Captures a saved connection's full settings, password included, so it can be recreated after being forgotten.
+  fn backup_profile(&self, ssid: &str) -> Result<ProfileBackup>;
+  /// Saves a backed up connection again.
+  fn restore_profile(&self, backup: ProfileBackup) -> Result<()>;
+  fn toggle_autoconnect(&self, ssid: &str) -> Result<()>;
+  fn set_autoconnect(&self, ssid: &str, enabled: bool) -> Result<()>;
+  /// Like `set_autoconnect`, but only for NetworkManager's in-memory copy of the profile. The saved setting comes back
+  /// when NetworkManager reloads its profiles or restarts.
+  fn set_autoconnect_temporarily(&self, ssid: &str, enabled: bool) -> Result<()>;
+  fn set_ip4_method(&self, ssid: &str, method: Ip4Method) -> Result<()>;
+  fn set_ip6_privacy(&self, ssid: &str, value: Ip6Privacy) -> Result<()>;
+  /// Turns WiFi power saving on or off for a saved connection, trading latency for battery life.
+  fn set_powersave(&self, ssid: &str, enabled: bool) -> Result<()>;
+  /// Keeps a saved connection from ever becoming the default route (IPv4 and IPv6), eg. for an IoT network that
+  /// shouldn't carry internet traffic.
+  fn set_never_default(&self, ssid: &str, never_default: bool) -> Result<()>;
+  /// Assigns a saved connection to a firewalld zone, or back to the default zone with `None`.
+  fn set_zone(&self, ssid: &str, zone: Option<&str>) -> Result<()>;
+  /// Asks NetworkManager to re-check internet connectivity right now. This can block for a few seconds.
+  fn check_connectivity(&self) -> Result<Connectivity>;
+  /// Compares a saved connection's settings with those applied to the device, to tell whether edits are live yet.
+  fn compare_settings(&self, ssid: &str) -> Result<Vec<SettingComparison>>;
+  /// Renders a saved connection as a keyfile (`.nmconnection`), optionally including its password.
+  fn export_profile(&self, ssid: &str, include_secrets: bool) -> Result<String>;
+  /// The password stored in a saved connection, `None` if it has none (open networks, or secrets kept by an agent).
+  fn saved_password(&self, ssid: &str) -> Result<Option<String>>;
+  /// Every saved WiFi connection with its password, for moving them to another machine. Profiles whose secrets can't
+  /// be read are reported rather than failing the rest.
+  fn export_profiles(&self) -> Result<ExportSummary>;
+  /// Saves exported profiles without activating them. Networks that already have a profile are skipped, unless
+  /// `overwrite` replaces their settings with the exported ones.
+  fn import_profiles(&self, profiles: Vec<ExportedProfile>, overwrite: bool) -> Result<ImportSummary>;
+  /// Replaces the password of a saved connection, keeping the rest of its settings. Takes effect on the next
+  /// activation.
+  fn update_password(&self, ssid: &str, password: &str) -> Result<()>;
+
+  /// Turns the WiFi radio on or off. Turning it on returns once the device is ready to connect.
+  fn set_wifi_enabled(&self, enabled: bool) -> Result<()>;
+  /// Makes scans, connections and disconnects use the WiFi device with this interface name, eg. `wlan1`.
+  fn select_wifi_interface(&self, interface: &str) -> Result<()>;
+  /// Turns NetworkManager's global connectivity checking (the periodic HTTP probe) on or off.
+  fn set_connectivity_check(&self, enabled: bool) -> Result<()>;
+  /// Turns autoconnecting on the WiFi device as a whole on or off, regardless of each profile's own setting.
+  fn set_device_autoconnect(&self, enabled: bool) -> Result<()>;
+
+  fn forget_networks(&self, ssids: &[Vec<u8>]) -> Result<()> {
+    // Keep going after individual failures so one bad profile doesn't block cleaning up the rest
+    let failures: Vec<String> = ssids
+      .iter()
+      .filter_map(|ssid| {
+        let e = self.forget_network(ssid).err()?;
+        Some(format!("{}: {:#}", String::from_utf8_lossy(ssid), e))
+      })
+      .collect();
+
+    if failures.is_empty() {
       Ok(())
     } else {
-      return Err(anyhow::anyhow!("Failed to toggle autoconnect: {:?}", output));
+      Err(anyhow::anyhow!(
+        "Failed to forget {} of {} networks:\n{}",
+        failures.len(),
+        ssids.len(),
+        failures.join("\n")
+      ))
     }
   }
 }
 
-fn decode_security(wpa_flags: u32, rsn_flags: u32) -> (String, bool) {
-  if wpa_flags == 0 && rsn_flags == 0 {
-    return ("Open".to_string(), true);
-  }
+/// A saved connection's settings with its secrets, kept around so forgetting it can be undone
+pub struct ProfileBackup {
+  pub ssid: String,
+  pub settings: HashMap<String, PropMap>,
+}
 
-  let mut modes = Vec::new();
-  let mut weak = false;
+/// The essentials of a saved WiFi connection, as written by `weefee export` to move profiles to another machine
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExportedProfile {
+  pub ssid: String,
+  /// NetworkManager's `key-mgmt`, eg. `wpa-psk`, `sae` or `none` (WEP). Absent for open networks.
+  pub security: Option<String>,
+  /// In plain text
+  pub password: Option<String>,
+  pub priority: i32,
+  pub autoconnect: bool,
+}
 
-  // Check for WPA (Legacy)
-  if wpa_flags != 0 {
-    modes.push("WPA");
+/// What `NetworkClient::export_profiles` could read
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+  pub profiles: Vec<ExportedProfile>,
+  /// "SSID: reason" for each profile that couldn't be exported
+  pub failed: Vec<String>,
+}
+
+/// What `NetworkClient::import_profiles` did with each profile
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+  pub imported: usize,
+  /// Already saved, and not overwritten
+  pub skipped: usize,
+  /// "SSID: reason" for each profile that couldn't be saved
+  pub failed: Vec<String>,
+}
+
+/// Lets another thread call off a connection attempt that's blocking the network thread. Once raised, connecting fails
+/// with `ConnectionError::Cancelled` until the flag is cleared again.
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::SeqCst);
   }
 
-  // Check for RSN (WPA2/WPA3)
-  if rsn_flags != 0 {
-    // bit 0x100 is Key Mgmt PSK (Personal)
-    // bit 0x200 is Key Mgmt 802.1X (Enterprise)
-    // bit 0x1000 is SAE (WPA3 Personal)
+  pub fn clear(&self) {
+    self.0.store(false, Ordering::SeqCst);
+  }
 
-    if (rsn_flags & 0x1000) != 0 {
-      modes.push("WPA3");
-    } else if (rsn_flags & 0x100) != 0 {
-      modes.push("WPA2");
-    } else if (rsn_flags & 0x200) != 0 {
-      modes.push("WPA2-Ent");
-    } else {
-      modes.push("RSN");
-    }
+  pub fn is_cancelled(&self) -> bool {
+    self.0.load(Ordering::SeqCst)
   }
+}
 
-  let mode_str = if modes.is_empty() {
-    weak = true;
-    "WEP/Open".to_string()
-  } else {
-    modes.join("/")
-  };
+/// An access point as read by `NmClient::access_points`
+struct AccessPoint {
+  path: dbus::Path<'static>,
+  /// Raw, and possibly not UTF-8
+  ssid: Vec<u8>,
+  strength: u8,
+  frequency: Option<u32>,
+  flags: u32,
+  wpa_flags: u32,
+  rsn_flags: u32,
+  bssid: Option<String>,
+}
 
-  (mode_str, weak)
+pub struct NmClient {
+  connection: Connection,
+  last_scan_request: Cell<Option<Instant>>,
+  /// The WiFi device picked with `select_wifi_interface`. Until then, or once it's gone, the first one is used.
+  selected_device: RefCell<Option<dbus::Path<'static>>>,
+  cancel: CancelFlag,
+}
+
+impl NmClient {
+  /// Connects to the system bus and checks that NetworkManager is there, failing with `ServiceUnavailable` if not.
+  /// Without the check, every later call would fail with a D-Bus error that doesn't say what's wrong.
+  pub fn new() -> Result<Self> {
+    let connection = Connection::new_system().context("Failed to connect to system bus")?;
+    let client = Self {
+      connection,
+      last_scan_request: Cell::new(None),
+      selected_device: RefCell::new(None),
+      cancel: CancelFlag::default(),
+    };
+    client.nm_version().map_err(|_| ServiceUnavailable)?;
+    Ok(client)
+  }
+
+  /// Makes connection attempts give up once `cancel` is raised.
+  pub fn with_cancel_flag(self, cancel: CancelFlag) -> Self {
+    Self { cancel, ..self }
+  }
+
+  fn get_all_connection_info(&self) -> Result<HashMap<String, ConnectionInfo>> {
+    // Batch load all connection info with minimal nmcli calls
+    Ok(
+      self
+        .saved_wifi_connections()?
+        .into_iter()
+        .map(|(ssid, uuid)| (ssid, self.connection_info(&uuid)))
+        .collect(),
+    )
+  }
+
+  /// Reads the settings the UI shows of the saved connection with this UUID, all in one nmcli call.
+  fn connection_info(&self, uuid: &str) -> ConnectionInfo {
+    // Get all fields for this connection in one call
+    let fields = std::process::Command::new("nmcli")
+      .args([
+        "--terse",
+        "--fields",
+        &CONNECTION_FIELDS.join(","),
+        "connection",
+        "show",
+        "uuid",
+        uuid,
+      ])
+      .output()
+      .ok()
+      .filter(|output| output.status.success())
+      .map(|output| parse_terse_fields(&String::from_utf8_lossy(&output.stdout)))
+      .unwrap_or_default();
+    let field = |name: &str| fields.get(name).map(String::as_str).filter(|v| !v.is_empty());
+
+    let autoconnect = Some(!matches!(
+      field("connection.autoconnect").map(str::to_lowercase).as_deref(),
+      Some("no" | "false" | "0")
+    ));
+
+    ConnectionInfo {
+      uuid: uuid.to_string(),
+      priority: field("connection.autoconnect-priority").and_then(|v| v.parse().ok()),
+      autoconnect,
+      autoconnect_retries: field("connection.autoconnect-retries").and_then(|v| v.parse().ok()),
+      ip4_method: field("ipv4.method").and_then(Ip4Method::parse),
+      ip6_privacy: field("ipv6.ip6-privacy").and_then(Ip6Privacy::parse),
+      metered: match field("connection.metered") {
+        Some("yes") => Some(true),
+        Some("no") => Some(false),
+        _ => None,
+      },
+      traffic_control: ["tc.qdiscs", "tc.tfilters"]
+        .into_iter()
+        .filter_map(field)
+        .flat_map(|rules| rules.split(',').map(|rule| rule.trim().to_string()))
+        .filter(|rule| !rule.is_empty())
+        .collect(),
+      // nmcli prints eg. "3 (enable)"
+      powersave: match field("802-11-wireless.powersave").and_then(|v| v.split_whitespace().next()) {
+        Some("3") => Some(true),
+        Some("2") => Some(false),
+        _ => None,
+      },
+      never_default: Some(
+        ["ipv4.never-default", "ipv6.never-default"]
+          .into_iter()
+          .any(|name| field(name) == Some("yes")),
+      ),
+      zone: field("connection.zone").map(str::to_string),
+    }
+  }
+
+  /// Lists saved WiFi connections as (SSID, UUID) pairs. Profile names can differ from the SSID (and nmcli's terse
+  /// output mangles names containing colons), so we read the SSID itself from the settings over D-Bus.
+  fn saved_wifi_connections(&self) -> Result<Vec<(String, String)>> {
+    Ok(
+      self
+        .saved_wifi_settings()?
+        .into_iter()
+        .filter_map(|(_path, ssid, settings)| {
+          let uuid = prop_cast::<String>(settings.get("connection")?, "uuid")?.clone();
+          Some((ssid, uuid))
+        })
+        .collect(),
+    )
+  }
+
+  /// The UUID of the saved connection to `ssid` (as broadcast), if there is one.
+  fn find_connection_uuid(&self, ssid: &[u8]) -> Result<Option<String>> {
+    Ok(
+      self
+        .saved_wifi_settings()?
+        .into_iter()
+        .find(|(_path, _ssid, settings)| wifi_ssid_bytes(settings) == Some(ssid))
+        .and_then(|(_path, _ssid, settings)| prop_cast::<String>(settings.get("connection")?, "uuid").cloned()),
+    )
+  }
+
+  /// The UUID of the saved connection to `ssid` (as broadcast).
+  fn connection_uuid(&self, ssid: &[u8]) -> Result<String> {
+    self
+      .find_connection_uuid(ssid)?
+      .with_context(|| format!("No saved connection for {}", String::from_utf8_lossy(ssid)))
+  }
+
+  /// The SSID as broadcast of the saved connection displayed as `ssid`, for the settings edits that only know the
+  /// displayed SSID. Two saved networks can display the same when their SSIDs aren't UTF-8, and then neither is picked.
+  fn saved_ssid_bytes(&self, ssid: &str) -> Result<Vec<u8>> {
+    let mut matching: Vec<Vec<u8>> = self
+      .saved_ssids()?
+      .into_iter()
+      .filter(|saved| String::from_utf8_lossy(saved) == ssid)
+      .collect();
+    match matching.len() {
+      0 => Err(anyhow::anyhow!("No saved connection for {}", ssid)),
+      1 => Ok(matching.remove(0)),
+      _ => Err(anyhow::anyhow!(
+        "{} saved networks are displayed as {}, so it's unclear which one is meant",
+        matching.len(),
+        ssid
+      )),
+    }
+  }
+
+  /// The settings of the saved connection to `ssid` (as broadcast), optionally with its secrets.
+  fn saved_settings(&self, ssid: &[u8], include_secrets: bool) -> Result<HashMap<String, PropMap>> {
+    self
+      .saved_settings_at(ssid, include_secrets)
+      .map(|(_path, settings)| settings)
+  }
+
+  /// Like `saved_settings`, along with the connection's settings path.
+  fn saved_settings_at(
+    &self,
+    ssid: &[u8],
+    include_secrets: bool,
+  ) -> Result<(dbus::Path<'static>, HashMap<String, PropMap>)> {
+    let (path, _ssid, mut settings) = self
+      .saved_wifi_settings()?
+      .into_iter()
+      .find(|(_path, _ssid, settings)| wifi_ssid_bytes(settings) == Some(ssid))
+      .with_context(|| format!("No saved connection for {}", String::from_utf8_lossy(ssid)))?;
+
+    if include_secrets {
+      self.add_secrets(&path, &mut settings)?;
+    }
+
+    Ok((path, settings))
+  }
+
+  /// Fills in the passwords that GetSettings leaves out of a saved connection's settings.
+  fn add_secrets(&self, path: &dbus::Path<'static>, settings: &mut HashMap<String, PropMap>) -> Result<()> {
+    let proxy = self.connection.with_proxy(NM_BUS, path.clone(), DBUS_TIMEOUT);
+    for setting in ["802-11-wireless-security", "802-1x"] {
+      if !settings.contains_key(setting) {
+        continue;
+      }
+      let (secrets,): (HashMap<String, PropMap>,) = proxy
+        .method_call(NM_SETTINGS_CONNECTION_INTERFACE, "GetSecrets", (setting,))
+        .context("Failed to read the saved password")?;
+      for (name, values) in secrets {
+        settings.entry(name).or_default().extend(values);
+      }
+    }
+    Ok(())
+  }
+
+  /// The settings (without secrets) of every saved WiFi connection, as (settings path, SSID, settings).
+  fn saved_wifi_settings(&self) -> Result<Vec<SavedSettings>> {
+    let (paths,): (Vec<dbus::Path<'static>>,) = self
+      .connection
+      .with_proxy(NM_BUS, NM_SETTINGS_PATH, DBUS_TIMEOUT)
+      .method_call(NM_SETTINGS_INTERFACE, "ListConnections", ())
+      .context("Failed to list saved connections")?;
+
+    Ok(
+      paths
+        .into_iter()
+        .filter_map(|path| {
+          // Connections can disappear between listing and reading them; just skip those
+          let (settings,): (HashMap<String, PropMap>,) = self
+            .connection
+            .with_proxy(NM_BUS, path.clone(), DBUS_TIMEOUT)
+            .method_call(NM_SETTINGS_CONNECTION_INTERFACE, "GetSettings", ())
+            .ok()?;
+          Some((path, wifi_ssid(&settings)?, settings))
+        })
+        .collect(),
+    )
+  }
+
+  /// The active connection object for a saved connection to `ssid` (as broadcast), if it's currently activating or
+  /// active.
+  fn active_connection_path(&self, ssid: &[u8]) -> Option<dbus::Path<'static>> {
+    let active_paths: Vec<dbus::Path<'static>> = self.nm_proxy().get(NM_INTERFACE, "ActiveConnections").ok()?;
+    active_paths.into_iter().find(|active_path| {
+      let settings_path = self
+        .connection
+        .with_proxy(NM_BUS, active_path.clone(), DBUS_TIMEOUT)
+        .get::<dbus::Path<'static>>(NM_ACTIVE_CONNECTION_INTERFACE, "Connection");
+      let settings: Option<(HashMap<String, PropMap>,)> = settings_path.ok().and_then(|path| {
+        self
+          .connection
+          .with_proxy(NM_BUS, path, DBUS_TIMEOUT)
+          .method_call(NM_SETTINGS_CONNECTION_INTERFACE, "GetSettings", ())
+          .ok()
+      });
+      settings.is_some_and(|(settings,)| wifi_ssid_bytes(&settings) == Some(ssid))
+    })
+  }
+
+  /// Active connections on the WiFi device, straight from NetworkManager's live list. This includes connections that
+  /// other tools or NetworkManager itself (eg. at boot) activated, not just ours.
+  fn wifi_active_connections(&self) -> Result<Vec<dbus::Path<'static>>> {
+    let device_path = self.wifi_device_path()?;
+    let active_paths: Vec<dbus::Path<'static>> = self
+      .nm_proxy()
+      .get(NM_INTERFACE, "ActiveConnections")
+      .context("Failed to get active connections")?;
+    Ok(
+      active_paths
+        .into_iter()
+        .filter(|active_path| {
+          self
+            .connection
+            .with_proxy(NM_BUS, active_path.clone(), DBUS_TIMEOUT)
+            .get::<Vec<dbus::Path<'static>>>(NM_ACTIVE_CONNECTION_INTERFACE, "Devices")
+            .is_ok_and(|devices| devices.contains(&device_path))
+        })
+        .collect(),
+    )
+  }
+
+  /// Activates the saved profile for `ssid` (as broadcast) with nmcli. Profiles made by other tools aren't necessarily
+  /// named after the SSID, so this goes by UUID.
+  fn connection_up(&self, ssid: &[u8]) -> Result<std::process::Output> {
+    let uuid = self.connection_uuid(ssid)?;
+    let mut child = std::process::Command::new("nmcli")
+      .args(["connection", "up", "uuid", &uuid])
+      .args(["ifname", &self.wifi_interface()?])
+      .stdout(std::process::Stdio::piped())
+      .stderr(std::process::Stdio::piped())
+      .spawn()
+      .context("Failed to execute nmcli")?;
+
+    // nmcli waits for the activation to finish, so poll it to be able to call the activation off in the meantime
+    while child.try_wait()?.is_none() {
+      if self.cancel.is_cancelled() {
+        if let Some(active_path) = self.active_connection_path(ssid) {
+          self.deactivate(active_path)?;
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(ConnectionError::Cancelled.into());
+      }
+      std::thread::sleep(Duration::from_millis(100));
+    }
+    child.wait_with_output().context("Failed to execute nmcli")
+  }
+
+  fn deactivate(&self, active_path: dbus::Path<'static>) -> Result<()> {
+    let result: Result<(), _> = self
+      .nm_proxy()
+      .method_call(NM_INTERFACE, "DeactivateConnection", (active_path,));
+    match result {
+      Ok(()) => Ok(()),
+      // It went down on its own in the meantime, which is what we wanted anyway
+      Err(e) if e.name() == Some("org.freedesktop.NetworkManager.ConnectionNotActive") => Ok(()),
+      Err(e) => Err(e).context("Failed to disconnect"),
+    }
+  }
+
+  /// Whether the profile behind the active connection `active_path` is saved to disk.
+  fn active_persistence(&self, active_path: &dbus::Path<'static>) -> Option<Persistence> {
+    let settings_path: dbus::Path<'static> = self
+      .connection
+      .with_proxy(NM_BUS, active_path.clone(), DBUS_TIMEOUT)
+      .get(NM_ACTIVE_CONNECTION_INTERFACE, "Connection")
+      .ok()?;
+    let flags: u32 = self
+      .connection
+      .with_proxy(NM_BUS, settings_path, DBUS_TIMEOUT)
+      .get(NM_SETTINGS_CONNECTION_INTERFACE, "Flags")
+      .ok()?;
+    Some(Persistence::from_flags(flags))
+  }
+
+  /// When the WiFi device last finished scanning, in milliseconds of `CLOCK_BOOTTIME`. -1 if it never has.
+  fn last_scan(&self) -> Option<i64> {
+    let device_path = self.wifi_device_path().ok()?;
+    self
+      .connection
+      .with_proxy(NM_BUS, device_path, DBUS_TIMEOUT)
+      .get(NM_WIRELESS_INTERFACE, "LastScan")
+      .ok()
+  }
+
+  /// Waits until the device's `LastScan` moves on from `previous`, ie. a requested scan finished, for at most
+  /// `SCAN_RESULTS_WAIT`. Without `LastScan` (NetworkManager < 1.12) there's nothing to wait for.
+  fn wait_for_scan(&self, previous: Option<i64>) {
+    if previous.is_none() {
+      return;
+    }
+    let deadline = Instant::now() + SCAN_RESULTS_WAIT;
+    while Instant::now() < deadline {
+      std::thread::sleep(SCAN_POLL_INTERVAL);
+      if self.last_scan() != previous {
+        return;
+      }
+    }
+  }
+
+  /// The WiFi device's current bitrate in kb/s, `None` if unknown or not associated (NetworkManager reports 0 then).
+  fn bitrate(&self) -> Option<u32> {
+    let device_path = self.wifi_device_path().ok()?;
+    let bitrate: u32 = self
+      .connection
+      .with_proxy(NM_BUS, device_path, DBUS_TIMEOUT)
+      .get(NM_WIRELESS_INTERFACE, "Bitrate")
+      .ok()?;
+    Some(bitrate).filter(|&bitrate| bitrate > 0)
+  }
+
+  /// The IPv4 configuration in use on the active connection `active_path`, or `None` before it has one.
+  fn active_ip4(&self, active_path: &dbus::Path<'static>) -> Option<ActiveIp4> {
+    let ip4_config: dbus::Path<'static> = self
+      .connection
+      .with_proxy(NM_BUS, active_path.clone(), DBUS_TIMEOUT)
+      .get(NM_ACTIVE_CONNECTION_INTERFACE, "Ip4Config")
+      .ok()
+      // NetworkManager uses "/" while there's no IP configuration yet
+      .filter(|path: &dbus::Path<'static>| &**path != "/")?;
+    let proxy = self.connection.with_proxy(NM_BUS, ip4_config, DBUS_TIMEOUT);
+
+    let address_data: Vec<PropMap> = proxy.get(NM_IP4_CONFIG_INTERFACE, "AddressData").unwrap_or_default();
+    let nameserver_data: Vec<PropMap> = proxy.get(NM_IP4_CONFIG_INTERFACE, "NameserverData").unwrap_or_default();
+    let gateway: String = proxy.get(NM_IP4_CONFIG_INTERFACE, "Gateway").unwrap_or_default();
+    Some(ActiveIp4 {
+      addresses: address_data
+        .iter()
+        .filter_map(|address| {
+          let ip = prop_cast::<String>(address, "address")?;
+          Some(match prop_cast::<u32>(address, "prefix") {
+            Some(prefix) => format!("{}/{}", ip, prefix),
+            None => ip.clone(),
+          })
+        })
+        .collect(),
+      gateway: Some(gateway).filter(|gateway| !gateway.is_empty()),
+      dns: nameserver_data
+        .iter()
+        .filter_map(|server| prop_cast::<String>(server, "address").cloned())
+        .collect(),
+    })
+  }
+
+  /// The settings currently applied to the WiFi device, or `None` if nothing is connected.
+  fn applied_wifi_settings(&self) -> Result<Option<HashMap<String, PropMap>>> {
+    let device_path = self.wifi_device_path()?;
+    let result: Result<(HashMap<String, PropMap>, u64), _> = self
+      .connection
+      .with_proxy(NM_BUS, device_path, DBUS_TIMEOUT)
+      .method_call(NM_DEVICE_INTERFACE, "GetAppliedConnection", (0u32,));
+    // NetworkManager answers with an error when the device has no active connection
+    Ok(result.ok().map(|(settings, _version_id)| settings))
+  }
+
+  fn get_connection_info(&self, ssid: &[u8]) -> Result<Option<ConnectionInfo>> {
+    // Single lookups (used in toggle_autoconnect), matched on the SSID as broadcast
+    Ok(self.find_connection_uuid(ssid)?.map(|uuid| self.connection_info(&uuid)))
+  }
+
+  /// Builds the settings map for a brand-new WiFi profile.
+  fn new_connection_settings(
+    &self,
+    ssid: &[u8],
+    password: &str,
+    autoconnect: Option<bool>,
+    mac_address: Option<MacAddressPolicy>,
+  ) -> HashMap<&'static str, PropMap> {
+    // Only look up the AP when there's a secret that needs to go into the right security block
+    let ap_flags = if password.is_empty() {
+      None
+    } else {
+      self.find_access_point_flags(ssid)
+    };
+    new_connection_settings(ssid, password, ap_flags, autoconnect, mac_address)
+  }
+
+  /// Adds a new connection profile and activates it on `device_path`, returning the paths of the new settings
+  /// connection and of the active connection.
+  ///
+  /// On NetworkManager >= 1.16 this uses `AddAndActivateConnection2`, falling back to `AddAndActivateConnection` on
+  /// older versions or when the version can't be read. The `result` dict of the former is reserved for future use and
+  /// comes back empty, so failure details still come from the D-Bus error, which is translated into a user-facing
+  /// message where we recognize it, and from the device's state reason afterwards.
+  fn add_and_activate_connection(
+    &self,
+    settings: HashMap<&'static str, PropMap>,
+    device_path: &dbus::Path<'static>,
+  ) -> Result<(dbus::Path<'static>, dbus::Path<'static>)> {
+    let proxy = self.nm_proxy();
+    let specific_object = dbus::Path::from("/");
+
+    let result = if self.nm_version().is_ok_and(|version| version >= (1, 16, 0)) {
+      let options: PropMap = HashMap::new();
+      proxy
+        .method_call::<(dbus::Path<'static>, dbus::Path<'static>, PropMap), _, _, _>(
+          NM_INTERFACE,
+          "AddAndActivateConnection2",
+          (settings, device_path, specific_object, options),
+        )
+        .map(|(connection_path, active_path, _)| (connection_path, active_path))
+    } else {
+      proxy.method_call::<(dbus::Path<'static>, dbus::Path<'static>), _, _, _>(
+        NM_INTERFACE,
+        "AddAndActivateConnection",
+        (settings, device_path, specific_object),
+      )
+    };
+
+    result.map_err(|e| anyhow::anyhow!("{}", describe_activation_error(&e)))
+  }
+
+  /// Creates a profile for a new network and activates it in one D-Bus call, waiting for the outcome. The profile is
+  /// deleted again if connecting fails, so the network doesn't show up as "known" after a failed attempt.
+  fn add_and_connect(&self, settings: HashMap<&'static str, PropMap>) -> Result<()> {
+    if self.cancel.is_cancelled() {
+      return Err(ConnectionError::Cancelled.into());
+    }
+    let device_path = self.wifi_device_path()?;
+    let (connection_path, active_path) = self.add_and_activate_connection(settings, &device_path)?;
+
+    match self.wait_for_connection_state(&active_path, &device_path) {
+      Ok(()) => Ok(()),
+      Err(e) => {
+        self
+          .delete_connection(&connection_path)
+          .context("failed to forget network")?;
+        Err(e)
+      }
+    }
+  }
+
+  /// Blocks until the active connection at `active_path` is either activated or has failed.
+  ///
+  /// NetworkManager only reports the reason for a failure on the device, so we watch the device's `StateReason` while
+  /// polling the active connection's `State`. A device that is still failed from an earlier attempt carries that
+  /// attempt's reason, so a failure only counts once the device has been seen in another state since we started.
+  fn wait_for_connection_state(
+    &self,
+    active_path: &dbus::Path<'static>,
+    device_path: &dbus::Path<'static>,
+  ) -> Result<()> {
+    let active_proxy = self.connection.with_proxy(NM_BUS, active_path.clone(), DBUS_TIMEOUT);
+    let device_proxy = self.connection.with_proxy(NM_BUS, device_path.clone(), DBUS_TIMEOUT);
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    let mut failure_reason = None;
+    let mut left_stale_failure = false;
+
+    loop {
+      if let Ok((state, reason)) = device_proxy.get::<(u32, u32)>(NM_DEVICE_INTERFACE, "StateReason") {
+        if state != NM_DEVICE_STATE_FAILED {
+          left_stale_failure = true;
+        } else if left_stale_failure {
+          failure_reason = Some(reason);
+        }
+      }
+
+      // The active connection object disappears once it's fully deactivated
+      let state = active_proxy
+        .get::<u32>(NM_ACTIVE_CONNECTION_INTERFACE, "State")
+        .unwrap_or(NM_ACTIVE_CONNECTION_STATE_DEACTIVATED);
+
+      match state {
+        NM_ACTIVE_CONNECTION_STATE_ACTIVATED => return Ok(()),
+        _ if self.cancel.is_cancelled() => {
+          self.deactivate(active_path.clone())?;
+          return Err(ConnectionError::Cancelled.into());
+        }
+        NM_ACTIVE_CONNECTION_STATE_DEACTIVATED => {
+          return Err(ConnectionError::from_state_reason(failure_reason.unwrap_or(0)).into());
+        }
+        _ if Instant::now() >= deadline => return Err(ConnectionError::TimedOut.into()),
+        _ => std::thread::sleep(Duration::from_millis(100)),
+      }
+    }
+  }
+
+  fn delete_connection(&self, connection_path: &dbus::Path<'static>) -> Result<()> {
+    self
+      .connection
+      .with_proxy(NM_BUS, connection_path.clone(), DBUS_TIMEOUT)
+      .method_call::<(), _, _, _>(NM_SETTINGS_CONNECTION_INTERFACE, "Delete", ())
+      .context("Failed to delete connection")
+  }
+
+  fn nm_proxy(&self) -> Proxy<'_, &Connection> {
+    self.connection.with_proxy(NM_BUS, NM_PATH, DBUS_TIMEOUT)
+  }
+
+  /// Returns the running NetworkManager version as (major, minor, micro).
+  pub fn nm_version(&self) -> Result<(u32, u32, u32)> {
+    let version: String = self
+      .nm_proxy()
+      .get(NM_INTERFACE, "Version")
+      .context("Failed to get NetworkManager version")?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+    Ok((
+      parts.next().unwrap_or(0),
+      parts.next().unwrap_or(0),
+      parts.next().unwrap_or(0),
+    ))
+  }
+
+  /// Returns the D-Bus object paths of all WiFi devices. The networkmanager crate doesn't expose device paths, so we
+  /// ask NetworkManager directly.
+  fn wifi_device_paths(&self) -> Result<Vec<dbus::Path<'static>>> {
+    let (devices,): (Vec<dbus::Path<'static>>,) = self
+      .nm_proxy()
+      .method_call(NM_INTERFACE, "GetDevices", ())
+      .context("Failed to get devices")?;
+
+    Ok(
+      devices
+        .into_iter()
+        .filter(|path| {
+          self
+            .connection
+            .with_proxy(NM_BUS, path.clone(), DBUS_TIMEOUT)
+            .get::<u32>(NM_DEVICE_INTERFACE, "DeviceType")
+            .is_ok_and(|t| t == NM_DEVICE_TYPE_WIFI)
+        })
+        .collect(),
+    )
+  }
+
+  /// Returns the D-Bus object path of the selected WiFi device, or of the first one if none is selected.
+  fn wifi_device_path(&self) -> Result<dbus::Path<'static>> {
+    let devices = self.wifi_device_paths()?;
+    let selected = self.selected_device.borrow();
+    devices
+      .iter()
+      .find(|path| selected.as_ref() == Some(path))
+      .or(devices.first())
+      .cloned()
+      .context("No WiFi device found")
+  }
+
+  fn device_interface(&self, device_path: &dbus::Path<'static>) -> Result<String> {
+    self
+      .connection
+      .with_proxy(NM_BUS, device_path.clone(), DBUS_TIMEOUT)
+      .get(NM_DEVICE_INTERFACE, "Interface")
+      .context("Failed to get device interface")
+  }
+
+  /// The interface name of the selected WiFi device, which the networkmanager crate's devices can be matched by
+  fn wifi_interface(&self) -> Result<String> {
+    self.device_interface(&self.wifi_device_path()?)
+  }
+
+  /// The WiFi device's interface name and whether NetworkManager manages it.
+  pub fn wifi_device_status(&self) -> Result<(String, bool)> {
+    let device_proxy = self
+      .connection
+      .with_proxy(NM_BUS, self.wifi_device_path()?, DBUS_TIMEOUT);
+    let interface: String = device_proxy
+      .get(NM_DEVICE_INTERFACE, "Interface")
+      .context("Failed to get device interface")?;
+    let managed: bool = device_proxy
+      .get(NM_DEVICE_INTERFACE, "Managed")
+      .context("Failed to get device managed state")?;
+    Ok((interface, managed))
+  }
+
+  /// What polkit allows the caller to do, by permission name. Values are "yes", "no" or "auth" (allowed after
+  /// authenticating).
+  pub fn permissions(&self) -> Result<HashMap<String, String>> {
+    let (permissions,): (HashMap<String, String>,) = self
+      .nm_proxy()
+      .method_call(NM_INTERFACE, "GetPermissions", ())
+      .context("Failed to get permissions")?;
+    Ok(permissions)
+  }
+
+  /// The reason for the WiFi device's most recent state change.
+  fn device_state_reason(&self) -> Option<u32> {
+    let device_path = self.wifi_device_path().ok()?;
+    self
+      .connection
+      .with_proxy(NM_BUS, device_path, DBUS_TIMEOUT)
+      .get::<(u32, u32)>(NM_DEVICE_INTERFACE, "StateReason")
+      .ok()
+      .map(|(_state, reason)| reason)
+  }
+
+  /// The first manually configured IPv4 address of a saved connection, without the prefix length. Read from the
+  /// profile's settings, as its name needn't match the SSID.
+  fn static_ipv4_address(&self, ssid: &[u8]) -> Option<String> {
+    first_ipv4_address(&self.saved_settings(ssid, false).ok()?)
+  }
+
+  /// Returns the (wpa_flags, rsn_flags) of an access point broadcasting `ssid` that the selected device sees.
+  fn find_access_point_flags(&self, ssid: &[u8]) -> Option<(u32, u32)> {
+    self
+      .access_points(&self.wifi_device_path().ok()?)
+      .ok()?
+      .into_iter()
+      .find(|ap| ap.ssid == ssid)
+      .map(|ap| (ap.wpa_flags, ap.rsn_flags))
+  }
+
+  /// The access points a WiFi device sees. networkmanager-rs assumes SSIDs are UTF-8 (and panics on others), so we read
+  /// them from NetworkManager directly.
+  fn access_points(&self, device_path: &dbus::Path<'static>) -> Result<Vec<AccessPoint>> {
+    let paths: Vec<dbus::Path<'static>> = self
+      .connection
+      .with_proxy(NM_BUS, device_path.clone(), DBUS_TIMEOUT)
+      .get(NM_WIRELESS_INTERFACE, "AccessPoints")
+      .context("Failed to get access points")?;
+
+    Ok(
+      paths
+        .into_iter()
+        .filter_map(|path| {
+          let proxy = self.connection.with_proxy(NM_BUS, path.clone(), DBUS_TIMEOUT);
+          let flags = |name: &str| proxy.get::<u32>(NM_ACCESS_POINT_INTERFACE, name).unwrap_or(0);
+          // Access points can disappear between listing and reading them; just skip those
+          let ssid: Vec<u8> = proxy.get(NM_ACCESS_POINT_INTERFACE, "Ssid").ok()?;
+          Some(AccessPoint {
+            ssid,
+            strength: proxy.get(NM_ACCESS_POINT_INTERFACE, "Strength").unwrap_or(0),
+            frequency: proxy.get(NM_ACCESS_POINT_INTERFACE, "Frequency").ok(),
+            flags: flags("Flags"),
+            wpa_flags: flags("WpaFlags"),
+            rsn_flags: flags("RsnFlags"),
+            bssid: proxy.get(NM_ACCESS_POINT_INTERFACE, "HwAddress").ok(),
+            path,
+          })
+        })
+        .collect(),
+    )
+  }
+
+  /// The access point a WiFi device is connected to, if any
+  fn active_access_point(&self, device_path: &dbus::Path<'static>) -> Option<dbus::Path<'static>> {
+    self
+      .connection
+      .with_proxy(NM_BUS, device_path.clone(), DBUS_TIMEOUT)
+      .get::<dbus::Path<'static>>(NM_WIRELESS_INTERFACE, "ActiveAccessPoint")
+      .ok()
+      // NetworkManager's way of saying there's none
+      .filter(|path| &**path != "/")
+  }
+
+  /// Writes a single setting on a saved connection. Like `nmcli connection modify`, this only changes the saved profile;
+  /// an active connection keeps its old settings until it is reactivated. As in `connection_up`, the profile is picked
+  /// by UUID since its name needn't match the SSID.
+  fn modify_connection(&self, ssid: &[u8], setting: &str, value: &str) -> Result<()> {
+    self.run_connection_modify(ssid, &[], setting, value)
+  }
+
+  /// `modify_connection` with extra options for `nmcli connection modify`, eg. `--temporary`.
+  fn run_connection_modify(&self, ssid: &[u8], options: &[&str], setting: &str, value: &str) -> Result<()> {
+    let uuid = self.connection_uuid(ssid)?;
+    let output = std::process::Command::new("nmcli")
+      .args(["connection", "modify"])
+      .args(options)
+      .args(["uuid", &uuid, setting, value])
+      .output()
+      .context("Failed to execute nmcli")?;
+
+    if output.status.success() {
+      Ok(())
+    } else {
+      Err(anyhow::anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()))
+    }
+  }
+}
+
+impl NetworkClient for NmClient {
+  fn get_device_info(&self) -> Result<WifiDeviceInfo> {
+    let nm = NetworkManager::new(&self.connection);
+    let wifi_enabled = nm.wireless_enabled().context("Failed to get WiFi state")?;
+    let proxy = self.nm_proxy();
+    let connectivity_check_enabled = if proxy.get(NM_INTERFACE, "ConnectivityCheckAvailable").unwrap_or(false) {
+      proxy.get(NM_INTERFACE, "ConnectivityCheckEnabled").ok()
+    } else {
+      None
+    };
+    let connectivity = Connectivity::from_nm(proxy.get(NM_INTERFACE, "Connectivity").unwrap_or(0));
+    let device_path = self.wifi_device_path().ok();
+    let device_proxy = device_path
+      .clone()
+      .map(|path| self.connection.with_proxy(NM_BUS, path, DBUS_TIMEOUT));
+    // Unmanaged and unavailable devices can't scan; they pass through these states while the radio comes up
+    let initializing = wifi_enabled
+      && device_proxy.as_ref().is_some_and(|proxy| {
+        proxy
+          .get::<u32>(NM_DEVICE_INTERFACE, "State")
+          .is_ok_and(|state| state <= NM_DEVICE_STATE_UNAVAILABLE)
+      });
+    let device_autoconnect = device_proxy
+      .as_ref()
+      .and_then(|proxy| proxy.get(NM_DEVICE_INTERFACE, "Autoconnect").ok());
+    let hw_address = device_proxy
+      .as_ref()
+      .and_then(|proxy| proxy.get(NM_WIRELESS_INTERFACE, "HwAddress").ok());
+    let permanent_hw_address = device_proxy
+      .as_ref()
+      .and_then(|proxy| proxy.get(NM_WIRELESS_INTERFACE, "PermHwAddress").ok());
+    let interface = device_path.as_ref().and_then(|path| self.device_interface(path).ok());
+    let interfaces = self
+      .wifi_device_paths()
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|path| self.device_interface(path).ok())
+      .collect();
+    let capabilities = device_proxy.and_then(|proxy| {
+      Some(DeviceCapabilities {
+        device: proxy.get(NM_DEVICE_INTERFACE, "Capabilities").ok()?,
+        wireless: proxy.get(NM_WIRELESS_INTERFACE, "WirelessCapabilities").ok()?,
+      })
+    });
+    Ok(WifiDeviceInfo {
+      wifi_enabled,
+      connectivity_check_enabled,
+      connectivity,
+      initializing,
+      capabilities,
+      device_autoconnect,
+      hw_address,
+      permanent_hw_address,
+      interface,
+      interfaces,
+    })
+  }
+
+  fn get_wifi_networks(&self) -> Result<Vec<WifiInfo>> {
+    let nm = NetworkManager::new(&self.connection);
+    let devices = nm.get_devices().context("Failed to get devices")?;
+    // Without a WiFi device there's nothing to list
+    let interface = self.wifi_interface().ok();
+
+    // Batch load all connection info upfront to avoid repeated nmcli calls
+    let connection_info_map = self.get_all_connection_info()?;
+
+    let mut networks = Vec::new();
+    let mut active_bssid = None;
+
+    for device in devices {
+      if let Device::WiFi(wifi_device) = device
+        && interface.is_some()
+        && wifi_device.interface().ok() == interface
+      {
+        // Request a scan to refresh the cache. Scanning takes seconds on some adapters and NetworkManager rejects
+        // frequent requests anyway, so only ask occasionally and read the cached results in between.
+        if self
+          .last_scan_request
+          .get()
+          .is_none_or(|t| t.elapsed() >= SCAN_REQUEST_INTERVAL)
+        {
+          self.last_scan_request.set(Some(Instant::now()));
+          let last_scan = self.last_scan();
+          if wifi_device.request_scan(HashMap::new()).is_ok() {
+            self.wait_for_scan(last_scan);
+          }
+        }
+
+        // Get all access points
+        let device_path = self.wifi_device_path()?;
+        let access_points = self.access_points(&device_path)?;
+
+        // Check if device is active
+        let is_device_active = wifi_device.state().unwrap_or(0) == 100; // 100 = ACTIVATED
+
+        // Get active access point if connected
+        let active_ap = is_device_active
+          .then(|| self.active_access_point(&device_path))
+          .flatten()
+          .and_then(|path| access_points.iter().find(|ap| ap.path == path));
+        active_bssid = active_ap.and_then(|ap| ap.bssid.clone());
+        let active_ssid = active_ap.map(|ap| ap.ssid.clone());
+
+        for ap in access_points {
+          if ap.ssid.is_empty() {
+            continue;
+          }
+
+          let ssid = String::from_utf8_lossy(&ap.ssid).into_owned();
+          let strength = ap.strength;
+          let frequency = ap.frequency;
+          let bssid = ap.bssid;
+
+          // Determine security
+          let (security, weak_security) = decode_security(ap.flags, ap.wpa_flags, ap.rsn_flags);
+          let supports_tkip = (ap.wpa_flags | ap.rsn_flags) & NM_802_11_AP_SEC_PAIR_TKIP != 0;
+
+          // Other access points of the same network count as active too, so that duplicates collapse onto it
+          let is_active = active_ssid.as_ref() == Some(&ap.ssid);
+
+          // Look up connection info from the cache
+          let info = connection_info_map.get(&ssid);
+
+          networks.push(WifiInfo {
+            ssid,
+            ssid_bytes: ap.ssid,
+            strength,
+            raw_strength: strength,
+            security,
+            active: is_active,
+            weak_security,
+            supports_tkip,
+            known: info.is_some(),
+            uuid: info.map(|i| i.uuid.clone()),
+            priority: info.and_then(|i| i.priority),
+            autoconnect: info.and_then(|i| i.autoconnect),
+            autoconnect_retries: info.and_then(|i| i.autoconnect_retries),
+            ip4_method: info.and_then(|i| i.ip4_method),
+            ip6_privacy: info.and_then(|i| i.ip6_privacy),
+            metered: info.and_then(|i| i.metered),
+            traffic_control: info.map(|i| i.traffic_control.clone()).unwrap_or_default(),
+            powersave: info.and_then(|i| i.powersave),
+            never_default: info.and_then(|i| i.never_default),
+            zone: info.and_then(|i| i.zone.clone()),
+            // Filled in below for the active network only
+            persistence: None,
+            active_ip4: None,
+            bitrate: None,
+            frequency,
+            bssid,
+          });
+        }
+      }
+    }
+
+    // Sort by SSID first to ensure duplicates are consecutive, but put active ones first
+    networks.sort_by(|a, b| match a.ssid.cmp(&b.ssid) {
+      std::cmp::Ordering::Equal => {
+        if a.active {
+          std::cmp::Ordering::Less
+        } else if b.active {
+          std::cmp::Ordering::Greater
+        } else {
+          std::cmp::Ordering::Equal
+        }
+      }
+      other => other,
+    });
+
+    // Deduplicate - keeps the first occurrence (which is active if any duplicate is active)
+    networks.dedup_by(|a, b| a.ssid == b.ssid);
+
+    for net in networks.iter_mut().filter(|n| n.active) {
+      if let Some(active_path) = self.active_connection_path(&net.ssid_bytes) {
+        net.persistence = self.active_persistence(&active_path);
+        net.active_ip4 = self.active_ip4(&active_path);
+      }
+      net.bitrate = self.bitrate();
+      // Other access points share the SSID, but this is the one we're talking to
+      if let Some(bssid) = active_bssid.take() {
+        net.bssid = Some(bssid);
+      }
+    }
+
+    // Final sort: active networks first, then by strength
+    networks.sort_by(|a, b| {
+      if a.active {
+        std::cmp::Ordering::Less
+      } else if b.active {
+        std::cmp::Ordering::Greater
+      } else {
+        b.strength.cmp(&a.strength)
+      }
+    });
+
+    Ok(networks)
+  }
+
+  fn connect(
+    &self,
+    ssid: &[u8],
+    password: &str,
+    autoconnect: Option<bool>,
+    mac_address: Option<MacAddressPolicy>,
+  ) -> Result<()> {
+    // Cancelled before we even got to it
+    if self.cancel.is_cancelled() {
+      return Err(ConnectionError::Cancelled.into());
+    }
+    // Make sure there's a WiFi device to connect with
+    self.wifi_device_path()?;
+
+    // Check if this is a known network. Saved profiles are matched on their SSID bytes, as SSIDs that aren't UTF-8 can
+    // display the same.
+    let known = self.find_connection_uuid(ssid)?.is_some();
+
+    if known {
+      if let Some(autoconnect) = autoconnect {
+        self
+          .modify_connection(ssid, "connection.autoconnect", if autoconnect { "yes" } else { "no" })
+          .context("Failed to set autoconnect")?;
+      }
+      // Known network - use nmcli to activate (networkmanager-rs doesn't expose easy activation API)
+      let output = self.connection_up(ssid)?;
+
+      if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("AlreadyActive") || stderr.contains("already active") {
+          // Already active doesn't mean activated: it may still be connecting, or about to fail
+          return match self.active_connection_path(ssid) {
+            Some(active_path) => self.wait_for_connection_state(&active_path, &self.wifi_device_path()?),
+            // It went away in the meantime, so it can't have succeeded
+            None => Err(anyhow::anyhow!(
+              "Connection to {} was deactivated",
+              String::from_utf8_lossy(ssid)
+            )),
+          };
+        }
+        // nmcli's own error doesn't say why activation failed, but the device remembers. For known networks, keep the
+        // profile even if connection fails.
+        return Err(match self.device_state_reason() {
+          Some(NM_DEVICE_STATE_REASON_IP_ADDRESS_DUPLICATE) => {
+            ConnectionError::IpConflict(self.static_ipv4_address(ssid)).into()
+          }
+          Some(reason) if reason != 0 => ConnectionError::from_state_reason(reason).into(),
+          _ => anyhow::anyhow!("Failed to activate: {:?}", output),
+        });
+      }
+      Ok(())
+    } else {
+      self.add_and_connect(self.new_connection_settings(ssid, password, autoconnect, mac_address))
+    }
+  }
+
+  fn connect_enterprise(
+    &self,
+    ssid: &[u8],
+    credentials: &EnterpriseCredentials,
+    autoconnect: Option<bool>,
+    mac_address: Option<MacAddressPolicy>,
+  ) -> Result<()> {
+    self.add_and_connect(enterprise_connection_settings(
+      ssid,
+      credentials,
+      autoconnect,
+      mac_address,
+    ))
+  }
+
+  fn reactivate(&self, ssid: &[u8]) -> Result<()> {
+    let output = self.connection_up(ssid)?;
+
+    if output.status.success() {
+      Ok(())
+    } else {
+      Err(anyhow::anyhow!(
+        "Failed to reconnect: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+      ))
+    }
+  }
+
+  fn disconnect(&self) -> Result<()> {
+    // Deactivate whatever NetworkManager says is active right now, whoever activated it
+    for active_path in self.wifi_active_connections()? {
+      self.deactivate(active_path)?;
+    }
+
+    Ok(())
+  }
+
+  fn forget_network(&self, ssid: &[u8]) -> Result<()> {
+    // In some cases, eg RSN networks, nmcli does not create a network profile after a failed connection attempt. We
+    // consider a forgetting successful as long as no network profile exists afterwards.
+    for (path, _ssid, settings) in self.saved_wifi_settings()? {
+      if wifi_ssid_bytes(&settings) == Some(ssid) {
+        self.delete_connection(&path).context("Failed to forget network")?;
+      }
+    }
+    Ok(())
+  }
+
+  fn saved_ssids(&self) -> Result<Vec<Vec<u8>>> {
+    let mut ssids: Vec<Vec<u8>> = self
+      .saved_wifi_settings()?
+      .iter()
+      .filter_map(|(_path, _ssid, settings)| wifi_ssid_bytes(settings).map(<[u8]>::to_vec))
+      .collect();
+    // A network can have several profiles, but it's forgotten as one
+    ssids.sort();
+    ssids.dedup();
+    Ok(ssids)
+  }
+
+  fn toggle_autoconnect(&self, ssid: &str) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    // Get current value
+    let info = self
+      .get_connection_info(ssid)?
+      .context("Network not found in saved connections")?;
+
+    let current = info.autoconnect.unwrap_or(true);
+    let new_value = if current { "no" } else { "yes" };
+
+    self
+      .modify_connection(ssid, "connection.autoconnect", new_value)
+      .context("Failed to toggle autoconnect")
+  }
+
+  fn set_autoconnect(&self, ssid: &str, enabled: bool) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    self
+      .modify_connection(ssid, "connection.autoconnect", if enabled { "yes" } else { "no" })
+      .context("Failed to set autoconnect")
+  }
+
+  fn set_autoconnect_temporarily(&self, ssid: &str, enabled: bool) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    self
+      .run_connection_modify(
+        ssid,
+        &["--temporary"],
+        "connection.autoconnect",
+        if enabled { "yes" } else { "no" },
+      )
+      .context("Failed to set autoconnect")
+  }
+
+  fn set_ip4_method(&self, ssid: &str, method: Ip4Method) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    self
+      .modify_connection(ssid, "ipv4.method", method.nmcli_value())
+      .context("Failed to set IPv4 method")
+  }
+
+  fn set_ip6_privacy(&self, ssid: &str, value: Ip6Privacy) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    self
+      .modify_connection(ssid, "ipv6.ip6-privacy", value.nmcli_value())
+      .context("Failed to set IPv6 privacy")
+  }
+
+  fn set_zone(&self, ssid: &str, zone: Option<&str>) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    // An empty value clears the setting
+    self
+      .modify_connection(ssid, "connection.zone", zone.unwrap_or(""))
+      .context("Failed to set firewall zone")
+  }
+
+  fn check_connectivity(&self) -> Result<Connectivity> {
+    let (state,): (u32,) = self
+      .nm_proxy()
+      .method_call(NM_INTERFACE, "CheckConnectivity", ())
+      .context("Failed to check connectivity")?;
+    Ok(Connectivity::from_nm(state))
+  }
+
+  fn set_connectivity_check(&self, enabled: bool) -> Result<()> {
+    self
+      .nm_proxy()
+      .set(NM_INTERFACE, "ConnectivityCheckEnabled", enabled)
+      .context("Failed to change connectivity checking")
+  }
+
+  fn set_device_autoconnect(&self, enabled: bool) -> Result<()> {
+    self
+      .connection
+      .with_proxy(NM_BUS, self.wifi_device_path()?, DBUS_TIMEOUT)
+      .set(NM_DEVICE_INTERFACE, "Autoconnect", enabled)
+      .context("Failed to change the device's auto-connect")
+  }
+
+  fn export_profile(&self, ssid: &str, include_secrets: bool) -> Result<String> {
+    Ok(keyfile(
+      &self.saved_settings(&self.saved_ssid_bytes(ssid)?, include_secrets)?,
+    ))
+  }
+
+  fn saved_password(&self, ssid: &str) -> Result<Option<String>> {
+    Ok(password_from_settings(
+      &self.saved_settings(&self.saved_ssid_bytes(ssid)?, true)?,
+    ))
+  }
+
+  fn export_profiles(&self) -> Result<ExportSummary> {
+    // Keep going after individual failures so one profile with unreadable secrets doesn't hold back the rest
+    let mut summary = ExportSummary::default();
+    for (path, ssid, mut settings) in self.saved_wifi_settings()? {
+      match self.add_secrets(&path, &mut settings) {
+        Ok(()) => summary.profiles.push(exported_profile(ssid, &settings)),
+        Err(e) => summary.failed.push(format!("{}: {:#}", ssid, e)),
+      }
+    }
+    Ok(summary)
+  }
+
+  fn import_profiles(&self, profiles: Vec<ExportedProfile>, overwrite: bool) -> Result<ImportSummary> {
+    let saved: HashMap<String, dbus::Path<'static>> = self
+      .saved_wifi_settings()?
+      .into_iter()
+      .map(|(path, ssid, _settings)| (ssid, path))
+      .collect();
+
+    let mut summary = ImportSummary::default();
+    for profile in profiles {
+      let existing = saved.get(&profile.ssid);
+      let result = import_settings(&profile, existing.is_some(), overwrite).and_then(|settings| {
+        let Some(settings) = settings else {
+          return Ok(false);
+        };
+        match existing {
+          // Updating in place keeps the profile's UUID, and doesn't lose it if the new settings are rejected
+          Some(path) => self
+            .connection
+            .with_proxy(NM_BUS, path.clone(), DBUS_TIMEOUT)
+            .method_call(NM_SETTINGS_CONNECTION_INTERFACE, "Update", (settings,))
+            .context("Failed to update the saved profile")?,
+          None => self
+            .connection
+            .with_proxy(NM_BUS, NM_SETTINGS_PATH, DBUS_TIMEOUT)
+            .method_call(NM_SETTINGS_INTERFACE, "AddConnection", (settings,))
+            .map(|_: (dbus::Path<'static>,)| ())
+            .context("Failed to save the profile")?,
+        }
+        Ok(true)
+      });
+      match result {
+        Ok(true) => summary.imported += 1,
+        Ok(false) => summary.skipped += 1,
+        Err(e) => summary.failed.push(format!("{}: {:#}", profile.ssid, e)),
+      }
+    }
+    Ok(summary)
+  }
+
+  fn update_password(&self, ssid: &str, password: &str) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    // Update replaces the whole profile, so send back everything we read, the other secrets included
+    let (path, mut settings) = self.saved_settings_at(ssid, true)?;
+    set_password(&mut settings, password)?;
+    let _: () = self
+      .connection
+      .with_proxy(NM_BUS, path, DBUS_TIMEOUT)
+      .method_call(NM_SETTINGS_CONNECTION_INTERFACE, "Update", (settings,))
+      .context("Failed to save the new password")?;
+    Ok(())
+  }
+
+  fn backup_profile(&self, ssid: &str) -> Result<ProfileBackup> {
+    Ok(ProfileBackup {
+      ssid: ssid.to_string(),
+      settings: self.saved_settings(&self.saved_ssid_bytes(ssid)?, true)?,
+    })
+  }
+
+  fn restore_profile(&self, backup: ProfileBackup) -> Result<()> {
+    let _: (dbus::Path<'static>,) = self
+      .connection
+      .with_proxy(NM_BUS, NM_SETTINGS_PATH, DBUS_TIMEOUT)
+      .method_call(NM_SETTINGS_INTERFACE, "AddConnection", (backup.settings,))
+      .with_context(|| format!("Failed to restore the profile for {}", backup.ssid))?;
+    Ok(())
+  }
+
+  fn compare_settings(&self, ssid: &str) -> Result<Vec<SettingComparison>> {
+    let saved = self.saved_settings(&self.saved_ssid_bytes(ssid)?, false)?;
+
+    // Only compare against the device if it's running this very connection
+    let uuid = |settings: &HashMap<String, PropMap>| {
+      settings
+        .get("connection")
+        .and_then(|c| prop_cast::<String>(c, "uuid"))
+        .cloned()
+    };
+    let applied = self
+      .applied_wifi_settings()?
+      .filter(|applied| uuid(applied).is_some() && uuid(applied) == uuid(&saved));
+
+    Ok(
+      COMPARED_SETTINGS
+        .iter()
+        .map(|&(name, setting, key)| SettingComparison {
+          name,
+          saved: setting_value(&saved, setting, key),
+          applied: applied.as_ref().map(|applied| setting_value(applied, setting, key)),
+        })
+        .collect(),
+    )
+  }
+
+  fn select_wifi_interface(&self, interface: &str) -> Result<()> {
+    let device_path = self
+      .wifi_device_paths()?
+      .into_iter()
+      .find(|path| self.device_interface(path).is_ok_and(|name| name == interface))
+      .with_context(|| format!("No WiFi device {}", interface))?;
+    *self.selected_device.borrow_mut() = Some(device_path);
+    // The other device's scan doesn't say anything about this one's, so scan right away
+    self.last_scan_request.set(None);
+    Ok(())
+  }
+
+  fn set_wifi_enabled(&self, enabled: bool) -> Result<()> {
+    self
+      .nm_proxy()
+      .set(NM_INTERFACE, "WirelessEnabled", enabled)
+      .context("Failed to change WiFi state")?;
+    if !enabled {
+      return Ok(());
+    }
+
+    // The device stays unavailable until the radio and wpa_supplicant are back up, and can't be activated before then
+    let device_state = || {
+      let device_path = self.wifi_device_path().ok()?;
+      let device_proxy = self.connection.with_proxy(NM_BUS, device_path, DBUS_TIMEOUT);
+      Some(device_proxy.get::<u32>(NM_DEVICE_INTERFACE, "State").unwrap_or(0))
+    };
+    wait_for_device_available(device_state, DEVICE_AVAILABLE_TIMEOUT)
+  }
+
+  fn set_powersave(&self, ssid: &str, enabled: bool) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    // NM_SETTING_WIRELESS_POWERSAVE_ENABLE / _DISABLE
+    let value = if enabled { "3" } else { "2" };
+    self
+      .modify_connection(ssid, "802-11-wireless.powersave", value)
+      .context("Failed to set power saving")
+  }
+
+  fn set_never_default(&self, ssid: &str, never_default: bool) -> Result<()> {
+    let ssid = &self.saved_ssid_bytes(ssid)?;
+    let value = if never_default { "yes" } else { "no" };
+    for setting in ["ipv4.never-default", "ipv6.never-default"] {
+      self
+        .modify_connection(ssid, setting, value)
+        .context("Failed to change the default route setting")?;
+    }
+    Ok(())
+  }
+}
+
+/// Derives a short security label (e.g. "WPA2/WPA3") from an access point's `Flags`, `WpaFlags`, and `RsnFlags`, and
+/// whether that security is weak enough to warn about before connecting.
+fn decode_security(flags: u32, wpa_flags: u32, rsn_flags: u32) -> (String, bool) {
+  if wpa_flags == 0 && rsn_flags == 0 {
+    // Without WPA/RSN, the privacy bit is the only thing distinguishing WEP from no encryption at all
+    return if flags & NM_802_11_AP_FLAGS_PRIVACY != 0 {
+      ("WEP".to_string(), true)
+    } else {
+      ("Open".to_string(), true)
+    };
+  }
+
+  let mut modes = Vec::new();
+  if wpa_flags != 0 {
+    modes.push("WPA");
+  }
+
+  let mut rsn_modes = Vec::new();
+  if rsn_flags & NM_802_11_AP_SEC_KEY_MGMT_EAP_SUITE_B_192 != 0 {
+    rsn_modes.push("WPA3-Ent");
+  } else if rsn_flags & NM_802_11_AP_SEC_KEY_MGMT_802_1X != 0 {
+    rsn_modes.push("WPA2-Ent");
+  } else {
+    // PSK and SAE together is a WPA2/WPA3 transition network
+    if rsn_flags & NM_802_11_AP_SEC_KEY_MGMT_PSK != 0 {
+      rsn_modes.push("WPA2");
+    }
+    if rsn_flags & NM_802_11_AP_SEC_KEY_MGMT_SAE != 0 {
+      rsn_modes.push("WPA3");
+    }
+    if rsn_flags & (NM_802_11_AP_SEC_KEY_MGMT_OWE | NM_802_11_AP_SEC_KEY_MGMT_OWE_TM) != 0 {
+      rsn_modes.push("OWE");
+    }
+  }
+  if rsn_flags != 0 && rsn_modes.is_empty() {
+    // RSN with a key management we don't recognise
+    rsn_modes.push("RSN");
+  }
+  modes.extend(rsn_modes);
+
+  (modes.join("/"), false)
+}
+
+/// The SSID of a WiFi connection's settings (as returned by `GetSettings`) for display, or `None` for other connection
+/// types.
+fn wifi_ssid(settings: &HashMap<String, PropMap>) -> Option<String> {
+  wifi_ssid_bytes(settings).map(|ssid| String::from_utf8_lossy(ssid).into_owned())
+}
+
+/// Like `wifi_ssid`, but the raw SSID. It's a byte array (`ay`), which dbus-rs hands us as a `Vec<u8>`.
+fn wifi_ssid_bytes(settings: &HashMap<String, PropMap>) -> Option<&[u8]> {
+  prop_cast::<Vec<u8>>(settings.get("802-11-wireless")?, "ssid").map(Vec::as_slice)
+}
+
+/// Settings for a new WiFi profile. `ap_flags` are the access point's (WPA, RSN) flags, if we found it.
+///
+/// An empty password leaves out the security block entirely, which is what an open network needs. That also covers open
+/// networks we mistook for secured ones (eg. misparsed flags): submitting the password prompt empty still connects.
+fn new_connection_settings(
+  ssid: &[u8],
+  password: &str,
+  ap_flags: Option<(u32, u32)>,
+  autoconnect: Option<bool>,
+  mac_address: Option<MacAddressPolicy>,
+) -> HashMap<&'static str, PropMap> {
+  let mut connection: PropMap = HashMap::new();
+  connection.insert("type".into(), Variant(Box::new("802-11-wireless".to_string())));
+  connection.insert(
+    "id".into(),
+    Variant(Box::new(String::from_utf8_lossy(ssid).into_owned())),
+  );
+  // Left out otherwise, so the profile gets NetworkManager's default
+  if let Some(autoconnect) = autoconnect {
+    connection.insert("autoconnect".into(), Variant(Box::new(autoconnect)));
+  }
+
+  let mut wireless: PropMap = HashMap::new();
+  wireless.insert("ssid".into(), Variant(Box::new(ssid.to_vec())));
+  wireless.insert("mode".into(), Variant(Box::new("infrastructure".to_string())));
+  if let Some(policy) = mac_address {
+    wireless.insert(
+      "cloned-mac-address".into(),
+      Variant(Box::new(policy.value().to_string())),
+    );
+  }
+
+  let mut settings = HashMap::new();
+  settings.insert("connection", connection);
+  settings.insert("802-11-wireless", wireless);
+
+  // Only add a security block when we actually have a secret to put in it
+  if !password.is_empty() {
+    let mut security: PropMap = HashMap::new();
+    match ap_flags {
+      // No WPA/RSN flags means a WEP network
+      Some((0, 0)) => {
+        security.insert("key-mgmt".into(), Variant(Box::new("none".to_string())));
+        security.insert("wep-key0".into(), Variant(Box::new(password.to_string())));
+      }
+      Some((wpa_flags, rsn_flags)) => {
+        security.insert(
+          "key-mgmt".into(),
+          Variant(Box::new(key_mgmt_for_flags(wpa_flags, rsn_flags).to_string())),
+        );
+        security.insert("psk".into(), Variant(Box::new(password.to_string())));
+      }
+      None => {
+        security.insert("key-mgmt".into(), Variant(Box::new("wpa-psk".to_string())));
+        security.insert("psk".into(), Variant(Box::new(password.to_string())));
+      }
+    }
+    settings.insert("802-11-wireless-security", security);
+  }
+
+  settings
+}
+
+/// Settings for a new 802.1X (WPA-Enterprise) profile. The server certificate isn't checked, as there's nowhere to get
+/// a CA certificate from in this flow; that's also what most OS prompts for these networks do by default.
+fn enterprise_connection_settings(
+  ssid: &[u8],
+  credentials: &EnterpriseCredentials,
+  autoconnect: Option<bool>,
+  mac_address: Option<MacAddressPolicy>,
+) -> HashMap<&'static str, PropMap> {
+  let mut settings = new_connection_settings(ssid, "", None, autoconnect, mac_address);
+
+  let mut security: PropMap = HashMap::new();
+  security.insert("key-mgmt".into(), Variant(Box::new("wpa-eap".to_string())));
+  settings.insert("802-11-wireless-security", security);
+
+  let mut eap: PropMap = HashMap::new();
+  eap.insert(
+    "eap".into(),
+    Variant(Box::new(vec![credentials.method.eap().to_string()])),
+  );
+  eap.insert("identity".into(), Variant(Box::new(credentials.identity.clone())));
+  eap.insert("password".into(), Variant(Box::new(credentials.password.clone())));
+  eap.insert(
+    "phase2-auth".into(),
+    Variant(Box::new(credentials.method.phase2_auth().to_string())),
+  );
+  settings.insert("802-1x", eap);
+
+  settings
+}
+
+/// The password in a connection's settings (read with secrets): the PSK, a WEP key, or an 802.1X password.
+fn password_from_settings(settings: &HashMap<String, PropMap>) -> Option<String> {
+  [
+    ("802-11-wireless-security", "psk"),
+    ("802-11-wireless-security", "wep-key0"),
+    ("802-1x", "password"),
+  ]
+  .into_iter()
+  .find_map(|(setting, key)| prop_cast::<String>(settings.get(setting)?, key).cloned())
+  .filter(|password| !password.is_empty())
+}
+
+fn exported_profile(ssid: String, settings: &HashMap<String, PropMap>) -> ExportedProfile {
+  let connection = settings.get("connection");
+  ExportedProfile {
+    ssid,
+    security: settings
+      .get("802-11-wireless-security")
+      .and_then(|security| prop_cast::<String>(security, "key-mgmt"))
+      .cloned(),
+    password: password_from_settings(settings),
+    priority: connection
+      .and_then(|c| prop_cast::<i32>(c, "autoconnect-priority"))
+      .copied()
+      .unwrap_or(0),
+    // NetworkManager leaves out settings at their default, and autoconnect defaults to on
+    autoconnect: connection
+      .and_then(|c| prop_cast::<bool>(c, "autoconnect"))
+      .copied()
+      .unwrap_or(true),
+  }
+}
+
+/// The settings importing an exported profile saves, `None` when its network already has a profile that's to be kept.
+/// Errors say why the profile can't be imported.
+pub fn import_settings(
+  profile: &ExportedProfile,
+  already_saved: bool,
+  overwrite: bool,
+) -> Result<Option<HashMap<&'static str, PropMap>>> {
+  if already_saved && !overwrite {
+    return Ok(None);
+  }
+  imported_connection_settings(profile).map(Some)
+}
+
+/// Settings for a new profile from an exported one, as `new_connection_settings` would create it for an access point
+/// with the same security.
+fn imported_connection_settings(profile: &ExportedProfile) -> Result<HashMap<&'static str, PropMap>> {
+  let ap_flags = match profile.security.as_deref() {
+    None => None,
+    Some("none") => Some((0, 0)),
+    Some("wpa-psk") => Some((0, NM_802_11_AP_SEC_KEY_MGMT_PSK)),
+    Some("sae") => Some((0, NM_802_11_AP_SEC_KEY_MGMT_SAE)),
+    Some("wpa-eap") => anyhow::bail!("Enterprise logins can't be imported, connect to enter yours again"),
+    Some(other) => anyhow::bail!("Unsupported security \"{}\"", other),
+  };
+  let password = profile.password.as_deref().unwrap_or_default();
+  if ap_flags.is_some() && password.is_empty() {
+    // Saving it without one would turn it into an open network
+    anyhow::bail!("No password was exported");
+  }
+
+  let mut settings = new_connection_settings(
+    profile.ssid.as_bytes(),
+    password,
+    ap_flags,
+    Some(profile.autoconnect),
+    None,
+  );
+  if let Some(connection) = settings.get_mut("connection") {
+    connection.insert("autoconnect-priority".into(), Variant(Box::new(profile.priority)));
+  }
+  Ok(settings)
+}
+
+/// Stores a new password wherever the profile's kind of security keeps it: the PSK, WEP key 0, or the 802.1X password.
+fn set_password(settings: &mut HashMap<String, PropMap>, password: &str) -> Result<()> {
+  let key_mgmt = settings
+    .get("802-11-wireless-security")
+    .and_then(|security| prop_cast::<String>(security, "key-mgmt"))
+    .cloned();
+  let (setting, key) = match key_mgmt.as_deref() {
+    Some("wpa-psk" | "sae") => ("802-11-wireless-security", "psk"),
+    Some("none") => ("802-11-wireless-security", "wep-key0"),
+    Some("wpa-eap") => ("802-1x", "password"),
+    _ => anyhow::bail!("This network has no password to change"),
+  };
+  settings
+    .entry(setting.to_string())
+    .or_default()
+    .insert(key.to_string(), Variant(Box::new(password.to_string())));
+  Ok(())
+}
+
+/// The `key-mgmt` for a password-protected profile on an access point with the given WPA and RSN flags.
+///
+/// WPA3-only networks need `sae`. Transition networks offering both PSK and SAE get `wpa-psk`: NetworkManager then
+/// negotiates SAE itself when the driver and wpa_supplicant support it, and falls back to WPA2 when they don't.
+fn key_mgmt_for_flags(wpa_flags: u32, rsn_flags: u32) -> &'static str {
+  let psk = (wpa_flags | rsn_flags) & NM_802_11_AP_SEC_KEY_MGMT_PSK != 0;
+  if rsn_flags & NM_802_11_AP_SEC_KEY_MGMT_SAE != 0 && !psk {
+    "sae"
+  } else {
+    "wpa-psk"
+  }
+}
+
+/// Renders connection settings in NetworkManager's keyfile format, as found in
+/// `/etc/NetworkManager/system-connections/*.nmconnection`. Values that keyfiles store differently from D-Bus are
+/// converted where we know how (addresses, MACs, DNS servers); deprecated duplicates and unknown types are left out.
+fn keyfile(settings: &HashMap<String, PropMap>) -> String {
+  let mut sections: Vec<&String> = settings.keys().collect();
+  // NetworkManager writes [connection] first, the rest alphabetically
+  sections.sort_by_key(|name| (*name != "connection", *name));
+
+  let mut text = String::new();
+  for section in sections {
+    let values = &settings[section];
+    let mut keys: Vec<&String> = values.keys().collect();
+    keys.sort();
+
+    text.push_str(&format!("[{}]\n", section));
+    for key in keys {
+      let value = &*values[key].0;
+      match key.as_str() {
+        // Superseded by address-data and dns-data, and in formats keyfiles don't use
+        "addresses" | "routes" | "route-data" | "dns" => {}
+        "address-data" => {
+          for (i, fields) in address_data(value).iter().enumerate() {
+            if let (Some(address), Some(prefix)) = (fields.get("address"), fields.get("prefix")) {
+              text.push_str(&format!("address{}={}/{}\n", i + 1, address, prefix));
+            }
+          }
+        }
+        "dns-data" => text.push_str(&format!("dns={}\n", keyfile_list(value))),
+        _ => {
+          if let Some(value) = keyfile_value(key, value) {
+            text.push_str(&format!("{}={}\n", key, value));
+          }
+        }
+      }
+    }
+    text.push('\n');
+  }
+  text
+}
+
+fn keyfile_value(key: &str, value: &dyn RefArg) -> Option<String> {
+  match value.signature().to_string().as_str() {
+    "s" => value.as_str().map(str::to_string),
+    "b" => Some(if value.as_u64() == Some(1) { "true" } else { "false" }.to_string()),
+    "y" | "i" | "u" | "x" | "t" => value.as_i64().map(|i| i.to_string()),
+    "as" => Some(keyfile_list(value)),
+    "ay" => {
+      let bytes: Vec<u8> = value.as_iter()?.filter_map(|b| b.as_u64()).map(|b| b as u8).collect();
+      if key.ends_with("mac-address") || key == "bssid" {
+        Some(bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":"))
+      } else {
+        // SSIDs are written as text when they can be, and as a list of bytes otherwise
+        match String::from_utf8(bytes.clone()) {
+          Ok(text) if !text.contains(';') && !text.chars().any(char::is_control) => Some(text),
+          _ => Some(bytes.iter().map(|b| format!("{};", b)).collect()),
+        }
+      }
+    }
+    _ => None,
+  }
+}
+
+/// Keyfile lists are semicolon-terminated, eg. `dns=1.1.1.1;9.9.9.9;`
+fn keyfile_list(value: &dyn RefArg) -> String {
+  value
+    .as_iter()
+    .into_iter()
+    .flatten()
+    .map(|item| format!("{};", format_ref_arg(item)))
+    .collect()
+}
+
+/// A single 0-100 rating of how good a network is to connect to, for users who don't want to weigh signal, security
+/// and band themselves. Signal counts the most since a weak link is unusable regardless of the rest.
+pub fn quality_score(net: &WifiInfo) -> u8 {
+  let security = if net.security.contains("WPA3") {
+    100
+  } else if net.security.contains("WPA2") {
+    80
+  } else if net.security.contains("OWE") {
+    60
+  } else if net.security.contains("WPA") {
+    40
+  } else {
+    // WEP and open networks
+    0
+  };
+  // 5 and 6 GHz are faster and less crowded than 2.4 GHz
+  let band = match net.frequency {
+    Some(freq) if freq >= 5000 => 100,
+    Some(_) => 40,
+    None => 50,
+  };
+  ((net.strength as u32 * 60 + security * 25 + band * 15) / 100) as u8
+}
+
+/// The entries of an `address-data` setting, each as its fields, eg. "address" => "192.168.1.5" and "prefix" => "24"
+fn address_data(value: &dyn RefArg) -> Vec<HashMap<String, String>> {
+  value
+    .as_iter()
+    .into_iter()
+    .flatten()
+    .map(|address| {
+      // Dicts iterate as alternating keys and values
+      address
+        .as_iter()
+        .into_iter()
+        .flatten()
+        .map(format_ref_arg)
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .filter_map(|pair| Some((pair.first()?.clone(), pair.get(1)?.clone())))
+        .collect()
+    })
+    .collect()
+}
+
+/// The first address in a saved connection's `ipv4.address-data`, without the prefix length
+fn first_ipv4_address(settings: &HashMap<String, PropMap>) -> Option<String> {
+  let value = settings.get("ipv4")?.get("address-data")?;
+  address_data(&*value.0).into_iter().next()?.remove("address")
+}
+
+/// Formats a single setting for display. NetworkManager leaves settings at their default value out of the map entirely.
+fn setting_value(settings: &HashMap<String, PropMap>, setting: &str, key: &str) -> String {
+  let Some(value) = settings.get(setting).and_then(|s| s.get(key)) else {
+    return "default".to_string();
+  };
+  if (setting, key) == ("ipv4", "dns") {
+    // Addresses as u32s in network byte order
+    return value
+      .0
+      .as_iter()
+      .into_iter()
+      .flatten()
+      .filter_map(|addr| addr.as_u64())
+      .map(|addr| std::net::Ipv4Addr::from((addr as u32).to_ne_bytes()).to_string())
+      .collect::<Vec<_>>()
+      .join(", ");
+  }
+  format_ref_arg(&*value.0)
+}
+
+fn format_ref_arg(value: &dyn RefArg) -> String {
+  if let Some(s) = value.as_str() {
+    s.to_string()
+  } else if value.arg_type() == ArgType::Boolean {
+    if value.as_u64() == Some(1) { "yes" } else { "no" }.to_string()
+  } else if let Some(i) = value.as_i64() {
+    i.to_string()
+  } else if let Some(items) = value.as_iter() {
+    // Dicts iterate as alternating keys and values, eg. the `address-data` entries
+    let items: Vec<String> = items.map(format_ref_arg).collect();
+    if value.signature().starts_with("a{") {
+      items.chunks(2).map(|pair| pair.join("=")).collect::<Vec<_>>().join(" ")
+    } else {
+      items.join(", ")
+    }
+  } else {
+    format!("{:?}", value)
+  }
+}
+
+/// Human-readable explanation of the common NMDeviceStateReason values, see nm-dbus-interface.h. NetworkManager
+/// only reports why an activation failed on the device, so these are what connection errors can tell.
+fn reason_to_string(reason: u32) -> &'static str {
+  match reason {
+    0 => "NetworkManager gave no reason",
+    2 => "the device is now managed",
+    3 => "the device is no longer managed",
+    4 => "the device could not be readied for configuration",
+    5 => "no IP configuration could be obtained",
+    6 => "the IP configuration is no longer valid",
+    7 => "secrets were required but not provided",
+    8 => "the supplicant disconnected, often a wrong password",
+    9 => "the supplicant configuration failed",
+    10 => "the supplicant failed",
+    11 => "the supplicant timed out",
+    15 => "the DHCP client failed to start",
+    16 => "the DHCP client reported an error",
+    17 => "the DHCP client failed or timed out",
+    36 => "the device was removed",
+    37 => "the system is going to sleep",
+    38 => "the connection was removed",
+    39 => "the device was disconnected by the user",
+    40 => "the carrier/link changed",
+    42 => "the supplicant is now available",
+    53 => "the network could not be found",
+    54 => "a secondary connection (eg. VPN) failed",
+    60 => "a new activation was started",
+    NM_DEVICE_STATE_REASON_IP_ADDRESS_DUPLICATE => "the IP address is already in use",
+    65 => "the IP method isn't supported",
+    _ => "an unknown reason",
+  }
+}
+
+/// Parses `nmcli --terse --fields ...` output ("name:value" per line) into a map. nmcli escapes colons inside values,
+/// so splitting at the first colon is safe.
+fn parse_terse_fields(output: &str) -> HashMap<String, String> {
+  output
+    .lines()
+    .filter_map(|line| line.split_once(':'))
+    .map(|(name, value)| (name.to_string(), value.trim().replace("\\:", ":")))
+    .collect()
+}
+
+/// Waits for the WiFi device to come out of the unavailable state after the radio was turned on. `device_state` reads
+/// its `State`, or `None` when there's no WiFi device at all (eg. an unplugged USB adapter). The radio is on either
+/// way, so having no device to wait for isn't a failure.
+fn wait_for_device_available(device_state: impl Fn() -> Option<u32>, timeout: Duration) -> Result<()> {
+  let start = Instant::now();
+  loop {
+    match device_state() {
+      None => return Ok(()),
+      Some(state) if state > NM_DEVICE_STATE_UNAVAILABLE => return Ok(()),
+      Some(_) if start.elapsed() >= timeout => {
+        return Err(anyhow::anyhow!(
+          "WiFi was turned on but the device did not become available"
+        ));
+      }
+      Some(_) => std::thread::sleep(Duration::from_millis(200)),
+    }
+  }
+}
+
+/// Turns D-Bus errors from the activation methods into something a user can act on. NetworkManager reports most
+/// up-front failures (bad settings, missing permissions, ...) as named D-Bus errors.
+fn describe_activation_error(e: &dbus::Error) -> String {
+  let message = e.message().unwrap_or("unknown error");
+  match e.name().unwrap_or_default() {
+    "org.freedesktop.NetworkManager.PermissionDenied" => format!("Permission denied: {}", message),
+    "org.freedesktop.NetworkManager.Settings.Connection.InvalidProperty"
+    | "org.freedesktop.NetworkManager.Settings.InvalidProperty" => format!("Invalid connection settings: {}", message),
+    "org.freedesktop.NetworkManager.UnknownDevice" | "org.freedesktop.NetworkManager.Device.NotAllowed" => {
+      format!("WiFi device unavailable: {}", message)
+    }
+    _ => format!("Failed to connect: {}", message),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Remaining cipher bits as reported alongside the key management
+  const PAIR_TKIP: u32 = NM_802_11_AP_SEC_PAIR_TKIP;
+  const PAIR_CCMP: u32 = 0x8;
+  const GROUP_TKIP: u32 = 0x40;
+  const GROUP_CCMP: u32 = 0x80;
+
+  #[test]
+  fn wifi_ssid_reads_raw_bytes() {
+    let ssid = "Café: 2nd floor";
+    let mut wireless: PropMap = HashMap::new();
+    wireless.insert("ssid".into(), Variant(Box::new(ssid.as_bytes().to_vec())));
+    let settings = HashMap::from([("802-11-wireless".to_string(), wireless)]);
+    assert_eq!(wifi_ssid(&settings).as_deref(), Some(ssid));
+
+    let wired = HashMap::from([("802-3-ethernet".to_string(), PropMap::new())]);
+    assert_eq!(wifi_ssid(&wired), None);
+  }
+
+  #[test]
+  fn non_utf8_ssids_are_saved_as_broadcast() {
+    // "Café" in Latin-1
+    let ssid = b"Caf\xe9";
+    let settings = settings_owned(new_connection_settings(ssid, "", None, None, None));
+    assert_eq!(wifi_ssid_bytes(&settings), Some(&ssid[..]));
+    assert_eq!(wifi_ssid(&settings).as_deref(), Some("Caf\u{FFFD}"));
+    assert_eq!(settings["connection"]["id"].0.as_str(), Some("Caf\u{FFFD}"));
+  }
+
+  #[test]
+  fn quality_score_prefers_strong_secure_fast_networks() {
+    let net = |strength, security: &str, frequency| WifiInfo {
+      strength,
+      security: security.to_string(),
+      frequency: Some(frequency),
+      ..WifiInfo::default()
+    };
+    assert_eq!(quality_score(&net(100, "WPA3", 5180)), 100);
+    assert_eq!(quality_score(&net(0, "Open", 2412)), 6);
+    // Security and band can't make up for a much weaker signal
+    assert!(quality_score(&net(80, "WPA2", 2437)) > quality_score(&net(30, "WPA3", 5180)));
+    assert!(quality_score(&net(60, "WPA2", 5180)) > quality_score(&net(60, "WPA2", 2437)));
+    assert!(quality_score(&net(60, "WPA2", 2437)) > quality_score(&net(60, "WEP", 2437)));
+  }
+
+  #[test]
+  fn new_profile_without_password_has_no_security() {
+    // The AP claims WPA2 but the user submitted an empty password, as for an open network with misparsed flags
+    let settings = new_connection_settings(b"Cafe", "", Some((0, NM_802_11_AP_SEC_KEY_MGMT_PSK)), None, None);
+    assert!(!settings.contains_key("802-11-wireless-security"));
+
+    let settings = new_connection_settings(
+      b"Home",
+      "hunter22",
+      Some((0, NM_802_11_AP_SEC_KEY_MGMT_PSK)),
+      None,
+      None,
+    );
+    let security = &settings["802-11-wireless-security"];
+    assert_eq!(security["key-mgmt"].0.as_str(), Some("wpa-psk"));
+    assert_eq!(security["psk"].0.as_str(), Some("hunter22"));
+
+    let settings = new_connection_settings(b"Old", "abcde", Some((0, 0)), None, None);
+    assert_eq!(
+      settings["802-11-wireless-security"]["key-mgmt"].0.as_str(),
+      Some("none")
+    );
+  }
+
+  #[test]
+  fn enterprise_profile_defaults_to_peap_mschapv2() {
+    let credentials = EnterpriseCredentials {
+      method: EapMethod::default(),
+      identity: "jdoe@example.edu".to_string(),
+      password: "hunter22".to_string(),
+    };
+    let settings = enterprise_connection_settings(b"eduroam", &credentials, None, None);
+    assert_eq!(
+      settings["802-11-wireless-security"]["key-mgmt"].0.as_str(),
+      Some("wpa-eap")
+    );
+    let eap = &settings["802-1x"];
+    assert_eq!(prop_cast::<Vec<String>>(eap, "eap"), Some(&vec!["peap".to_string()]));
+    assert_eq!(eap["identity"].0.as_str(), Some("jdoe@example.edu"));
+    assert_eq!(eap["password"].0.as_str(), Some("hunter22"));
+    assert_eq!(eap["phase2-auth"].0.as_str(), Some("mschapv2"));
+  }
+
+  #[test]
+  fn wpa3_only_networks_use_sae() {
+    assert_eq!(key_mgmt_for_flags(0, NM_802_11_AP_SEC_KEY_MGMT_SAE), "sae");
+    assert_eq!(key_mgmt_for_flags(0, NM_802_11_AP_SEC_KEY_MGMT_PSK), "wpa-psk");
+    assert_eq!(key_mgmt_for_flags(NM_802_11_AP_SEC_KEY_MGMT_PSK, 0), "wpa-psk");
+    // Transition networks stay on wpa-psk, which NetworkManager upgrades to SAE where it can
+    assert_eq!(
+      key_mgmt_for_flags(0, NM_802_11_AP_SEC_KEY_MGMT_PSK | NM_802_11_AP_SEC_KEY_MGMT_SAE),
+      "wpa-psk"
+    );
+
+    let settings = new_connection_settings(
+      b"Modern",
+      "hunter22",
+      Some((0, NM_802_11_AP_SEC_KEY_MGMT_SAE)),
+      None,
+      None,
+    );
+    assert_eq!(settings["802-11-wireless-security"]["key-mgmt"].0.as_str(), Some("sae"));
+  }
+
+  #[test]
+  fn new_profile_can_opt_out_of_autoconnect() {
+    let settings = new_connection_settings(b"Hotel", "", None, None, None);
+    assert!(!settings["connection"].contains_key("autoconnect"));
+
+    let settings = new_connection_settings(b"Hotel", "", None, Some(false), None);
+    assert_eq!(settings["connection"]["autoconnect"].0.as_u64(), Some(0));
+
+    let settings = new_connection_settings(b"Hotel", "", None, Some(true), None);
+    assert_eq!(settings["connection"]["autoconnect"].0.as_u64(), Some(1));
+  }
+
+  #[test]
+  fn saved_password_comes_from_whichever_secret_is_stored() {
+    let settings = settings_owned(new_connection_settings(b"Home", "hunter22", None, None, None));
+    assert_eq!(password_from_settings(&settings).as_deref(), Some("hunter22"));
+
+    let settings = settings_owned(new_connection_settings(b"Old", "abcde", Some((0, 0)), None, None));
+    assert_eq!(password_from_settings(&settings).as_deref(), Some("abcde"));
+
+    let settings = settings_owned(new_connection_settings(b"Cafe", "", None, None, None));
+    assert_eq!(password_from_settings(&settings), None);
+  }
+
+  #[test]
+  fn exported_profiles_keep_password_priority_and_autoconnect() {
+    let mut settings = settings_owned(new_connection_settings(b"Home", "hunter22", None, Some(false), None));
+    settings
+      .get_mut("connection")
+      .unwrap()
+      .insert("autoconnect-priority".into(), Variant(Box::new(5i32)));
+    assert_eq!(
+      exported_profile("Home".to_string(), &settings),
+      ExportedProfile {
+        ssid: "Home".to_string(),
+        security: Some("wpa-psk".to_string()),
+        password: Some("hunter22".to_string()),
+        priority: 5,
+        autoconnect: false,
+      }
+    );
+
+    let settings = settings_owned(new_connection_settings(b"Cafe", "", None, None, None));
+    let profile = exported_profile("Cafe".to_string(), &settings);
+    assert_eq!((profile.security, profile.password), (None, None));
+    assert!(profile.autoconnect);
+  }
+
+  #[test]
+  fn imported_profiles_match_what_was_exported() {
+    let profiles = [
+      ("Home", Some("wpa-psk"), Some("hunter22")),
+      ("Flat", Some("sae"), Some("hunter22")),
+      ("Old", Some("none"), Some("abcde")),
+      ("Cafe", None, None),
+    ];
+    for (ssid, security, password) in profiles {
+      let profile = ExportedProfile {
+        ssid: ssid.to_string(),
+        security: security.map(str::to_string),
+        password: password.map(str::to_string),
+        priority: 3,
+        autoconnect: false,
+      };
+      let settings = settings_owned(imported_connection_settings(&profile).unwrap());
+      assert_eq!(exported_profile(ssid.to_string(), &settings), profile);
+    }
+
+    let enterprise = ExportedProfile {
+      ssid: "Office".to_string(),
+      security: Some("wpa-eap".to_string()),
+      password: Some("secret".to_string()),
+      priority: 0,
+      autoconnect: true,
+    };
+    assert!(imported_connection_settings(&enterprise).is_err());
+    let without_password = ExportedProfile {
+      security: Some("wpa-psk".to_string()),
+      password: None,
+      ..enterprise
+    };
+    assert!(imported_connection_settings(&without_password).is_err());
+  }
+
+  #[test]
+  fn saved_profiles_are_skipped_unless_overwritten() {
+    let profile = ExportedProfile {
+      ssid: "Home".to_string(),
+      security: Some("wpa-psk".to_string()),
+      password: Some("hunter22".to_string()),
+      priority: 0,
+      autoconnect: true,
+    };
+    assert!(import_settings(&profile, true, false).unwrap().is_none());
+    assert!(import_settings(&profile, true, true).unwrap().is_some());
+    assert!(import_settings(&profile, false, false).unwrap().is_some());
+
+    // Profiles that can't be imported only fail when they'd be saved
+    let enterprise = ExportedProfile {
+      security: Some("wpa-eap".to_string()),
+      ..profile
+    };
+    assert!(import_settings(&enterprise, true, false).unwrap().is_none());
+    assert!(import_settings(&enterprise, true, true).is_err());
+  }
+
+  #[test]
+  fn new_password_replaces_the_saved_secret_only() {
+    let mut settings = settings_owned(new_connection_settings(b"Home", "hunter22", None, Some(false), None));
+    set_password(&mut settings, "hunter23").unwrap();
+    assert_eq!(password_from_settings(&settings).as_deref(), Some("hunter23"));
+    assert_eq!(settings["connection"]["autoconnect"].0.as_u64(), Some(0));
+
+    let mut settings = settings_owned(new_connection_settings(b"Old", "abcde", Some((0, 0)), None, None));
+    set_password(&mut settings, "fghij").unwrap();
+    assert_eq!(
+      settings["802-11-wireless-security"]["wep-key0"].0.as_str(),
+      Some("fghij")
+    );
+
+    let mut settings = settings_owned(new_connection_settings(b"Cafe", "", None, None, None));
+    assert!(set_password(&mut settings, "secret").is_err());
+  }
+
+  #[test]
+  fn new_profile_clones_mac_address_only_when_asked() {
+    let settings = new_connection_settings(b"Hotel", "", None, None, None);
+    assert!(!settings["802-11-wireless"].contains_key("cloned-mac-address"));
+
+    let settings = new_connection_settings(b"Hotel", "", None, None, Some(MacAddressPolicy::Stable));
+    assert_eq!(
+      settings["802-11-wireless"]["cloned-mac-address"].0.as_str(),
+      Some("stable")
+    );
+
+    let credentials = EnterpriseCredentials {
+      method: EapMethod::default(),
+      identity: "jdoe@example.edu".to_string(),
+      password: "hunter22".to_string(),
+    };
+    let settings = enterprise_connection_settings(b"eduroam", &credentials, None, Some(MacAddressPolicy::Stable));
+    assert_eq!(
+      settings["802-11-wireless"]["cloned-mac-address"].0.as_str(),
+      Some("stable")
+    );
+  }
+
+  #[test]
+  fn psk_length_limits() {
+    assert_eq!(validate_psk("1234567"), Err("too short (min 8)"));
+    assert_eq!(validate_psk("12345678"), Ok(()));
+    assert_eq!(validate_psk(&"a".repeat(63)), Ok(()));
+    assert_eq!(validate_psk(&"0f".repeat(32)), Ok(()));
+    assert_eq!(validate_psk(&"z".repeat(64)), Err("too long (max 63)"));
+    // Characters, not bytes
+    assert_eq!(validate_psk("pässwort"), Ok(()));
+
+    assert!(uses_wpa_psk("WPA2") && uses_wpa_psk("WPA/WPA2") && uses_wpa_psk("WPA2/WPA3"));
+    assert!(!uses_wpa_psk("WPA3") && !uses_wpa_psk("WPA2-Ent") && !uses_wpa_psk("WEP") && !uses_wpa_psk("Open"));
+  }
+
+  #[test]
+  fn oui_registry_lines_and_prefixes() {
+    assert_eq!(
+      parse_oui_line("00-00-0C   (hex)\t\tCisco Systems, Inc"),
+      Some(("00000C".to_string(), "Cisco Systems, Inc".to_string()))
+    );
+    // The base 16 lines that follow each (hex) line, and the headers
+    assert_eq!(parse_oui_line("00000C     (base 16)\t\tCisco Systems, Inc"), None);
+    assert_eq!(
+      parse_oui_line("OUI/MA-L                                                    Organization"),
+      None
+    );
+
+    assert_eq!(oui("a4:2b:b0:11:22:33").as_deref(), Some("A42BB0"));
+    assert!(is_locally_administered("02:11:22:33:44:55") && is_locally_administered("DA:11:22:33:44:55"));
+    assert_eq!(oui("DA:11:22:33:44:55"), None);
+    assert_eq!(oui("A4:2B"), None);
+  }
+
+  #[test]
+  fn channels_of_band_edges() {
+    assert_eq!(freq_to_channel(2412), Some(1));
+    assert_eq!(freq_to_channel(2472), Some(13));
+    assert_eq!(freq_to_channel(2484), Some(14));
+    assert_eq!(freq_to_channel(5180), Some(36));
+    assert_eq!(freq_to_channel(5825), Some(165));
+    assert_eq!(freq_to_channel(5935), Some(2));
+    assert_eq!(freq_to_channel(5955), Some(1));
+    assert_eq!(freq_to_channel(7115), Some(233));
+    for off_grid in [0, 2400, 2413, 2477, 5182, 5900, 7120] {
+      assert_eq!(freq_to_channel(off_grid), None, "{} MHz", off_grid);
+    }
+  }
+
+  #[test]
+  fn device_capabilities_list_missing_bands() {
+    let capabilities = DeviceCapabilities {
+      device: 0,
+      wireless: NM_WIFI_DEVICE_CAP_FREQ_VALID | NM_WIFI_DEVICE_CAP_FREQ_2GHZ | NM_WIFI_DEVICE_CAP_RSN,
+    };
+    let features = capabilities.features();
+    assert!(features.contains(&("2.4 GHz", true)));
+    assert!(features.contains(&("5 GHz", false)));
+    assert!(features.contains(&("WPA2/RSN", true)));
+    assert!(!capabilities.access_point());
+
+    // Without the valid flag, the band bits mean nothing
+    let capabilities = DeviceCapabilities {
+      device: 0,
+      wireless: NM_WIFI_DEVICE_CAP_RSN,
+    };
+    assert_eq!(capabilities.band(Band::Ghz5), None);
+    assert!(
+      !capabilities
+        .features()
+        .iter()
+        .any(|(feature, _)| feature.ends_with("GHz"))
+    );
+  }
+
+  #[test]
+  fn keyfile_matches_networkmanager_format() {
+    let mut settings = settings_owned(new_connection_settings(b"Home", "hunter22", None, None, None));
+    let connection = settings.get_mut("connection").unwrap();
+    connection.insert("autoconnect".into(), Variant(Box::new(false)));
+    connection.insert("autoconnect-priority".into(), Variant(Box::new(5i32)));
+    let mut address: PropMap = HashMap::new();
+    address.insert("address".into(), Variant(Box::new("192.168.1.5".to_string())));
+    address.insert("prefix".into(), Variant(Box::new(24u32)));
+    let mut ipv4: PropMap = HashMap::new();
+    ipv4.insert("method".into(), Variant(Box::new("manual".to_string())));
+    ipv4.insert("address-data".into(), Variant(Box::new(vec![address])));
+    ipv4.insert("dns-data".into(), Variant(Box::new(vec!["1.1.1.1".to_string()])));
+    settings.insert("ipv4".to_string(), ipv4);
+
+    assert_eq!(
+      keyfile(&settings),
+      "[connection]\nautoconnect=false\nautoconnect-priority=5\nid=Home\ntype=802-11-wireless\n\n\
+       [802-11-wireless]\nmode=infrastructure\nssid=Home\n\n\
+       [802-11-wireless-security]\nkey-mgmt=wpa-psk\npsk=hunter22\n\n\
+       [ipv4]\naddress1=192.168.1.5/24\ndns=1.1.1.1;\nmethod=manual\n\n"
+    );
+  }
+
+  #[test]
+  fn conflicting_address_comes_from_the_saved_settings() {
+    let mut settings = settings_owned(new_connection_settings(b"Home", "", None, None, None));
+    assert_eq!(first_ipv4_address(&settings), None);
+
+    let mut address: PropMap = HashMap::new();
+    address.insert("address".into(), Variant(Box::new("192.168.1.5".to_string())));
+    address.insert("prefix".into(), Variant(Box::new(24u32)));
+    let mut ipv4: PropMap = HashMap::new();
+    ipv4.insert("address-data".into(), Variant(Box::new(vec![address])));
+    settings.insert("ipv4".to_string(), ipv4);
+    assert_eq!(first_ipv4_address(&settings).as_deref(), Some("192.168.1.5"));
+  }
+
+  fn settings_owned(settings: HashMap<&'static str, PropMap>) -> HashMap<String, PropMap> {
+    settings.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+  }
+
+  #[test]
+  fn decode_security_labels() {
+    let psk = NM_802_11_AP_SEC_KEY_MGMT_PSK;
+    let cases = [
+      ("Open", 0, 0, 0, "Open", true),
+      ("WEP", NM_802_11_AP_FLAGS_PRIVACY, 0, 0, "WEP", true),
+      ("WPA", 1, PAIR_TKIP | GROUP_TKIP | psk, 0, "WPA", false),
+      ("WPA2-PSK", 1, 0, PAIR_CCMP | GROUP_CCMP | psk, "WPA2", false),
+      (
+        "WPA2-Ent",
+        1,
+        0,
+        PAIR_CCMP | GROUP_CCMP | NM_802_11_AP_SEC_KEY_MGMT_802_1X,
+        "WPA2-Ent",
+        false,
+      ),
+      (
+        "WPA3-SAE",
+        1,
+        0,
+        PAIR_CCMP | GROUP_CCMP | NM_802_11_AP_SEC_KEY_MGMT_SAE,
+        "WPA3",
+        false,
+      ),
+      (
+        "WPA3-Ent",
+        1,
+        0,
+        PAIR_CCMP | GROUP_CCMP | NM_802_11_AP_SEC_KEY_MGMT_EAP_SUITE_B_192,
+        "WPA3-Ent",
+        false,
+      ),
+      (
+        "WPA/WPA2 mixed",
+        1,
+        PAIR_TKIP | GROUP_TKIP | psk,
+        PAIR_CCMP | PAIR_TKIP | GROUP_TKIP | psk,
+        "WPA/WPA2",
+        false,
+      ),
+      (
+        "WPA2/WPA3 transition",
+        1,
+        0,
+        PAIR_CCMP | GROUP_CCMP | psk | NM_802_11_AP_SEC_KEY_MGMT_SAE,
+        "WPA2/WPA3",
+        false,
+      ),
+      (
+        "OWE",
+        0,
+        0,
+        PAIR_CCMP | GROUP_CCMP | NM_802_11_AP_SEC_KEY_MGMT_OWE,
+        "OWE",
+        false,
+      ),
+      ("Unknown RSN", 1, 0, PAIR_CCMP | GROUP_CCMP, "RSN", false),
+    ];
+
+    for (name, flags, wpa_flags, rsn_flags, label, weak) in cases {
+      assert_eq!(
+        decode_security(flags, wpa_flags, rsn_flags),
+        (label.to_string(), weak),
+        "{name}"
+      );
+    }
+  }
+
+  #[test]
+  fn connection_failures_explain_their_reason() {
+    assert_eq!(
+      ConnectionError::Failed(17).to_string(),
+      "Connection failed: the DHCP client failed or timed out (code 17)"
+    );
+    assert_eq!(
+      ConnectionError::Failed(53).to_string(),
+      "Connection failed: the network could not be found (code 53)"
+    );
+    assert_eq!(
+      ConnectionError::Failed(999).to_string(),
+      "Connection failed: an unknown reason (code 999)"
+    );
+  }
+
+  #[test]
+  fn only_failures_unrelated_to_the_password_are_retried() {
+    let retried = |reason| ConnectionError::from_state_reason(reason).is_transient();
+    assert!(!retried(NM_DEVICE_STATE_REASON_NO_SECRETS));
+    assert!(!retried(NM_DEVICE_STATE_REASON_SUPPLICANT_DISCONNECT));
+    assert!(!retried(NM_DEVICE_STATE_REASON_IP_ADDRESS_DUPLICATE));
+    // The supplicant configuration failing, or DHCP timing out
+    assert!(retried(9));
+    assert!(retried(17));
+    // The device or profile went away, or the user disconnected
+    assert!(!retried(NM_DEVICE_STATE_REASON_REMOVED));
+    assert!(!retried(NM_DEVICE_STATE_REASON_CONNECTION_REMOVED));
+    assert!(!retried(NM_DEVICE_STATE_REASON_USER_REQUESTED));
+  }
+
+  fn device_info(current: &str, permanent: &str) -> WifiDeviceInfo {
+    WifiDeviceInfo {
+      wifi_enabled: true,
+      connectivity_check_enabled: None,
+      connectivity: Connectivity::Unknown,
+      initializing: false,
+      capabilities: None,
+      device_autoconnect: None,
+      hw_address: Some(current.to_string()),
+      permanent_hw_address: Some(permanent.to_string()),
+      interface: None,
+      interfaces: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn mac_randomization_compares_addresses_case_insensitively() {
+    assert_eq!(
+      device_info("a4:c3:f0:85:1d:2e", "A4:C3:F0:85:1D:2E").mac_randomized(),
+      Some(false)
+    );
+    assert_eq!(
+      device_info("5E:A1:0C:3B:77:92", "A4:C3:F0:85:1D:2E").mac_randomized(),
+      Some(true)
+    );
+    // Some drivers don't report a permanent address
+    assert_eq!(device_info("5E:A1:0C:3B:77:92", "").mac_randomized(), None);
+  }
+
+  #[test]
+  fn next_interface_cycles_through_adapters() {
+    let mut info = device_info("", "");
+    info.interfaces = vec!["wlan0".to_string(), "wlan1".to_string()];
+    info.interface = Some("wlan1".to_string());
+    assert_eq!(info.next_interface(), Some("wlan0"));
+    info.interfaces.pop();
+    assert_eq!(info.next_interface(), None);
+  }
+
+  #[test]
+  fn turning_wifi_on_needs_no_device() {
+    assert!(wait_for_device_available(|| None, Duration::ZERO).is_ok());
+    // NM_DEVICE_STATE_DISCONNECTED, ready to connect
+    assert!(wait_for_device_available(|| Some(30), Duration::ZERO).is_ok());
+    assert!(wait_for_device_available(|| Some(NM_DEVICE_STATE_UNAVAILABLE), Duration::ZERO).is_err());
+  }
 }
@@ -0,0 +1,79 @@
+//! QR codes for sharing WiFi credentials with a phone camera, encoded by the `qrcode` crate.
+
+use anyhow::{Context, Result};
+
+/// A square of dark and light modules, without the quiet zone around it
+#[derive(Debug)]
+pub struct QrCode {
+  size: usize,
+  modules: Vec<bool>,
+}
+
+impl QrCode {
+  /// Encodes `data` in the smallest version it fits in, at error correction level M
+  pub fn encode(data: &[u8]) -> Result<Self> {
+    let code =
+      qrcode::QrCode::new(data).with_context(|| format!("Too much data for a QR code ({} bytes)", data.len()))?;
+    Ok(Self {
+      size: code.width(),
+      modules: code
+        .into_colors()
+        .into_iter()
+        .map(|color| color == qrcode::Color::Dark)
+        .collect(),
+    })
+  }
+
+  pub fn size(&self) -> usize {
+    self.size
+  }
+
+  /// Whether the module at column `x`, row `y` is dark. Anything outside the code is light, ie. the quiet zone.
+  pub fn is_dark(&self, x: isize, y: isize) -> bool {
+    let in_range = |v: isize| (0..self.size as isize).contains(&v);
+    in_range(x) && in_range(y) && self.modules[y as usize * self.size + x as usize]
+  }
+}
+
+/// The `WIFI:` text that phone cameras offer to join a network from. `security` is weefee's label for the network;
+/// an empty password makes it an open network.
+pub fn wifi_payload(ssid: &str, security: &str, password: &str) -> Result<String> {
+  let kind = if password.is_empty() {
+    "nopass"
+  } else if security.contains("Ent") {
+    anyhow::bail!("Enterprise networks can't be shared as a QR code");
+  } else if security.contains("WEP") {
+    "WEP"
+  } else {
+    // Also right for WPA3, which phones then negotiate
+    "WPA"
+  };
+  Ok(format!("WIFI:S:{};T:{};P:{};;", escape(ssid), kind, escape(password)))
+}
+
+/// Backslash-escapes the characters that delimit fields in a `WIFI:` payload
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn wifi_payload_escapes_delimiters() {
+    assert_eq!(
+      wifi_payload("Cafe;Bar", "WPA2", r#"a:b\c"d,e"#).unwrap(),
+      r#"WIFI:S:Cafe\;Bar;T:WPA;P:a\:b\\c\"d\,e;;"#
+    );
+    assert_eq!(wifi_payload("Lobby", "Open", "").unwrap(), "WIFI:S:Lobby;T:nopass;P:;;");
+    assert!(wifi_payload("Office", "WPA2-Ent", "secret").is_err());
+  }
+}
@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// State that weefee itself persists between runs, stored in `$XDG_STATE_HOME/weefee/state.toml` (usually
+/// `~/.local/state/weefee/state.toml`). Unlike `Config`, this file is written by weefee and not meant to be hand-edited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistentState {
+  /// SSIDs hidden from the network list
+  pub ignored_ssids: BTreeSet<String>,
+}
+
+impl PersistentState {
+  /// Loads the persisted state. A missing or unreadable state file is not fatal; we just start fresh.
+  pub fn load() -> Self {
+    state_path()
+      .and_then(|path| std::fs::read_to_string(path).ok())
+      .and_then(|contents| toml::from_str(&contents).ok())
+      .unwrap_or_default()
+  }
+
+  /// Whether weefee has run before, judged by the state file it writes. Used to show onboarding once.
+  pub fn exists() -> bool {
+    state_path().is_some_and(|path| path.exists())
+  }
+
+  pub fn save(&self) -> Result<()> {
+    let path = state_path().context("Could not determine state directory")?;
+    if let Some(dir) = path.parent() {
+      std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let contents = toml::to_string(self).context("Failed to serialize state")?;
+    std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+  }
+}
+
+fn state_path() -> Option<PathBuf> {
+  let state_dir = std::env::var_os("XDG_STATE_HOME")
+    .filter(|dir| !dir.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+  Some(state_dir.join("weefee").join("state.toml"))
+}
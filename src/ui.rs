@@ -2,15 +2,25 @@ use ratatui::{
   Frame,
   layout::{Constraint, Direction, Layout, Rect},
   style::{Color, Modifier, Style},
-  widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+  widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Sparkline, Wrap},
 };
-use throbber_widgets_tui::{CANADIAN, Throbber, WhichUse};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use throbber_widgets_tui::{BRAILLE_SIX, CANADIAN, Throbber, WhichUse};
 
-use crate::app::{App, AppState};
-use crate::network::WifiDeviceInfo;
-use crate::network::WifiInfo;
+use crate::app::{
+  App, AppState, DIALOG_FADE_TICKS, EnterpriseField, ListFilter, PendingOp, ZONE_CHOICES, password_problem,
+};
+use crate::config::{Config, Theme};
+use crate::keymap::{Action, KeyMap};
+use crate::network::{Band, Connectivity, EapMethod, MacAddressPolicy, WifiDeviceInfo};
+use crate::network::{WifiInfo, freq_to_channel, is_locally_administered, mac_vendor, quality_score};
+use crate::qr::QrCode;
 
 pub fn draw(f: &mut Frame, app: &mut App) {
+  let visible_networks = app.visible_networks();
+  let matching_count = app.matching_network_count();
+
   // Early return if app is quitting
   let App::Running {
     networks,
@@ -18,6 +28,24 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     device_info,
     state,
     show_detailed_view,
+    selected_ssids,
+    filter,
+    scan_only,
+    show_signal_scanner,
+    refresh_paused,
+    signal_history,
+    connected_since,
+    last_errors,
+    autoconnect_guarded,
+    autoconnect_choice,
+    pending_ops,
+    status,
+    scan_error,
+    scanning,
+    scan_started,
+    config,
+    persistent,
+    ..
   } = app
   else {
     return;
@@ -33,14 +61,58 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     .split(f.area());
 
   let is_dialog_open = !matches!(state, AppState::Normal);
-  draw_header(f, device_info, networks, chunks[0], is_dialog_open);
-  draw_network_list(f, networks, list_state, *show_detailed_view, chunks[1], is_dialog_open);
-  draw_footer(f, chunks[2], is_dialog_open);
+  draw_header(
+    f,
+    device_info,
+    networks,
+    scan_error.as_deref(),
+    scan_indicator(*scanning, *scan_started, config.animate_throbber).as_deref(),
+    *scan_only,
+    *refresh_paused,
+    &config.theme,
+    &config.keybindings,
+    chunks[0],
+    is_dialog_open,
+  );
+  if *show_signal_scanner {
+    draw_signal_scanner(
+      f,
+      signal_history,
+      &config.theme,
+      &config.keybindings,
+      chunks[1],
+      is_dialog_open,
+    );
+  } else {
+    draw_network_list(
+      f,
+      &visible_networks,
+      list_state,
+      selected_ssids,
+      filter.show_ignored.then_some(&persistent.ignored_ssids),
+      autoconnect_guarded,
+      pending_ops,
+      signal_history,
+      connected_since.as_ref(),
+      last_errors,
+      config,
+      *show_detailed_view,
+      chunks[1],
+      is_dialog_open,
+    );
+  }
+  match status {
+    Some((message, _)) => draw_status(f, chunks[2], message, &config.theme),
+    None => draw_footer(f, chunks[2], filter, config, (visible_networks.len(), matching_count)),
+  }
 
   match state {
     AppState::EditingPassword {
       network,
       password_input,
+      show_password,
+      mac_address,
+      update,
     } => {
       // Calculate base position for all blocks
       let base_area = centered_rect_fixed(50, 3, f.area());
@@ -66,9 +138,9 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
       use ratatui::text::{Line, Span};
       let ssid_text = Line::from(vec![
-        Span::raw("Connecting to "),
-        Span::styled(&network.ssid, Style::default().fg(Color::Yellow)),
-        Span::raw("..."),
+        Span::raw(if *update { "New password for " } else { "Connecting to " }),
+        Span::styled(&network.ssid, Style::default().fg(config.theme.accent)),
+        Span::raw(if *update { "" } else { "..." }),
       ]);
       let ssid_widget = Paragraph::new(ssid_text);
       f.render_widget(ssid_widget, ssid_inner);
@@ -76,8 +148,21 @@ pub fn draw(f: &mut Frame, app: &mut App) {
       current_y += 3;
 
       // Password input block
-      let password_block = Block::default()
-        .title("Password")
+      let password_block = if *update {
+        // The profile stays as it is apart from the password, so there's no autoconnect choice here
+        Block::default().title("Password")
+      } else {
+        Block::default().title("Password (leave empty if there is none)")
+      };
+      let password_block = match password_problem(network, password_input.value()) {
+        Some(problem) => password_block
+          .title_bottom(Line::from(format!(" {} ", problem)).style(Style::default().fg(config.theme.error))),
+        None if !*update => password_block
+          .title_bottom(Line::from(" Ctrl+Enter: always auto-connect ").style(Style::default().fg(config.theme.dim))),
+        None => password_block,
+      };
+      let password_block = password_block
+        .title_bottom(show_password_hint(*show_password, &config.theme))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
       let password_area = Rect {
@@ -97,24 +182,78 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         height: 1,
       };
 
-      let scroll = password_input.visual_scroll(inner_area.width as usize);
-      let input_widget = Paragraph::new(password_input.value())
-        .style(Style::default().fg(Color::Yellow))
+      let shown_input = masked(password_input, *show_password);
+      let scroll = shown_input.visual_scroll(inner_area.width as usize);
+      let input_widget = Paragraph::new(shown_input.value())
+        .style(Style::default().fg(config.theme.accent))
         .scroll((0, scroll as u16));
       f.render_widget(input_widget, inner_area);
 
       // Set cursor position
       f.set_cursor_position((
-        inner_area.x + ((password_input.visual_cursor()).max(scroll) - scroll) as u16,
+        inner_area.x + ((shown_input.visual_cursor()).max(scroll) - scroll) as u16,
         inner_area.y,
       ));
+
+      // MAC address the new profile will use. Updating a password keeps the existing profile's.
+      if !*update {
+        current_y += 3;
+
+        let mac_block = Block::default()
+          .title("MAC address")
+          .title_bottom(
+            Line::from(" Ctrl+N: change ")
+              .style(Style::default().fg(config.theme.dim))
+              .right_aligned(),
+          )
+          .borders(Borders::ALL)
+          .border_type(BorderType::Rounded);
+        let mac_area = Rect {
+          x: base_area.x,
+          y: current_y,
+          width: base_area.width,
+          height: 3,
+        };
+        f.render_widget(Clear, mac_area);
+        f.render_widget(
+          Paragraph::new(MacAddressPolicy::describe(*mac_address)).block(mac_block),
+          mac_area,
+        );
+      }
     }
-    AppState::Connecting { throbber_state, .. } => {
+    AppState::EditingEnterprise {
+      network,
+      method,
+      identity_input,
+      password_input,
+      field,
+      show_password,
+      ..
+    } => draw_enterprise_login(
+      f,
+      network,
+      *method,
+      identity_input,
+      password_input,
+      *field,
+      *show_password,
+      &config.theme,
+    ),
+    AppState::Connecting {
+      throbber_state,
+      attempt,
+      ..
+    } => {
       let block = Block::default()
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .style(Style::default().fg(Color::Cyan));
-      let area = centered_rect_fixed(30, 3, f.area());
+        .style(Style::default().fg(config.theme.dialog))
+        .title_bottom(
+          ratatui::text::Line::from(" Esc: cancel ")
+            .style(Style::default().fg(config.theme.dim))
+            .right_aligned(),
+        );
+      let area = centered_rect_fixed(34, 3, f.area());
       f.render_widget(Clear, area); // Clear background
       f.render_widget(block, area);
 
@@ -133,13 +272,24 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         width: inner_area.width,
         height: 1,
       };
-      let throbber = Throbber::default()
-        .label("Connecting...")
-        .style(Style::default().fg(Color::Yellow))
-        .throbber_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        .throbber_set(CANADIAN)
-        .use_type(WhichUse::Spin);
-      f.render_stateful_widget(throbber, throbber_area, throbber_state);
+      // Only mention attempts once there's been a retry
+      let label = if *attempt > 1 {
+        format!("Connecting (attempt {}/{})...", attempt, config.connect_retries + 1)
+      } else {
+        "Connecting...".to_string()
+      };
+      if config.animate_throbber {
+        let throbber = Throbber::default()
+          .label(label)
+          .style(Style::default().fg(config.theme.accent))
+          .throbber_style(Style::default().fg(config.theme.dialog).add_modifier(Modifier::BOLD))
+          .throbber_set(CANADIAN)
+          .use_type(WhichUse::Spin);
+        f.render_stateful_widget(throbber, throbber_area, throbber_state);
+      } else {
+        let label = Paragraph::new(label.replace("...", "…")).style(Style::default().fg(config.theme.accent));
+        f.render_widget(label, throbber_area);
+      }
     }
     AppState::Normal => {}
     AppState::ConfirmDisconnect { network } => {
@@ -147,7 +297,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         .title("Disconnect")
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .style(Style::default().fg(Color::Yellow));
+        .style(Style::default().fg(config.theme.warning));
       let area = centered_rect(60, 25, f.area());
       f.render_widget(Clear, area);
       f.render_widget(block, area);
@@ -174,34 +324,40 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         Span::raw("Disconnect from "),
         Span::styled(
           &network.ssid,
-          Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+          Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
         ),
         Span::raw("?"),
       ])];
 
       let message = Paragraph::new(message_lines)
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(config.theme.text))
         .wrap(Wrap { trim: true });
       f.render_widget(message, layout[0]);
 
       // Render prompt at bottom, centered
       let prompt_line = Line::from(vec![
-        Span::styled("Y", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
         Span::raw("es / "),
-        Span::styled("N", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
         Span::raw("o"),
       ]);
       let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(config.theme.text))
         .alignment(ratatui::layout::Alignment::Center);
       f.render_widget(prompt_widget, layout[1]);
     }
-    AppState::ConfirmForget { network } => {
+    AppState::ConfirmNewNetwork { network } => {
       let block = Block::default()
-        .title("Forget Network")
+        .title("New network")
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .style(Style::default().fg(Color::Red));
+        .style(Style::default().fg(config.theme.warning));
       let area = centered_rect(60, 25, f.area());
       f.render_widget(Clear, area);
       f.render_widget(block, area);
@@ -215,7 +371,6 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
       use ratatui::text::{Line, Span};
 
-      // Split inner area: message area (flexible) and prompt at bottom (1 line)
       let layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -224,104 +379,110 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         ])
         .split(inner_area);
 
-      let mut message_lines = vec![
+      let message_lines = vec![
         Line::from(vec![
-          Span::raw("Forget network "),
+          Span::raw("Connect to new network "),
           Span::styled(
             &network.ssid,
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
           ),
           Span::raw("?"),
         ]),
         Line::from(""),
+        Line::from("NetworkManager will save a profile for it."),
       ];
 
-      if network.active {
-        message_lines.push(Line::from(
-          "This will disconnect and delete the saved password and settings.",
-        ));
-      } else {
-        message_lines.push(Line::from("This will delete the saved password and settings."));
-      }
-
       let message = Paragraph::new(message_lines)
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(config.theme.text))
         .wrap(Wrap { trim: true });
       f.render_widget(message, layout[0]);
 
-      // Render prompt at bottom, centered
       let prompt_line = Line::from(vec![
-        Span::styled("Y", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
         Span::raw("es / "),
-        Span::styled("N", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
         Span::raw("o"),
       ]);
       let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(config.theme.text))
         .alignment(ratatui::layout::Alignment::Center);
       f.render_widget(prompt_widget, layout[1]);
     }
-    AppState::ConfirmWeakSecurity { network } => {
+    AppState::ConfirmExportSecrets { network } => {
+      let block = Block::default()
+        .title("Export profile")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.warning));
+      let area = centered_rect(60, 25, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
       use ratatui::text::{Line, Span};
-      let mut message_lines = vec![];
 
-      // Distinguish between no security and weak security
-      if network.security == "Open" {
-        message_lines.push(Line::from(vec![
-          Span::raw("Network "),
-          Span::styled(
-            network.ssid.as_str(),
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-          ),
-          Span::raw(" has "),
-          Span::styled(
-            "no security",
-            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-          ),
-          Span::raw(". Anyone can intercept your data."),
-        ]));
-      } else {
-        // Weak security (WEP or similar)
-        message_lines.push(Line::from(vec![
-          Span::raw("Network "),
-          Span::styled(
-            network.ssid.as_str(),
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-          ),
-          Span::raw(" uses "),
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + prompt
+        ])
+        .split(inner_area);
+
+      let message_lines = vec![
+        Line::from(vec![
+          Span::raw("Include the password of "),
           Span::styled(
-            network.security.as_str(),
-            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            &network.ssid,
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
           ),
-          Span::raw("."),
-        ]));
+          Span::raw(" in the exported file?"),
+        ]),
+        Line::from(""),
+        Line::from("Without it, NetworkManager asks for the password when the profile is first used."),
+      ];
 
-        if network.security.contains("WEP") {
-          message_lines.push(Line::from(
-            "WEP is outdated and can be cracked in minutes. Your data can be easily intercepted by attackers.",
-          ));
-        } else {
-          message_lines.push(Line::from(
-            "This encryption method is outdated and insecure. Your data may be vulnerable to interception.",
-          ));
-        }
-      }
+      let message = Paragraph::new(message_lines)
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: true });
+      f.render_widget(message, layout[0]);
 
-      message_lines.push(Line::from(""));
-      message_lines.push(Line::from(vec![
-        Span::styled("Continue anyway? ", Style::default().fg(Color::White)),
-        Span::styled("Y", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+      let prompt_line = Line::from(vec![
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
         Span::raw("es / "),
-        Span::styled("N", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
-        Span::raw("o"),
-      ]));
-
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("o / Esc to cancel"),
+      ]);
+      let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+        .style(Style::default().fg(config.theme.text))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(prompt_widget, layout[1]);
+    }
+    AppState::ConfirmRadioOff { network } => {
       let block = Block::default()
+        .title("Turn WiFi Off")
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .style(Style::default().fg(Color::Red));
-
-      let area = centered_rect(70, 30, f.area());
+        .style(Style::default().fg(config.theme.warning));
+      let area = centered_rect(60, 25, f.area());
       f.render_widget(Clear, area);
       f.render_widget(block, area);
 
@@ -332,7 +493,8 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         height: area.height.saturating_sub(2),
       };
 
-      // Split inner area: message area (flexible) and prompt at bottom (1 line)
+      use ratatui::text::{Line, Span};
+
       let layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -341,34 +503,47 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         ])
         .split(inner_area);
 
-      // Remove the last two lines from message_lines (blank line + prompt)
-      let prompt_line = message_lines.pop();
-      let _blank_line = message_lines.pop();
+      let message_lines = vec![Line::from(vec![
+        Span::raw("Turning WiFi off disconnects from "),
+        Span::styled(
+          &network.ssid,
+          Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(". Turn it off anyway?"),
+      ])];
 
       let message = Paragraph::new(message_lines)
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(config.theme.text))
         .wrap(Wrap { trim: true });
       f.render_widget(message, layout[0]);
 
-      // Render prompt at bottom, centered
-      if let Some(prompt) = prompt_line {
-        let prompt_widget = Paragraph::new(vec![Line::from(""), prompt])
-          .style(Style::default().fg(Color::White))
-          .alignment(ratatui::layout::Alignment::Center);
-        f.render_widget(prompt_widget, layout[1]);
-      }
+      let prompt_line = Line::from(vec![
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("es / "),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("o"),
+      ]);
+      let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+        .style(Style::default().fg(config.theme.text))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(prompt_widget, layout[1]);
     }
-    AppState::ShowingError { error } => {
+    AppState::ConfirmRevealPassword { network } => {
       let block = Block::default()
-        .title("Error")
+        .title("Copy Password")
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .style(Style::default().fg(Color::Red));
+        .style(Style::default().fg(config.theme.warning));
       let area = centered_rect(60, 25, f.area());
-      f.render_widget(Clear, area); // Clear background
+      f.render_widget(Clear, area);
       f.render_widget(block, area);
 
-      // Calculate inner area with margin for the message
       let inner_area = Rect {
         x: area.x + 1,
         y: area.y + 1,
@@ -376,108 +551,957 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         height: area.height.saturating_sub(2),
       };
 
-      use ratatui::text::Line;
+      use ratatui::text::{Line, Span};
 
-      // Split inner area: message area (flexible) and dismiss text at bottom (1 line)
       let layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
           Constraint::Min(0),    // Message area
-          Constraint::Length(2), // Blank line + dismiss text
+          Constraint::Length(2), // Blank line + prompt
         ])
         .split(inner_area);
 
-      let error_display = Paragraph::new(format!("{:#}", error))
-        .style(Style::default().fg(Color::White))
+      let message_lines = vec![
+        Line::from(vec![
+          Span::raw("Copy the password of "),
+          Span::styled(
+            &network.ssid,
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw(" to the clipboard?"),
+        ]),
+        Line::from(""),
+        Line::from("It stays there, readable by other programs, until something else is copied."),
+      ];
+
+      let message = Paragraph::new(message_lines)
+        .style(Style::default().fg(config.theme.text))
         .wrap(Wrap { trim: true });
-      f.render_widget(error_display, layout[0]);
+      f.render_widget(message, layout[0]);
 
-      // Render dismiss text at bottom, centered
-      let dismiss_text = Paragraph::new(vec![Line::from(""), Line::from("Enter or Esc to dismiss")])
-        .style(Style::default().fg(Color::DarkGray))
+      let prompt_line = Line::from(vec![
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("es / "),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("o"),
+      ]);
+      let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+        .style(Style::default().fg(config.theme.text))
         .alignment(ratatui::layout::Alignment::Center);
-      f.render_widget(dismiss_text, layout[1]);
+      f.render_widget(prompt_widget, layout[1]);
     }
-  }
-}
+    AppState::ConfirmReapply { network } => {
+      let block = Block::default()
+        .title("Apply Settings")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.warning));
+      let area = centered_rect(60, 25, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-  let popup_layout = Layout::default()
-    .direction(Direction::Vertical)
-    .constraints([
-      Constraint::Percentage((100 - percent_y) / 2),
-      Constraint::Percentage(percent_y),
-      Constraint::Percentage((100 - percent_y) / 2),
-    ])
-    .split(r);
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
 
-  Layout::default()
-    .direction(Direction::Horizontal)
-    .constraints([
-      Constraint::Percentage((100 - percent_x) / 2),
-      Constraint::Percentage(percent_x),
-      Constraint::Percentage((100 - percent_x) / 2),
-    ])
-    .split(popup_layout[1])[1]
-}
+      use ratatui::text::{Line, Span};
 
-fn centered_rect_fixed(width: u16, height: u16, r: Rect) -> Rect {
-  let vertical_margin = r.height.saturating_sub(height) / 2;
-  let horizontal_margin = r.width.saturating_sub(width) / 2;
+      // Split inner area: message area (flexible) and prompt at bottom (1 line)
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + prompt
+        ])
+        .split(inner_area);
 
-  Rect {
-    x: r.x + horizontal_margin,
-    y: r.y + vertical_margin,
-    width: width.min(r.width),
-    height: height.min(r.height),
-  }
-}
+      let message_lines = vec![
+        Line::from(vec![
+          Span::raw("Settings for "),
+          Span::styled(
+            &network.ssid,
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw(" were saved."),
+        ]),
+        Line::from(""),
+        Line::from("They take effect the next time you connect. Reconnect now?"),
+      ];
 
-fn draw_header(
-  f: &mut Frame,
-  device_info: &Option<WifiDeviceInfo>,
-  networks: &[WifiInfo],
-  area: Rect,
-  is_dimmed: bool,
-) {
-  // Check if WiFi is disabled
-  let wifi_disabled = device_info.as_ref().map_or(false, |info| !info.wifi_enabled);
-  // Check if we're connected to any network
+      let message = Paragraph::new(message_lines)
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: true });
+      f.render_widget(message, layout[0]);
+
+      // Render prompt at bottom, centered
+      let prompt_line = Line::from(vec![
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("es / "),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("o"),
+      ]);
+      let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+        .style(Style::default().fg(config.theme.text))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(prompt_widget, layout[1]);
+    }
+    AppState::ConfirmNewProfile {
+      network,
+      password,
+      mac_address,
+    } => {
+      let block = Block::default()
+        .title("New Network")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.warning));
+      let area = centered_rect(60, 35, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      use ratatui::text::{Line, Span};
+
+      // Split inner area: message area (flexible) and prompt at bottom (1 line)
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + prompt
+        ])
+        .split(inner_area);
+
+      // These mirror what NetworkManager saves for a profile created without any extra settings
+      let password_status = if password.is_empty() { "none" } else { "saved" };
+      let message_lines = vec![
+        Line::from(vec![
+          Span::raw("Create a saved profile for "),
+          Span::styled(
+            &network.ssid,
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw("?"),
+        ]),
+        Line::from(""),
+        Line::from(format!("  security: {}", network.security)),
+        Line::from(format!("  password: {}", password_status)),
+        Line::from(match autoconnect_choice {
+          Some(false) => "  auto-connect: off (Alt+Enter)",
+          Some(true) => "  auto-connect: on, saved in the profile (Ctrl+Enter)",
+          None => "  auto-connect: on",
+        }),
+        Line::from("  IPv4/IPv6: automatic (DHCP)"),
+        Line::from(format!("  MAC address: {}", MacAddressPolicy::describe(*mac_address))),
+      ];
+
+      let message = Paragraph::new(message_lines)
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: false });
+      f.render_widget(message, layout[0]);
+
+      // Render prompt at bottom, centered
+      let prompt_line = Line::from(vec![
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("es / "),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("o"),
+      ]);
+      let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+        .style(Style::default().fg(config.theme.text))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(prompt_widget, layout[1]);
+    }
+    AppState::ConfirmForget { network, fade } => {
+      let block = Block::default()
+        .title("Forget Network")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(fade_in(config.theme.error, *fade)));
+      let area = centered_rect(60, 25, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      use ratatui::text::{Line, Span};
+
+      // Split inner area: message area (flexible) and prompt at bottom (1 line)
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + prompt
+        ])
+        .split(inner_area);
+
+      let mut message_lines = vec![
+        Line::from(vec![
+          Span::raw("Forget network "),
+          Span::styled(
+            &network.ssid,
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw("?"),
+        ]),
+        Line::from(""),
+      ];
+
+      if network.active {
+        message_lines.push(Line::from(
+          "This will disconnect and delete the saved password and settings.",
+        ));
+      } else {
+        message_lines.push(Line::from("This will delete the saved password and settings."));
+      }
+
+      let message = Paragraph::new(message_lines)
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: true });
+      f.render_widget(message, layout[0]);
+
+      // Render prompt at bottom, centered
+      let prompt_line = Line::from(vec![
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("es / "),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("o"),
+      ]);
+      let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+        .style(Style::default().fg(config.theme.text))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(prompt_widget, layout[1]);
+    }
+    AppState::ConfirmBatchForget { ssids, .. } => draw_forget_networks(f, config, ssids, None),
+    AppState::ConfirmForgetAll { ssids, keep } => {
+      let ssids: Vec<String> = ssids
+        .iter()
+        .map(|ssid| String::from_utf8_lossy(ssid).into_owned())
+        .collect();
+      draw_forget_networks(f, config, &ssids, Some(keep.as_deref()))
+    }
+    AppState::ConfirmWeakSecurity { network } => {
+      use ratatui::text::{Line, Span};
+      let mut message_lines = vec![];
+
+      // Distinguish between no security and weak security
+      if network.security == "Open" {
+        message_lines.push(Line::from(vec![
+          Span::raw("Network "),
+          Span::styled(
+            network.ssid.as_str(),
+            Style::default().fg(config.theme.warning).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw(" has "),
+          Span::styled(
+            "no security",
+            Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw(". Anyone can intercept your data."),
+        ]));
+      } else {
+        // Weak security (WEP or similar)
+        message_lines.push(Line::from(vec![
+          Span::raw("Network "),
+          Span::styled(
+            network.ssid.as_str(),
+            Style::default().fg(config.theme.warning).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw(" uses "),
+          Span::styled(
+            network.security.as_str(),
+            Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw("."),
+        ]));
+
+        if network.security.contains("WEP") {
+          message_lines.push(Line::from(
+            "WEP is outdated and can be cracked in minutes. Your data can be easily intercepted by attackers.",
+          ));
+        } else {
+          message_lines.push(Line::from(
+            "This encryption method is outdated and insecure. Your data may be vulnerable to interception.",
+          ));
+        }
+      }
+
+      message_lines.push(Line::from(""));
+      message_lines.push(Line::from(vec![
+        Span::styled("Continue anyway? ", Style::default().fg(config.theme.text)),
+        Span::styled(
+          "Y",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("es / "),
+        Span::styled(
+          "N",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("o"),
+      ]));
+
+      let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.error));
+
+      let area = centered_rect(70, 30, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      // Split inner area: message area (flexible) and prompt at bottom (1 line)
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + prompt
+        ])
+        .split(inner_area);
+
+      // Remove the last two lines from message_lines (blank line + prompt)
+      let prompt_line = message_lines.pop();
+      let _blank_line = message_lines.pop();
+
+      let message = Paragraph::new(message_lines)
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: true });
+      f.render_widget(message, layout[0]);
+
+      // Render prompt at bottom, centered
+      if let Some(prompt) = prompt_line {
+        let prompt_widget = Paragraph::new(vec![Line::from(""), prompt])
+          .style(Style::default().fg(config.theme.text))
+          .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(prompt_widget, layout[1]);
+      }
+    }
+    AppState::PickingZone { network, selected } => {
+      let block = Block::default()
+        .title(format!("Firewall Zone: {}", network.ssid))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.dialog));
+      let area = centered_rect_fixed(40, ZONE_CHOICES.len() as u16 + 4, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      use ratatui::text::Line;
+
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Choices
+          Constraint::Length(2), // Blank line + hint
+        ])
+        .split(inner_area);
+
+      let choices: Vec<Line> = ZONE_CHOICES
+        .iter()
+        .enumerate()
+        .map(|(i, zone)| {
+          let label = zone.unwrap_or("default");
+          let current = if *zone == network.zone.as_deref() {
+            " (current)"
+          } else {
+            ""
+          };
+          if i == *selected {
+            Line::styled(
+              format!("→ {}{}", label, current),
+              Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+            )
+          } else {
+            Line::styled(
+              format!("  {}{}", label, current),
+              Style::default().fg(config.theme.text),
+            )
+          }
+        })
+        .collect();
+      f.render_widget(Paragraph::new(choices), layout[0]);
+
+      let hint = Paragraph::new(vec![Line::from(""), Line::from("Enter to set, Esc to cancel")])
+        .style(Style::default().fg(config.theme.dim))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(hint, layout[1]);
+    }
+    AppState::CaptivePortal { ssid } => {
+      let block = Block::default()
+        .title("Captive Portal")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.warning));
+      let area = centered_rect(60, 25, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      use ratatui::text::{Line, Span};
+
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + prompt
+        ])
+        .split(inner_area);
+
+      let message_lines = vec![
+        Line::from(vec![
+          Span::raw("Connected to "),
+          Span::styled(
+            ssid.as_str(),
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+          ),
+          Span::raw(", but it needs you to log in before you can reach the internet."),
+        ]),
+        Line::from(""),
+        Line::from("Open the login page in your browser?"),
+      ];
+
+      let message = Paragraph::new(message_lines)
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: true });
+      f.render_widget(message, layout[0]);
+
+      let prompt_line = Line::from(vec![
+        Span::styled(
+          "O",
+          Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("pen login / "),
+        Span::styled(
+          "Esc",
+          Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" to dismiss"),
+      ]);
+      let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+        .style(Style::default().fg(config.theme.text))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(prompt_widget, layout[1]);
+    }
+    AppState::ShowingSettingsComparison { ssid, comparison } => {
+      let block = Block::default()
+        .title(format!("Settings: {}", ssid))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.dialog));
+      let area = centered_rect(80, 50, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      use ratatui::text::{Line, Span};
+
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Table
+          Constraint::Length(2), // Blank line + dismiss text
+        ])
+        .split(inner_area);
+
+      let header_style = Style::default().fg(config.theme.dim).add_modifier(Modifier::BOLD);
+      let mut lines = vec![Line::from(Span::styled(
+        format!("  {:<16} {:<24} {}", "setting", "saved", "applied"),
+        header_style,
+      ))];
+      for row in comparison.iter() {
+        // Differences mean the saved settings haven't taken effect yet
+        let (marker, style) = if row.differs() {
+          (
+            "≠ ",
+            Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+          )
+        } else {
+          ("  ", Style::default().fg(config.theme.text))
+        };
+        let applied = row.applied.as_deref().unwrap_or("(not active)");
+        lines.push(Line::from(Span::styled(
+          format!("{}{:<16} {:<24} {}", marker, row.name, row.saved, applied),
+          style,
+        )));
+      }
+      if comparison.iter().any(|row| row.differs()) {
+        lines.push(Line::from(""));
+        lines.push(Line::from("Reconnect to apply the saved settings."));
+      }
+
+      let table = Paragraph::new(lines).wrap(Wrap { trim: false });
+      f.render_widget(table, layout[0]);
+
+      let dismiss_text = Paragraph::new(vec![Line::from(""), Line::from("Enter or Esc to close")])
+        .style(Style::default().fg(config.theme.dim))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(dismiss_text, layout[1]);
+    }
+    AppState::ShowingHelp { first_run } => draw_help(f, config, *first_run),
+    AppState::ShowingDeviceInfo => draw_device_info(f, device_info, &config.theme),
+    AppState::ShowingInfo { message } => {
+      let block = Block::default()
+        .title("Info")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(config.theme.dialog));
+      let area = centered_rect(60, 25, f.area());
+      f.render_widget(Clear, area);
+      f.render_widget(block, area);
+
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      use ratatui::text::Line;
+
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + dismiss text
+        ])
+        .split(inner_area);
+
+      let message_display = Paragraph::new(message.as_str())
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: true });
+      f.render_widget(message_display, layout[0]);
+
+      let dismiss_text = Paragraph::new(vec![Line::from(""), Line::from("Enter or Esc to dismiss")])
+        .style(Style::default().fg(config.theme.dim))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(dismiss_text, layout[1]);
+    }
+    AppState::ShowingQrCode { ssid, code } => draw_qr_code(f, ssid, code, &config.theme),
+    AppState::ShowingError { error, fade } => {
+      let block = Block::default()
+        .title("Error")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(fade_in(config.theme.error, *fade)));
+      let area = centered_rect(60, 25, f.area());
+      f.render_widget(Clear, area); // Clear background
+      f.render_widget(block, area);
+
+      // Calculate inner area with margin for the message
+      let inner_area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+      };
+
+      use ratatui::text::Line;
+
+      // Split inner area: message area (flexible) and dismiss text at bottom (1 line)
+      let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+          Constraint::Min(0),    // Message area
+          Constraint::Length(2), // Blank line + dismiss text
+        ])
+        .split(inner_area);
+
+      let error_display = Paragraph::new(format!("{:#}", error))
+        .style(Style::default().fg(config.theme.text))
+        .wrap(Wrap { trim: true });
+      f.render_widget(error_display, layout[0]);
+
+      // Render dismiss text at bottom, centered
+      let dismiss_text = Paragraph::new(vec![Line::from(""), Line::from("Enter or Esc to dismiss")])
+        .style(Style::default().fg(config.theme.dim))
+        .alignment(ratatui::layout::Alignment::Center);
+      f.render_widget(dismiss_text, layout[1]);
+    }
+  }
+}
+
+/// The border color of a dialog `fade` ticks after it opened, brightening from dark gray to `target`.
+fn fade_in(target: Color, fade: u8) -> Color {
+  if fade >= DIALOG_FADE_TICKS {
+    return target;
+  }
+  // Approximations of the standard terminal palette, since named colors can't be blended
+  let (r, g, b) = match target {
+    Color::Rgb(r, g, b) => (r, g, b),
+    Color::Red => (205, 49, 49),
+    Color::Yellow => (229, 229, 16),
+    Color::Cyan => (17, 168, 205),
+    _ => return target,
+  };
+  let start = 64u16;
+  let steps = DIALOG_FADE_TICKS as u16 + 1;
+  let step = fade as u16 + 1;
+  let blend = |to: u8| ((start * (steps - step) + to as u16 * step) / steps) as u8;
+  Color::Rgb(blend(r), blend(g), blend(b))
+}
+
+/// Confirmation for forgetting several networks at once. `keep` is given when forgetting all saved networks but the
+/// connected one, if any.
+fn draw_forget_networks(f: &mut Frame, config: &Config, ssids: &[String], keep: Option<Option<&str>>) {
+  use ratatui::text::{Line, Span};
+
+  let block = Block::default()
+    .title(if keep.is_some() {
+      "Forget All Networks"
+    } else {
+      "Forget Networks"
+    })
+    .borders(Borders::ALL)
+    .border_type(BorderType::Rounded)
+    .style(Style::default().fg(config.theme.error));
+  let area = centered_rect(60, 40, f.area());
+  f.render_widget(Clear, area);
+  f.render_widget(block, area);
+
+  let inner_area = Rect {
+    x: area.x + 1,
+    y: area.y + 1,
+    width: area.width.saturating_sub(2),
+    height: area.height.saturating_sub(2),
+  };
+
+  // Split inner area: message area (flexible) and prompt at bottom (1 line)
+  let layout = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints([
+      Constraint::Min(0),    // Message area
+      Constraint::Length(2), // Blank line + prompt
+    ])
+    .split(inner_area);
+
+  let mut message_lines = vec![
+    Line::from(vec![
+      Span::raw("Forget "),
+      Span::styled(
+        format!("{} networks", ssids.len()),
+        Style::default().fg(config.theme.accent).add_modifier(Modifier::BOLD),
+      ),
+      Span::raw("?"),
+    ]),
+    Line::from("This will delete the saved passwords and settings for:"),
+    Line::from(""),
+  ];
+  match keep {
+    Some(Some(ssid)) => message_lines.insert(1, Line::from(format!("Only {}, which is connected, is kept.", ssid))),
+    Some(None) => message_lines.insert(1, Line::from("No network is connected, so none is kept.")),
+    None => {}
+  }
+  message_lines.extend(ssids.iter().map(|ssid| Line::from(format!("  • {}", ssid))));
+
+  let message = Paragraph::new(message_lines)
+    .style(Style::default().fg(config.theme.text))
+    .wrap(Wrap { trim: false });
+  f.render_widget(message, layout[0]);
+
+  // Render prompt at bottom, centered
+  let prompt_line = Line::from(vec![
+    Span::styled(
+      "Y",
+      Style::default().fg(config.theme.success).add_modifier(Modifier::BOLD),
+    ),
+    Span::raw("es / "),
+    Span::styled(
+      "N",
+      Style::default().fg(config.theme.error).add_modifier(Modifier::BOLD),
+    ),
+    Span::raw("o"),
+  ]);
+  let prompt_widget = Paragraph::new(vec![Line::from(""), prompt_line])
+    .style(Style::default().fg(config.theme.text))
+    .alignment(ratatui::layout::Alignment::Center);
+  f.render_widget(prompt_widget, layout[1]);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+  let popup_layout = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints([
+      Constraint::Percentage((100 - percent_y) / 2),
+      Constraint::Percentage(percent_y),
+      Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .split(r);
+
+  Layout::default()
+    .direction(Direction::Horizontal)
+    .constraints([
+      Constraint::Percentage((100 - percent_x) / 2),
+      Constraint::Percentage(percent_x),
+      Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .split(popup_layout[1])[1]
+}
+
+/// Light modules around a QR code. The standard asks for 4, but 2 scans fine and saves room.
+const QR_QUIET_ZONE: isize = 2;
+
+/// Draws a QR code with half blocks, two module rows per line, dark on light regardless of the terminal's colors
+fn draw_qr_code(f: &mut Frame, ssid: &str, code: &QrCode, theme: &Theme) {
+  use ratatui::text::Line;
+
+  let modules = code.size() as isize + 2 * QR_QUIET_ZONE;
+  let lines: Vec<Line> = (0..modules)
+    .step_by(2)
+    .map(|y| {
+      let row: String = (0..modules)
+        .map(|x| {
+          let dark = |y| code.is_dark(x - QR_QUIET_ZONE, y - QR_QUIET_ZONE);
+          match (dark(y), dark(y + 1)) {
+            (true, true) => '█',
+            (true, false) => '▀',
+            (false, true) => '▄',
+            (false, false) => ' ',
+          }
+        })
+        .collect();
+      Line::from(row)
+    })
+    .collect();
+
+  let block = Block::default()
+    .title(format!("Scan to join {}", ssid))
+    .title_bottom(Line::from(" Enter or Esc to dismiss ").right_aligned())
+    .borders(Borders::ALL)
+    .border_type(BorderType::Rounded)
+    .style(Style::default().fg(theme.dialog));
+  let area = centered_rect_fixed(modules as u16 + 2, lines.len() as u16 + 2, f.area());
+  f.render_widget(Clear, area);
+
+  if area.width < modules as u16 + 2 || area.height < lines.len() as u16 + 2 {
+    let message = Paragraph::new("Enlarge the terminal to show the QR code")
+      .style(Style::default().fg(theme.text))
+      .wrap(Wrap { trim: true })
+      .block(block);
+    f.render_widget(message, area);
+    return;
+  }
+
+  let inner_area = block.inner(area);
+  f.render_widget(block, area);
+  let qr = Paragraph::new(lines).style(Style::default().fg(Color::Black).bg(Color::White));
+  f.render_widget(qr, inner_area);
+}
+
+fn centered_rect_fixed(width: u16, height: u16, r: Rect) -> Rect {
+  let vertical_margin = r.height.saturating_sub(height) / 2;
+  let horizontal_margin = r.width.saturating_sub(width) / 2;
+
+  Rect {
+    x: r.x + horizontal_margin,
+    y: r.y + vertical_margin,
+    width: width.min(r.width),
+    height: height.min(r.height),
+  }
+}
+
+/// Scans run every second and usually finish quickly, so they're only pointed out once they take longer than this
+const SCAN_INDICATOR_DELAY: Duration = Duration::from_millis(300);
+
+/// "scanning…" while an empty first scan is retried or a scan takes a while, spinning unless animations are off
+fn scan_indicator(retrying: bool, scan_started: Option<Instant>, animate: bool) -> Option<String> {
+  let elapsed = scan_started.map(|started| started.elapsed());
+  if !retrying && elapsed.is_none_or(|elapsed| elapsed < SCAN_INDICATOR_DELAY) {
+    return None;
+  }
+  match elapsed {
+    Some(elapsed) if animate => {
+      let symbols = BRAILLE_SIX.symbols;
+      let frame = (elapsed.as_millis() / 100) as usize % symbols.len();
+      Some(format!("scanning {}", symbols[frame]))
+    }
+    _ => Some("scanning…".to_string()),
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_header(
+  f: &mut Frame,
+  device_info: &Option<WifiDeviceInfo>,
+  networks: &[WifiInfo],
+  scan_error: Option<&str>,
+  scanning: Option<&str>,
+  scan_only: bool,
+  refresh_paused: bool,
+  theme: &Theme,
+  keys: &KeyMap,
+  area: Rect,
+  is_dimmed: bool,
+) {
+  // Check if WiFi is disabled
+  let wifi_disabled = device_info.as_ref().is_some_and(|info| !info.wifi_enabled);
+  // Check if we're connected to any network
   let is_connected = networks.iter().any(|n| n.active);
+  // Connected, but NetworkManager found the internet out of reach, eg. behind a hotel's login page
+  let connectivity = device_info
+    .as_ref()
+    .map_or(Connectivity::Unknown, |info| info.connectivity);
+  let no_internet = is_connected
+    && matches!(
+      connectivity,
+      Connectivity::None | Connectivity::Portal | Connectivity::Limited
+    );
 
   let style = if wifi_disabled {
-    // WiFi is disabled - use red color
-    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    // WiFi is disabled
+    Style::default().fg(theme.error).add_modifier(Modifier::BOLD)
+  } else if scan_only {
+    Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)
   } else if !is_connected {
-    // WiFi is enabled but not connected - use orange color
+    // WiFi is enabled but not connected
+    Style::default()
+      .fg(theme.header_disconnected)
+      .add_modifier(Modifier::BOLD)
+  } else if no_internet {
     Style::default()
-      .fg(Color::Rgb(255, 165, 0))
+      .fg(theme.header_no_internet)
       .add_modifier(Modifier::BOLD)
   } else if is_dimmed {
-    Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD)
+    Style::default().fg(theme.dim).add_modifier(Modifier::BOLD)
   } else {
-    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+    Style::default().fg(theme.header_connected).add_modifier(Modifier::BOLD)
   };
   let block_style = if wifi_disabled {
-    // WiFi is disabled - use red border
-    Style::default().fg(Color::Red)
+    // WiFi is disabled
+    Style::default().fg(theme.error)
+  } else if scan_only {
+    Style::default().fg(theme.warning)
   } else if !is_connected {
-    // WiFi is enabled but not connected - use orange border
-    Style::default().fg(Color::Rgb(255, 165, 0))
+    // WiFi is enabled but not connected
+    Style::default().fg(theme.header_disconnected)
+  } else if no_internet {
+    Style::default().fg(theme.header_no_internet)
   } else if is_dimmed {
-    Style::default().fg(Color::DarkGray)
+    Style::default().fg(theme.dim)
   } else {
     Style::default()
   };
 
-  let header_text = if let Some(info) = device_info {
-    let enabled_status = if info.wifi_enabled { "enabled" } else { "disabled" };
+  let mut header_text = if let Some(info) = device_info {
+    let enabled_status = if info.wifi_enabled && info.initializing {
+      "initializing…".to_string()
+    } else if info.wifi_enabled {
+      "enabled".to_string()
+    } else {
+      format!("disabled{}", key_hint(keys, Action::ToggleWifi, "to enable"))
+    };
     let connected = networks.iter().any(|n| n.active);
-    let connection_status = if connected { "connected" } else { "not connected" };
-    format!("WeeFee | WiFi {}, {}", enabled_status, connection_status)
+    let connection_status = match (connected, connectivity) {
+      (false, _) => "not connected",
+      (true, Connectivity::Portal) => "connected, captive portal detected",
+      (true, Connectivity::None | Connectivity::Limited) => "connected (no internet)",
+      (true, Connectivity::Full | Connectivity::Unknown) => "connected",
+    };
+    let position = info
+      .interfaces
+      .iter()
+      .position(|name| Some(name) == info.interface.as_ref());
+    let device = match (&info.interface, position) {
+      (Some(interface), Some(ix)) if info.interfaces.len() > 1 => {
+        let switch = keys
+          .first_key(Action::SwitchDevice)
+          .map_or(String::new(), |key| format!(", {} to switch", key));
+        format!(" | {} ({} of {}{})", interface, ix + 1, info.interfaces.len(), switch)
+      }
+      (Some(interface), _) => format!(" | {}", interface),
+      (None, _) => String::new(),
+    };
+    format!("WeeFee | WiFi {}, {}{}", enabled_status, connection_status, device)
   } else {
     "WeeFee | Loading...".to_string()
   };
+  if let Some(Some(enabled)) = device_info.as_ref().map(|info| info.connectivity_check_enabled) {
+    header_text.push_str(if enabled {
+      " | connectivity checks on"
+    } else {
+      " | connectivity checks off"
+    });
+  }
+  if let Some(scanning) = scanning {
+    header_text.push_str(&format!(" | {}", scanning));
+  }
+  if let Some(error) = scan_error {
+    header_text.push_str(&format!(" | scan failed: {}", error));
+  }
+  if refresh_paused {
+    header_text.push_str(&format!(
+      " | paused{}",
+      key_hint(keys, Action::PauseRefresh, "to resume")
+    ));
+  }
+  if scan_only {
+    header_text.push_str(&format!(
+      " | SCAN ONLY: connecting disabled{}",
+      key_hint(keys, Action::ToggleScanOnly, "to leave")
+    ));
+  }
 
   let text = Paragraph::new(header_text).style(style).block(
     Block::default()
@@ -488,10 +1512,19 @@ fn draw_header(
   f.render_widget(text, area);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_network_list(
   f: &mut Frame,
   networks: &[WifiInfo],
   list_state: &mut ListState,
+  selected_ssids: &HashSet<String>,
+  revealed_ignored_ssids: Option<&BTreeSet<String>>,
+  autoconnect_guarded: &BTreeSet<String>,
+  pending_ops: &HashMap<String, PendingOp>,
+  signal_history: &HashMap<String, VecDeque<u8>>,
+  connected_since: Option<&(String, Instant)>,
+  last_errors: &HashMap<String, String>,
+  config: &Config,
   show_detailed_view: bool,
   area: Rect,
   is_dimmed: bool,
@@ -505,39 +1538,59 @@ fn draw_network_list(
       let focused = Some(i) == list_state.selected();
 
       let main_style = if is_dimmed {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(config.theme.dim)
       } else if focused {
-        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        Style::default().fg(config.theme.selection).add_modifier(Modifier::BOLD)
       } else {
         Style::default()
       };
 
-      let prefix = if focused { "→ " } else { "  " };
+      let focus_marker = if focused { "→ " } else { "  " };
+      // Only reserve a column for selection checkmarks while a multi-selection exists
+      let selection_marker = if selected_ssids.contains(&net.ssid) {
+        "✓ "
+      } else if !selected_ssids.is_empty() {
+        "  "
+      } else {
+        ""
+      };
+      let prefix = format!("{}{}", focus_marker, selection_marker);
       // let active_marker = if net.active { "🛜 " } else { "   " };
       // let active_marker = if net.active { "● " } else { "  " };
       // let active_marker = if net.active { "🌐 " } else { "   " };
       let active_marker = if net.active { "🔗 " } else { "   " };
+      let home_marker = if config.is_home(&net.ssid) { "🏠 " } else { "" };
+      let is_ignored = revealed_ignored_ssids.is_some_and(|ignored| ignored.contains(&net.ssid));
+      let ignored_marker = if is_ignored { " (ignored)" } else { "" };
+      let pending_marker = pending_ops
+        .get(&net.ssid)
+        .map(|op| format!(" ({})", op.label()))
+        .unwrap_or_default();
+
+      // The home network keeps its own color unless it's focused or dimmed, so it stands out in crowded lists
+      let ssid_style = if config.is_home(&net.ssid) && !is_dimmed && !focused {
+        Style::default().fg(config.theme.home).add_modifier(Modifier::BOLD)
+      } else {
+        main_style
+      };
 
       // Signal strength indicator (always shown)
-      let signal_indicator = match net.strength {
-        0..=25 => "▁    ",
-        26..=50 => "▁▃   ",
-        51..=75 => "▁▃▅  ",
-        _ => "▁▃▅▇ ",
-      };
+      let signal_indicator = signal_indicator(net.strength);
+      let quality = quality_column(net);
 
-      // Signal style: yellow when focused, gray otherwise
+      // Signal style: the selection color when focused, gray otherwise
       let signal_style = if is_dimmed {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(config.theme.dim)
       } else if focused {
-        Style::default().fg(Color::Yellow)
+        Style::default().fg(config.theme.selection)
       } else {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(config.theme.dim)
       };
-      let detail_style = if is_dimmed {
-        Style::default().fg(Color::DarkGray)
+      let detail_style = Style::default().fg(config.theme.dim);
+      let pending_style = if is_dimmed {
+        Style::default().fg(config.theme.dim)
       } else {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(config.theme.accent).add_modifier(Modifier::ITALIC)
       };
 
       if show_detailed_view {
@@ -547,38 +1600,25 @@ fn draw_network_list(
           Line::from(vec![
             Span::styled(format!("{}{}", prefix, active_marker), main_style),
             Span::styled(signal_indicator, signal_style),
-            Span::styled(net.ssid.clone(), main_style),
+            Span::styled(quality, signal_style),
+            Span::styled(home_marker, ssid_style),
+            Span::styled(net.ssid.clone(), ssid_style),
+            Span::styled(ignored_marker, signal_style),
+            Span::styled(pending_marker, pending_style),
+            Span::styled(
+              signal_history
+                .get(&net.ssid)
+                .map(|samples| format!("  {}", signal_sparkline(samples)))
+                .unwrap_or_default(),
+              signal_style,
+            ),
           ]),
         ];
 
-        // Build details for second line
-        let mut detail_parts = vec![];
-
-        // Signal strength percentage
-        detail_parts.push(format!("signal: {}%", net.strength));
-
-        // Frequency and band information
-        if let Some(freq) = net.frequency {
-          let band = if freq >= 2412 && freq <= 2484 {
-            "2.4 GHz"
-          } else if freq >= 5170 && freq <= 5835 {
-            "5 GHz"
-          } else if freq >= 5945 && freq <= 7125 {
-            "6 GHz"
-          } else {
-            "unknown band"
-          };
-          detail_parts.push(format!("frequency: {} MHz ({})", freq, band));
-        }
-
-        // Security with warning if weak
-        let warning = if net.weak_security { " (⚠ insecure)" } else { "" };
-        detail_parts.push(format!("security: {}{}", net.security, warning));
-
-        // Known status
-        if net.known {
-          detail_parts.push("known network (F to forget)".to_string());
-        }
+        let connected_for = connected_since
+          .filter(|(ssid, _)| net.active && *ssid == net.ssid)
+          .map(|(_, since)| since.elapsed());
+        let detail_parts = detail_parts(net, connected_for, config, true);
 
         // Second line: basic details (always gray, no highlight)
         let detail_indent = Span::styled("          ", detail_style);
@@ -592,27 +1632,15 @@ fn draw_network_list(
 
         // Third line: advanced details (only for known networks)
         if net.known {
-          let mut advanced_parts = vec![];
-
-          if let Some(p) = net.priority {
-            advanced_parts.push(format!("priority: {}", p));
-          }
-
-          match net.autoconnect {
-            Some(true) => advanced_parts.push("auto-connect: on (A to toggle)".to_string()),
-            Some(false) => advanced_parts.push("auto-connect: off (A to toggle)".to_string()),
-            None => advanced_parts.push("auto-connect: default (A to toggle)".to_string()),
-          }
-
-          match net.autoconnect_retries {
-            Some(r) => advanced_parts.push(format!("auto-connect retries: {}", r)),
-            None => advanced_parts.push("auto-connect retries: default".to_string()),
-          }
+          let guarded_below = config
+            .min_autoconnect_signal
+            .filter(|_| autoconnect_guarded.contains(&net.ssid));
+          let advanced_parts = advanced_parts(net, guarded_below, config);
 
           if !advanced_parts.is_empty() {
             lines.push(
               Line::from(vec![
-                detail_indent,
+                detail_indent.clone(),
                 Span::styled(advanced_parts.join(" | "), detail_style),
               ])
               .style(detail_style),
@@ -620,13 +1648,28 @@ fn draw_network_list(
           }
         }
 
+        // Why the last attempt to connect failed, until one succeeds
+        if let Some(error) = last_errors.get(&net.ssid) {
+          lines.push(
+            Line::from(vec![
+              detail_indent,
+              Span::styled(format!("last error: {}", error), detail_style),
+            ])
+            .style(detail_style),
+          );
+        }
+
         ListItem::new(lines)
       } else {
         // Single line format: just show the network name
         let content = Line::from(vec![
           Span::styled(format!("{}{}", prefix, active_marker), main_style),
           Span::styled(signal_indicator, signal_style),
-          Span::styled(net.ssid.clone(), main_style),
+          Span::styled(quality, signal_style),
+          Span::styled(home_marker, ssid_style),
+          Span::styled(net.ssid.clone(), ssid_style),
+          Span::styled(ignored_marker, signal_style),
+          Span::styled(pending_marker, pending_style),
         ]);
         ListItem::new(content)
       }
@@ -634,7 +1677,7 @@ fn draw_network_list(
     .collect();
 
   let block_style = if is_dimmed {
-    Style::default().fg(Color::DarkGray)
+    Style::default().fg(config.theme.dim)
   } else {
     Style::default()
   };
@@ -642,24 +1685,698 @@ fn draw_network_list(
     Block::default()
       .borders(Borders::ALL)
       .border_type(BorderType::Rounded)
-      .title("Networks")
+      .title(if revealed_ignored_ssids.is_some() {
+        match config.keybindings.first_key(Action::ShowIgnored) {
+          Some(key) => format!("Networks (showing ignored, {} to hide)", key),
+          None => "Networks (showing ignored)".to_string(),
+        }
+      } else {
+        "Networks".to_string()
+      })
       .style(block_style),
   );
 
   f.render_stateful_widget(list, area, list_state);
 }
 
-fn draw_footer(f: &mut Frame, area: Rect, is_dimmed: bool) {
-  use ratatui::text::Span;
+/// Most networks shown at once in the signal scanner; any more and the sparklines get too short to read
+const MAX_SCANNER_SERIES: usize = 8;
+
+/// Plots the recent signal strength of the strongest networks, one sparkline each, for finding the best spot in a room.
+fn draw_signal_scanner(
+  f: &mut Frame,
+  signal_history: &HashMap<String, VecDeque<u8>>,
+  theme: &Theme,
+  keys: &KeyMap,
+  area: Rect,
+  is_dimmed: bool,
+) {
+  let border_style = if is_dimmed {
+    Style::default().fg(theme.dim)
+  } else {
+    Style::default()
+  };
+  let block = Block::default()
+    .title(format!(
+      "Signal scanner{}",
+      key_hint(keys, Action::SignalScanner, "to close")
+    ))
+    .borders(Borders::ALL)
+    .border_type(BorderType::Rounded)
+    .style(border_style);
+  let inner_area = block.inner(area);
+  f.render_widget(block, area);
+
+  // Strongest networks right now come first
+  let mut series: Vec<(&String, &VecDeque<u8>)> = signal_history.iter().collect();
+  series.sort_by(|(a_ssid, a), (b_ssid, b)| {
+    let latest = |samples: &VecDeque<u8>| samples.back().copied().unwrap_or(0);
+    latest(b).cmp(&latest(a)).then(a_ssid.cmp(b_ssid))
+  });
+  series.truncate(MAX_SCANNER_SERIES);
+
+  let rows = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints(vec![Constraint::Length(2); series.len()])
+    .split(inner_area);
+
+  for ((ssid, samples), row) in series.into_iter().zip(rows.iter()) {
+    let columns = Layout::default()
+      .direction(Direction::Horizontal)
+      .constraints([Constraint::Length(24), Constraint::Min(0)])
+      .split(*row);
+
+    let latest = samples.back().copied().unwrap_or(0);
+    let color = if is_dimmed {
+      theme.dim
+    } else if latest > 66 {
+      theme.success
+    } else if latest > 33 {
+      theme.warning
+    } else {
+      theme.error
+    };
+
+    let label = Paragraph::new(vec![ssid.as_str().into(), format!("{}%", latest).into()])
+      .style(Style::default().fg(if is_dimmed { theme.dim } else { theme.text }));
+    f.render_widget(label, columns[0]);
+
+    // Only the most recent samples that fit in the available width
+    let width = columns[1].width as usize;
+    let data: Vec<u64> = samples
+      .iter()
+      .skip(samples.len().saturating_sub(width))
+      .map(|&s| s as u64)
+      .collect();
+    let sparkline = Sparkline::default()
+      .data(&data)
+      .max(100)
+      .style(Style::default().fg(color));
+    f.render_widget(sparkline, columns[1]);
+  }
+}
+
+/// How many recent scans the detail view's signal sparkline covers
+const DETAIL_SPARKLINE_SAMPLES: usize = 30;
+
+/// The last `DETAIL_SPARKLINE_SAMPLES` signal samples as a one-line sparkline of block characters, for places where a
+/// `Sparkline` widget doesn't fit, like list items.
+fn signal_sparkline(samples: &VecDeque<u8>) -> String {
+  const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+  samples
+    .iter()
+    .skip(samples.len().saturating_sub(DETAIL_SPARKLINE_SAMPLES))
+    .map(|&s| LEVELS[(s.min(100) as usize * (LEVELS.len() - 1) + 50) / 100])
+    .collect()
+}
+
+/// Signal bars for a strength in percent, padded to a fixed width.
+fn signal_indicator(strength: u8) -> &'static str {
+  match strength {
+    0..=25 => "▁    ",
+    26..=50 => "▁▃   ",
+    51..=75 => "▁▃▅  ",
+    _ => "▁▃▅▇ ",
+  }
+}
+
+/// Fixed-width quality score so the SSIDs after it stay aligned
+fn quality_column(net: &WifiInfo) -> String {
+  format!("{:>3} ", quality_score(net))
+}
+
+/// The basic details shown under a network in the detail view. `hints` adds the keys for acting on them.
+fn detail_parts(net: &WifiInfo, connected_for: Option<Duration>, config: &Config, hints: bool) -> Vec<String> {
+  let mut detail_parts = vec![];
+
+  // Signal strength percentage
+  if net.raw_strength == net.strength {
+    detail_parts.push(format!("signal: {}%", net.strength));
+  } else {
+    detail_parts.push(format!("signal: {}% (last scan {}%)", net.strength, net.raw_strength));
+  }
+
+  // Frequency and band information
+  if let Some(freq) = net.frequency {
+    let band = Band::from_frequency(freq).map_or("unknown band", Band::label);
+    detail_parts.push(format!("frequency: {} MHz ({})", freq, band));
+    if let Some(channel) = freq_to_channel(freq) {
+      detail_parts.push(format!("channel: {}", channel));
+    }
+  }
+
+  if let Some(bssid) = &net.bssid {
+    match mac_vendor(bssid) {
+      Some(vendor) => detail_parts.push(format!("BSSID: {} ({})", bssid, vendor)),
+      None if is_locally_administered(bssid) => detail_parts.push(format!("BSSID: {} (locally administered)", bssid)),
+      None => detail_parts.push(format!("BSSID: {}", bssid)),
+    }
+  }
+
+  if let Some(bitrate) = net.bitrate {
+    detail_parts.push(format!("link: {:.1} Mb/s", bitrate as f64 / 1000.0));
+  }
+
+  if let Some(duration) = connected_for {
+    let secs = duration.as_secs();
+    detail_parts.push(format!(
+      "connected for {:02}:{:02}:{:02}",
+      secs / 3600,
+      secs / 60 % 60,
+      secs % 60
+    ));
+  }
+
+  // Security with warning if weak
+  let warning = if net.weak_security { " (⚠ insecure)" } else { "" };
+  detail_parts.push(format!("security: {}{}", net.security, warning));
+  if net.supports_tkip && !net.weak_security {
+    detail_parts.push("supports TKIP (weak cipher)".to_string());
+  }
+
+  if config.is_home(&net.ssid) {
+    detail_parts.push("home network".to_string());
+  }
+
+  // Known status
+  if net.known {
+    let hint = if hints {
+      key_hint(&config.keybindings, Action::Forget, "to forget")
+    } else {
+      String::new()
+    };
+    detail_parts.push(format!("known network{}", hint));
+  }
+
+  detail_parts
+}
+
+/// The saved-connection settings shown under a known network in the detail view.
+/// The advanced details of a known network. `guarded_below` is the signal threshold if weefee turned autoconnect off
+/// because the network is too weak.
+fn advanced_parts(net: &WifiInfo, guarded_below: Option<u8>, config: &Config) -> Vec<String> {
+  let mut advanced_parts = vec![];
+  let hint = |action, what| key_hint(&config.keybindings, action, what);
+
+  if let Some(p) = net.priority {
+    advanced_parts.push(format!("priority: {}", p));
+  }
+
+  let autoconnect = match net.autoconnect {
+    Some(false) if let Some(threshold) = guarded_below => format!("off while signal is below {}%", threshold),
+    Some(true) => "on".to_string(),
+    Some(false) => "off".to_string(),
+    None => "default".to_string(),
+  };
+  advanced_parts.push(format!(
+    "auto-connect: {}{}",
+    autoconnect,
+    hint(Action::ToggleAutoconnect, "to toggle")
+  ));
+
+  match net.autoconnect_retries {
+    Some(r) => advanced_parts.push(format!("auto-connect retries: {}", r)),
+    None => advanced_parts.push("auto-connect retries: default".to_string()),
+  }
+
+  if let Some(method) = net.ip4_method {
+    advanced_parts.push(format!(
+      "ipv4: {}{}",
+      method.label(),
+      hint(Action::CycleIp4Method, "to cycle")
+    ));
+  }
+
+  if let Some(privacy) = net.ip6_privacy {
+    advanced_parts.push(format!(
+      "ipv6 privacy: {}{}",
+      privacy.label(),
+      hint(Action::CycleIp6Privacy, "to cycle")
+    ));
+  }
+
+  let powersave = match net.powersave {
+    Some(true) => "on",
+    Some(false) => "off",
+    None => "default",
+  };
+  advanced_parts.push(format!(
+    "power save: {}{}",
+    powersave,
+    hint(Action::TogglePowersave, "to toggle")
+  ));
+
+  if let Some(never_default) = net.never_default {
+    advanced_parts.push(format!(
+      "default route: {}{}",
+      if never_default { "never" } else { "allowed" },
+      hint(Action::ToggleNeverDefault, "to toggle")
+    ));
+  }
+
+  if net.active {
+    if let Some(key) = config.keybindings.first_key(Action::CompareSettings) {
+      advanced_parts.push(format!("{} to compare saved/applied", key));
+    }
+    if let Some(ip4) = &net.active_ip4 {
+      if !ip4.addresses.is_empty() {
+        advanced_parts.push(format!("ip: {}", ip4.addresses.join(", ")));
+      }
+      if let Some(gateway) = &ip4.gateway {
+        advanced_parts.push(format!("gateway: {}", gateway));
+      }
+      if !ip4.dns.is_empty() {
+        advanced_parts.push(format!("dns in use: {}", ip4.dns.join(", ")));
+      }
+    }
+  }
+
+  advanced_parts.push(format!(
+    "firewall zone: {}{}",
+    net.zone.as_deref().unwrap_or("default"),
+    hint(Action::PickZone, "to change")
+  ));
+
+  match net.metered {
+    Some(true) => advanced_parts.push("metered: yes".to_string()),
+    Some(false) => advanced_parts.push("metered: no".to_string()),
+    None => advanced_parts.push("metered: auto".to_string()),
+  }
+
+  // NetworkManager applies these rules to shape traffic, which explains unexpectedly slow connections
+  if !net.traffic_control.is_empty() {
+    advanced_parts.push(format!("traffic control: {}", net.traffic_control.join(", ")));
+  }
+
+  // Tells whether the credentials linger after disconnecting, eg. on public WiFi
+  if let Some(persistence) = net.persistence {
+    advanced_parts.push(format!("profile: {}", persistence.label()));
+  }
+
+  if let Some(uuid) = &net.uuid {
+    advanced_parts.push(format!("uuid: {}", uuid));
+  }
+
+  if let Some(key) = config.keybindings.first_key(Action::ExportProfile) {
+    advanced_parts.push(format!("{} to export", key));
+  }
+
+  advanced_parts
+}
+
+/// Plain-text version of the network list with each network's basic details, for pasting into bug reports and support
+/// threads.
+pub fn network_list_text(networks: &[WifiInfo], config: &Config) -> String {
+  let mut text = String::new();
+  for net in networks {
+    let active_marker = if net.active { "🔗 " } else { "   " };
+    let home_marker = if config.is_home(&net.ssid) { "🏠 " } else { "" };
+    text.push_str(&format!(
+      "{}{}{}{}{}\n",
+      active_marker,
+      signal_indicator(net.strength),
+      quality_column(net),
+      home_marker,
+      net.ssid
+    ));
+    text.push_str(&format!(
+      "          {}\n",
+      detail_parts(net, None, config, false).join(" | ")
+    ));
+  }
+  text
+}
+
+/// Shortcuts listed in the help overlay, as (keys, action). Actions without keys are left out.
+fn key_help(config: &Config) -> Vec<(String, &'static str)> {
+  let keys: &[(&[Action], &'static str)] = &[
+    (&[Action::MoveDown, Action::MoveUp], "Move down/up"),
+    (&[Action::Connect], "Connect / disconnect"),
+    (&[Action::ConnectOnce], "Connect without auto-connect"),
+    (&[Action::ToggleDetails], "Detail view"),
+    (&[Action::ToggleSelection], "Select known network"),
+    (&[Action::Forget], "Forget network(s)"),
+    (&[Action::ForgetNow], "Forget without asking (needs fast_forget)"),
+    (&[Action::UndoForget], "Undo forgetting"),
+    (&[Action::ForgetAll], "Forget all saved networks but the connected one"),
+    (&[Action::ResetPassword], "Re-enter password of connected network"),
+    (&[Action::ToggleAutoconnect], "Toggle auto-connect"),
+    (
+      &[Action::ToggleIgnore, Action::ShowIgnored],
+      "Ignore network / show ignored",
+    ),
+    (
+      &[Action::LowerSignalFilter, Action::RaiseSignalFilter],
+      "Min signal filter",
+    ),
+    (&[Action::BandFilter], "Band filter (all, 2.4, 5, 6 GHz)"),
+    (
+      &[Action::CycleSort],
+      "Cycle sort order (signal, quality, name, band, known)",
+    ),
+    (&[Action::ToggleScanOnly], "Toggle scan-only"),
+    (&[Action::JumpTop, Action::JumpBottom], "Jump to first / last network"),
+    (&[Action::SignalScanner], "Signal scanner"),
+    (&[Action::Refresh], "Refresh now"),
+    (&[Action::ToggleWifi], "Toggle WiFi on/off"),
+    (&[Action::ExportList], "Export list as text"),
+    (&[Action::DeviceInfo], "WiFi adapter capabilities and auto-connect"),
+    (&[Action::SwitchDevice], "Switch WiFi adapter"),
+    (&[Action::Help], "This help"),
+    (&[Action::Quit], "Quit"),
+  ];
+  keys
+    .iter()
+    .filter_map(|(actions, description)| {
+      let labels: Vec<String> = actions
+        .iter()
+        .map(|action| config.keybindings.label(*action))
+        .filter(|label| !label.is_empty())
+        .collect();
+      (!labels.is_empty()).then(|| (labels.join(" / "), *description))
+    })
+    .collect()
+}
+
+fn draw_help(f: &mut Frame, config: &Config, first_run: bool) {
+  use ratatui::text::{Line, Span};
+
+  let mut lines = vec![];
+  if first_run {
+    lines.push(Line::from("Welcome to weefee!"));
+    lines.push(Line::from(""));
+    lines.push(Line::from(
+      "weefee manages your NetworkManager connections, so networks you join or forget here are the same ones your \
+       desktop and nmcli see.",
+    ));
+    lines.push(Line::from(""));
+  }
+  for (key, action) in key_help(config) {
+    lines.push(Line::from(vec![
+      Span::styled(format!("{:>9}  ", key), Style::default().fg(config.theme.accent)),
+      Span::raw(action),
+    ]));
+  }
+  lines.push(Line::from(""));
+  lines.push(Line::from(
+    "The detail view has more keys for editing known networks, see the README.",
+  ));
+
+  let block = Block::default()
+    .title(if first_run { "Getting started" } else { "Help" })
+    .borders(Borders::ALL)
+    .border_type(BorderType::Rounded)
+    .style(Style::default().fg(config.theme.dialog));
+  let area = centered_rect_fixed(64, lines.len() as u16 + 6, f.area());
+  f.render_widget(Clear, area);
+  f.render_widget(block, area);
+
+  let inner_area = Rect {
+    x: area.x + 1,
+    y: area.y + 1,
+    width: area.width.saturating_sub(2),
+    height: area.height.saturating_sub(2),
+  };
+  let layout = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints([
+      Constraint::Min(0),    // Shortcuts
+      Constraint::Length(2), // Blank line + dismiss text
+    ])
+    .split(inner_area);
+
+  let help = Paragraph::new(lines)
+    .style(Style::default().fg(config.theme.text))
+    .wrap(Wrap { trim: true });
+  f.render_widget(help, layout[0]);
+
+  let dismiss_text = Paragraph::new(vec![Line::from(""), Line::from("Press any key to continue")])
+    .style(Style::default().fg(config.theme.dim))
+    .alignment(ratatui::layout::Alignment::Center);
+  f.render_widget(dismiss_text, layout[1]);
+}
 
-  let style = if is_dimmed {
-    Style::default().fg(Color::DarkGray)
+/// What a password field shows: the input itself, or bullets in place of its characters with the cursor on the same
+/// character, so scrolling and cursor placement follow the real password either way.
+fn masked(input: &tui_input::Input, show: bool) -> tui_input::Input {
+  if show {
+    input.clone()
   } else {
-    Style::default().fg(Color::DarkGray)
+    tui_input::Input::new("•".repeat(input.value().chars().count())).with_cursor(input.cursor())
+  }
+}
+
+/// The hint in a password field's bottom border for revealing or masking it again
+fn show_password_hint(showing: bool, theme: &Theme) -> ratatui::text::Line<'static> {
+  ratatui::text::Line::from(if showing { " Ctrl+R: hide " } else { " Ctrl+R: show " })
+    .style(Style::default().fg(theme.dim))
+    .right_aligned()
+}
+
+fn draw_enterprise_login(
+  f: &mut Frame,
+  network: &WifiInfo,
+  method: EapMethod,
+  identity_input: &tui_input::Input,
+  password_input: &tui_input::Input,
+  field: EnterpriseField,
+  show_password: bool,
+  theme: &Theme,
+) {
+  use ratatui::text::{Line, Span};
+
+  let block = Block::default()
+    .title("WPA-Enterprise login")
+    .borders(Borders::ALL)
+    .border_type(BorderType::Rounded)
+    .style(Style::default().fg(theme.dialog));
+  let area = centered_rect_fixed(56, 12, f.area());
+  f.render_widget(Clear, area);
+  f.render_widget(block, area);
+
+  let layout = Layout::default()
+    .direction(Direction::Vertical)
+    .margin(1)
+    .constraints([
+      Constraint::Length(1), // SSID
+      Constraint::Length(1), // EAP method
+      Constraint::Length(1), // Blank line
+      Constraint::Length(3), // Identity
+      Constraint::Length(3), // Password
+      Constraint::Length(1), // Hints
+    ])
+    .split(area);
+
+  let ssid_text = Line::from(vec![
+    Span::raw("Connecting to "),
+    Span::styled(&network.ssid, Style::default().fg(theme.accent)),
+    Span::raw(format!(" ({})", network.security)),
+  ]);
+  f.render_widget(
+    Paragraph::new(ssid_text).style(Style::default().fg(theme.text)),
+    layout[0],
+  );
+  f.render_widget(
+    Paragraph::new(format!("EAP method: {} (Ctrl+T to change)", method.label())).style(Style::default().fg(theme.text)),
+    layout[1],
+  );
+
+  let shown_password = masked(password_input, show_password);
+  for (input, title, this_field, area, hint) in [
+    (
+      identity_input,
+      "Identity (username)",
+      EnterpriseField::Identity,
+      layout[3],
+      None,
+    ),
+    (
+      &shown_password,
+      "Password",
+      EnterpriseField::Password,
+      layout[4],
+      Some(show_password_hint(show_password, theme)),
+    ),
+  ] {
+    let focused = field == this_field;
+    let mut input_block = Block::default().title(title);
+    if let Some(hint) = hint {
+      input_block = input_block.title_bottom(hint);
+    }
+    let input_block = input_block
+      .borders(Borders::ALL)
+      .border_type(BorderType::Rounded)
+      .style(Style::default().fg(if focused { theme.accent } else { theme.dim }));
+    let inner_area = input_block.inner(area);
+    f.render_widget(input_block, area);
+
+    let scroll = input.visual_scroll(inner_area.width as usize);
+    let input_widget = Paragraph::new(input.value())
+      .style(Style::default().fg(theme.accent))
+      .scroll((0, scroll as u16));
+    f.render_widget(input_widget, inner_area);
+
+    if focused {
+      f.set_cursor_position((
+        inner_area.x + (input.visual_cursor().max(scroll) - scroll) as u16,
+        inner_area.y,
+      ));
+    }
+  }
+
+  let hints = Paragraph::new("Tab: next field · Enter: connect · Esc: cancel")
+    .style(Style::default().fg(theme.dim))
+    .alignment(ratatui::layout::Alignment::Center);
+  f.render_widget(hints, layout[5]);
+}
+
+fn draw_device_info(f: &mut Frame, device_info: &Option<WifiDeviceInfo>, theme: &Theme) {
+  use ratatui::text::{Line, Span};
+
+  let mut lines = vec![];
+  if let Some(enabled) = device_info.as_ref().and_then(|info| info.device_autoconnect) {
+    lines.push(Line::from(format!(
+      "Device auto-connect: {} (a to toggle)",
+      if enabled {
+        "on"
+      } else {
+        "off, no network connects automatically"
+      }
+    )));
+    lines.push(Line::from(""));
+  }
+  if let Some(info) = device_info
+    && let Some(randomized) = info.mac_randomized()
+  {
+    let current = info.hw_address.as_deref().unwrap_or_default();
+    lines.push(Line::from(format!(
+      "MAC address: {} ({})",
+      current,
+      if randomized { "randomized" } else { "permanent" }
+    )));
+    if randomized {
+      lines.push(Line::from(format!(
+        "Permanent MAC: {}",
+        info.permanent_hw_address.as_deref().unwrap_or_default()
+      )));
+    }
+    lines.push(Line::from(""));
+  }
+  lines.push(Line::from(
+    "What this WiFi adapter supports, according to NetworkManager:",
+  ));
+  lines.push(Line::from(""));
+  match device_info.as_ref().and_then(|info| info.capabilities) {
+    Some(capabilities) => {
+      for (feature, supported) in capabilities.features() {
+        lines.push(Line::from(vec![
+          if supported {
+            Span::styled("  ✓ ", Style::default().fg(theme.success))
+          } else {
+            Span::styled("  ✗ ", Style::default().fg(theme.error))
+          },
+          Span::raw(feature),
+        ]));
+      }
+    }
+    None => lines.push(Line::from("NetworkManager didn't report the adapter's capabilities.")),
+  }
+
+  let block = Block::default()
+    .title("WiFi Adapter")
+    .borders(Borders::ALL)
+    .border_type(BorderType::Rounded)
+    .style(Style::default().fg(theme.dialog));
+  let area = centered_rect_fixed(64, lines.len() as u16 + 4, f.area());
+  f.render_widget(Clear, area);
+  f.render_widget(block, area);
+
+  let inner_area = Rect {
+    x: area.x + 1,
+    y: area.y + 1,
+    width: area.width.saturating_sub(2),
+    height: area.height.saturating_sub(2),
   };
+  let layout = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints([
+      Constraint::Min(0),    // Capabilities
+      Constraint::Length(2), // Blank line + dismiss text
+    ])
+    .split(inner_area);
+
+  let capabilities = Paragraph::new(lines).style(Style::default().fg(theme.text));
+  f.render_widget(capabilities, layout[0]);
+
+  let dismiss_text = Paragraph::new(vec![Line::from(""), Line::from("Press any key to continue")])
+    .style(Style::default().fg(theme.dim))
+    .alignment(ratatui::layout::Alignment::Center);
+  f.render_widget(dismiss_text, layout[1]);
+}
+
+/// A hint like " (p to toggle)" naming the first key of `action`, or nothing if it has no keys
+fn key_hint(keys: &KeyMap, action: Action, what: &str) -> String {
+  keys
+    .first_key(action)
+    .map_or(String::new(), |key| format!(" ({} {})", key, what))
+}
+
+/// A short-lived message in place of the footer's shortcuts
+fn draw_status(f: &mut Frame, area: Rect, message: &str, theme: &Theme) {
+  let status = Paragraph::new(message).style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
+  f.render_widget(status, area);
+}
 
-  let shortcuts = Span::styled("↑/↓: Navigate | Enter to dis/connect | D: Details | Q: Quit", style);
+/// `shown` is how many networks are listed out of how many match the filters
+fn draw_footer(f: &mut Frame, area: Rect, filter: &ListFilter, config: &Config, shown: (usize, usize)) {
+  use ratatui::text::{Line, Span};
+
+  let style = Style::default().fg(config.theme.dim);
+
+  let key = |action| config.keybindings.first_key(action);
+  let navigate = key(Action::MoveUp)
+    .zip(key(Action::MoveDown))
+    .map(|(up, down)| format!("{}/{}", up, down));
+  let signal_filter = key(Action::LowerSignalFilter)
+    .zip(key(Action::RaiseSignalFilter))
+    .map(|(lower, raise)| format!("{}/{}", lower, raise));
+  let shortcuts = [
+    navigate.map(|keys| format!("{}: Navigate", keys)),
+    key(Action::Connect).map(|key| format!("{} to dis/connect", key)),
+    key(Action::ToggleSelection).map(|key| format!("{}: Select", key)),
+    key(Action::Forget).map(|key| format!("{}: Forget", key)),
+    key(Action::ToggleIgnore).map(|key| format!("{}: Ignore", key)),
+    key(Action::ToggleDetails).map(|key| format!("{}: Details", key)),
+    key(Action::Help).map(|key| format!("{}: Help", key)),
+    key(Action::Quit).map(|key| format!("{}: Quit", key)),
+    signal_filter.map(|keys| format!("{}: Min signal {}%", keys, filter.min_signal)),
+    key(Action::BandFilter).map(|key| format!("{}: {}", key, filter.band.map_or("All bands", Band::label))),
+    key(Action::CycleSort).map(|key| format!("{}: Sorted by {}", key, filter.sort_mode.label())),
+  ];
+
+  let mut spans = vec![Span::styled(
+    shortcuts.into_iter().flatten().collect::<Vec<_>>().join(" | "),
+    style,
+  )];
+  if shown.0 < shown.1 {
+    spans.push(Span::styled(format!(" | showing {} of {}", shown.0, shown.1), style));
+  }
 
-  let footer = Paragraph::new(shortcuts);
+  let footer = Paragraph::new(Line::from(spans));
   f.render_widget(footer, area);
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sparkline_maps_strength_to_block_heights() {
+    assert_eq!(signal_sparkline(&VecDeque::from([0, 100])), "▁█");
+    assert_eq!(signal_sparkline(&VecDeque::from([50, 7, 8, 255])), "▅▁▂█");
+
+    // Only the most recent samples fit
+    let samples: VecDeque<u8> = std::iter::repeat_n(0, 5).chain(std::iter::repeat_n(100, 30)).collect();
+    assert_eq!(signal_sparkline(&samples), "█".repeat(DETAIL_SPARKLINE_SAMPLES));
+  }
+}